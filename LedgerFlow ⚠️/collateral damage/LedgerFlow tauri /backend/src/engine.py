@@ -13,11 +13,15 @@ import datetime
 import math
 import re
 import uuid
-from typing import List, Dict, Optional, Literal, Any
-from dataclasses import dataclass
+from typing import List, Dict, Optional, Literal, Any, Tuple, get_args
+from dataclasses import dataclass, fields
 from datetime import date, datetime, timedelta
 import argparse
+import gzip
+import importlib
+import importlib.metadata
 import json
+import platform
 import sys
 
 
@@ -31,7 +35,7 @@ class SimulationConfig:
     max_items: int
     min_invoice_amount: float
     max_invoice_amount: float
-    item_filter_mode: Literal['all', 'selected']
+    item_filter_mode: Literal['all', 'include', 'exclude', 'selected']  # 'selected' is the old name for 'include'
     selected_items: List[str]
     name_type: Literal[
         'malabar_muslim', 'south_indian_generic', 
@@ -39,11 +43,29 @@ class SimulationConfig:
     ]
     realism_mode: Literal['random', 'pareto', 'equal']
     seed: Optional[int] = None
-    invoice_count_mode: Optional[str] = 'auto'  # 'auto' or 'manual'
+    invoice_count_mode: Optional[str] = 'auto'  # 'auto', 'manual' or 'target_revenue'
     manual_invoice_count: Optional[int] = None
-    reality_buffer: Optional[float] = 0.0  # Percentage (0-50)
-    distribution_mode: Optional[str] = 'even'  # 'even', 'weighted', 'burst'
-    customer_repeat_rate: Optional[float] = 0.0  # Percentage (0-100)
+    reality_buffer: Optional[float] = 0.1  # Fraction (0-1) manual-mode amounts may vary by
+    distribution_mode: Optional[str] = 'uniform'  # One of DISTRIBUTION_MODES
+    customer_repeat_rate: Optional[float] = 0.0  # Fraction (0-1) of invoices to returning customers
+    # Set when extending an earlier run: where numbering continues and who its customers were
+    invoice_number_start: Optional[int] = None
+    existing_customers: Optional[List[str]] = None
+
+ENGINE_VERSION = "1.1.0"
+
+# Modules a run needs that a stripped-down Python can lack (gzip needs zlib)
+REQUIRED_MODULES = ['dataclasses', 'gzip', 'json', 'uuid', 'zlib']
+
+# Modes handled by _distribute_invoice_dates; anything else falls back to 'uniform'
+DISTRIBUTION_MODES = ['uniform', 'front_loaded', 'back_loaded', 'weekday_only', 'seasonal']
+
+# Names older frontends send for the same spreads
+DISTRIBUTION_MODE_ALIASES = {'even': 'uniform', 'weighted': 'back_loaded', 'burst': 'front_loaded'}
+
+# front_loaded puts this share of invoices in the first FRONT_LOADED_SPAN of the range
+FRONT_LOADED_SHARE = 0.8
+FRONT_LOADED_SPAN = 0.2
 
 # GST rates based on item categories
 GST_RATES = {
@@ -83,9 +105,12 @@ class InvoiceSimulator:
             random.seed(config.seed)
             
         # Filter catalog if needed
-        if config.item_filter_mode == 'selected' and config.selected_items:
+        if config.item_filter_mode in ('include', 'selected') and config.selected_items:
             self.catalog = [item for item in catalog 
                            if item['sku'] in config.selected_items or item['name'] in config.selected_items]
+        elif config.item_filter_mode == 'exclude' and config.selected_items:
+            self.catalog = [item for item in catalog
+                           if item['sku'] not in config.selected_items and item['name'] not in config.selected_items]
         
         # Generate customer pool based on name_type
         self.customers = self._generate_customer_pool()
@@ -93,8 +118,11 @@ class InvoiceSimulator:
         # Calculate simulation parameters
         self.date_range = (config.end_date - config.start_date).days + 1
         
-        # Initialize invoice number counter
-        self.invoice_counter = random.randint(1000, 9999)
+        # Initialize invoice number counter (incremented before each use)
+        if config.invoice_number_start is not None:
+            self.invoice_counter = config.invoice_number_start - 1
+        else:
+            self.invoice_counter = random.randint(1000, 9999)
         
     def simulate(self) -> List[Dict]:
         """
@@ -124,14 +152,21 @@ class InvoiceSimulator:
         # Generate invoices
         invoices = []
         remaining_revenue = self.config.revenue_target
+        last_reported = -1
         
         for i in range(invoice_count):
+            # Report progress to the Rust shell whenever the whole percentage changes
+            percent = (i * 100) // invoice_count
+            if percent != last_reported:
+                print(f"PROGRESS: {percent}", flush=True)
+                last_reported = percent
+
             # For manual mode, distribute revenue evenly across invoices with reality buffer
             if self.config.invoice_count_mode == 'manual':
                 base_amount = self.config.revenue_target / invoice_count
                 
                 # Apply reality buffer for variation
-                reality_buffer = (self.config.reality_buffer or 0) / 100
+                reality_buffer = self.config.reality_buffer or 0
                 if reality_buffer > 0:
                     variation = 1 + random.uniform(-reality_buffer, reality_buffer)
                     target_amount = base_amount * variation
@@ -153,7 +188,7 @@ class InvoiceSimulator:
             invoices.append(invoice)
 
         # --- PATCH: Scale all invoice totals to match revenue target exactly in auto mode ---
-        if self.config.invoice_count_mode == 'auto':
+        if self.config.invoice_count_mode in ('auto', 'target_revenue'):
             total_generated = sum(inv['total'] for inv in invoices)
             if total_generated > 0:
                 scale = self.config.revenue_target / total_generated
@@ -179,6 +214,7 @@ class InvoiceSimulator:
             if last_inv['invoice_type'] == 'Plain':
                 new_total = last_inv['total'] + diff
                 if self.config.min_invoice_amount <= new_total <= self.config.max_invoice_amount:
+                    self._rescale_items(last_inv, round(new_total, 2))
                     last_inv['subtotal'] = round(new_total, 2)
                     last_inv['total'] = round(new_total, 2)
                     adj_successful = True
@@ -190,6 +226,7 @@ class InvoiceSimulator:
                 if self.config.min_invoice_amount <= new_total <= self.config.max_invoice_amount:
                     cgst = round(new_subtotal * 0.09, 2)
                     sgst = round(new_subtotal * 0.09, 2)
+                    self._rescale_items(last_inv, new_subtotal)
                     last_inv['subtotal'] = new_subtotal
                     last_inv['tax_breakup']['CGST'] = cgst
                     last_inv['tax_breakup']['SGST'] = sgst
@@ -201,6 +238,7 @@ class InvoiceSimulator:
                 new_subtotal = round(new_total / 1.10, 2)
                 if self.config.min_invoice_amount <= new_total <= self.config.max_invoice_amount:
                     vat_amt = round(new_subtotal * 0.10, 2)
+                    self._rescale_items(last_inv, new_subtotal)
                     last_inv['subtotal'] = new_subtotal
                     last_inv['VAT'] = vat_amt
                     last_inv['total'] = round(new_subtotal + vat_amt, 2)
@@ -212,6 +250,11 @@ class InvoiceSimulator:
                 adj_total = max(self.config.min_invoice_amount, min(adj_total, self.config.max_invoice_amount))
                 adj_subtotal = adj_total  # Plain invoice no tax
                 adj_date = self.config.end_date
+                mode = self.config.distribution_mode
+                if DISTRIBUTION_MODE_ALIASES.get(mode, mode) == 'weekday_only':
+                    # Validation guarantees the range has a weekday to fall back to
+                    while adj_date.weekday() >= 5 and adj_date > self.config.start_date:
+                        adj_date -= timedelta(days=1)
                 self.invoice_counter += 1
                 adj_invoice_id = f"ADJ/{adj_date.year}/{self.invoice_counter:05d}"
                 invoices.append({
@@ -229,9 +272,26 @@ class InvoiceSimulator:
                     "total": adj_total,
                     "template_applied": "plain_template"
                 })
+                # Shown to the user by the Rust shell, which collects WARNING: lines
+                print(f"WARNING: Added adjustment invoice {adj_invoice_id} for {diff:.2f} "
+                      f"to reach the revenue target", file=sys.stderr)
         
         return invoices
     
+    def _rescale_items(self, invoice: Dict, new_subtotal: float) -> None:
+        """Scale an invoice's line items so their amounts still add up to a new subtotal."""
+        items = invoice['items']
+        old_subtotal = sum(item['amount'] for item in items)
+        if not items or old_subtotal <= 0:
+            return
+        scale = new_subtotal / old_subtotal
+        for item in items:
+            item['amount'] = round(item['amount'] * scale, 2)
+            item['rate'] = round(item['rate'] * scale, 2)
+        # Rounding each line can leave a cent or two over, which goes on the largest line
+        largest = max(items, key=lambda item: item['amount'])
+        largest['amount'] = round(largest['amount'] + new_subtotal - sum(item['amount'] for item in items), 2)
+
     def _generate_invoice(self, invoice_date: datetime, customer_name: str, 
                          target_amount: Optional[float] = None) -> Dict:
         """
@@ -385,6 +445,7 @@ class InvoiceSimulator:
                 },
                 "items": [
                     {
+                        "sku": item['sku'],
                         "item": item['name'],
                         "qty": item['qty'],
                         "rate": item['rate'],
@@ -431,6 +492,7 @@ class InvoiceSimulator:
                 },
                 "items": [
                     {
+                        "sku": item['sku'],
                         "item": item['name'],
                         "qty": item['qty'],
                         "rate": item['rate'],
@@ -455,6 +517,7 @@ class InvoiceSimulator:
                 "customer": {"name": customer_name},
                 "items": [
                     {
+                        "sku": item['sku'],
                         "item": item['name'],
                         "qty": item['qty'],
                         "rate": item['rate'],
@@ -554,6 +617,9 @@ class InvoiceSimulator:
         """
         Distribute invoice dates across the date range based on distribution_mode.
         
+        'uniform' spaces invoices evenly; every other mode draws each invoice's day
+        from _day_weights, which the Rust shell mirrors for its distribution preview.
+        
         Args:
             count: Number of invoices to distribute
             
@@ -563,50 +629,21 @@ class InvoiceSimulator:
         start = datetime.combine(self.config.start_date, datetime.min.time())
         end = datetime.combine(self.config.end_date, datetime.min.time())
         
-        distribution_mode = self.config.distribution_mode or 'even'
+        mode = self.config.distribution_mode or 'uniform'
+        mode = DISTRIBUTION_MODE_ALIASES.get(mode, mode)
         
-        if distribution_mode == 'even':
-            # Evenly distribute across date range
-            if count > 1:
-                interval = (end - start) / (count - 1)
-                dates = [start + interval * i for i in range(count)]
-            else:
-                dates = [start + (end - start) / 2]  # Middle of range for single invoice
-                
-        elif distribution_mode == 'burst':
-            # Concentrate invoices in first 20% of time period
-            burst_split = int(count * 0.8)
-            burst_date_range = start + timedelta(days=int(self.date_range * 0.2))
-            
-            # Generate dates in both segments
-            early_dates = [
-                start + (burst_date_range - start) * random.random()
-                for _ in range(burst_split)
-            ]
-            
-            late_dates = [
-                burst_date_range + (end - burst_date_range) * random.random()
-                for _ in range(count - burst_split)
-            ]
-            
-            dates = early_dates + late_dates
-            
-        elif distribution_mode == 'weighted':
-            # More invoices toward end of period (weighted distribution)
-            dates = []
-            for _ in range(count):
-                # Use power function to skew toward end of period
-                random_factor = random.random() ** 0.5  # Square root for moderate skew
-                date_offset = (end - start) * random_factor
-                dates.append(start + date_offset)
-            
-        else:  # Default to even distribution for unknown modes
-            # Even distribution as fallback
-            if count > 1:
-                interval = (end - start) / (count - 1)
-                dates = [start + interval * i for i in range(count)]
-            else:
-                dates = [start + (end - start) / 2]
+        if mode in DISTRIBUTION_MODES and mode != 'uniform':
+            days = [start + timedelta(days=i) for i in range(self.date_range)]
+            weights = _day_weights(mode, [day.date() for day in days])
+            if not any(weights):
+                raise ValueError(f"distribution_mode '{mode}' leaves no days in the date range")
+            dates = random.choices(days, weights=weights, k=count)
+        elif count > 1:
+            # Evenly distribute across date range (also the fallback for unknown modes)
+            interval = (end - start) / (count - 1)
+            dates = [start + interval * i for i in range(count)]
+        else:
+            dates = [start + (end - start) / 2]  # Middle of range for single invoice
         
         # Sort dates chronologically
         dates.sort()
@@ -628,12 +665,25 @@ class InvoiceSimulator:
         Returns:
             List of customer names
         """
-        customers = []
+        return self._unique_customer_names(pool_size, [])
+    
+    def _unique_customer_names(self, count: int, taken: List[str]) -> List[str]:
+        """
+        Generate up to count customer names that differ from each other and from taken.
         
-        for _ in range(pool_size):
-            customers.append(self._generate_customer_name(self.config.name_type))
-            
-        return customers
+        Name generators draw from fixed word lists, so a small name_type can run out;
+        generation stops after a bounded number of collisions and returns what it has.
+        """
+        names = []
+        seen = set(taken)
+        attempts = 0
+        while len(names) < count and attempts < count * 20:
+            attempts += 1
+            name = self._generate_customer_name(self.config.name_type)
+            if name not in seen:
+                seen.add(name)
+                names.append(name)
+        return names
     
     def _generate_customer_name(self, name_type: str) -> str:
         """
@@ -771,17 +821,24 @@ class InvoiceSimulator:
         Returns:
             List of customer names for each invoice
         """
-        customer_repeat_rate = (self.config.customer_repeat_rate or 0) / 100
+        customer_repeat_rate = self.config.customer_repeat_rate or 0
+        existing = self.config.existing_customers or []
         
         if customer_repeat_rate == 0:
-            # No repeat customers - use different customer for each invoice
+            # No repeat customers - use different customer for each invoice,
+            # avoiding the earlier run's customers when extending one
+            fresh = [c for c in self.customers if c not in existing]
+            if len(fresh) < count:
+                # More invoices than the pool holds: top it up so nobody repeats
+                fresh += self._unique_customer_names(count - len(fresh), self.customers + existing)
+            fresh = fresh or self.customers
             result = []
             for i in range(count):
                 # Cycle through customers if we have more invoices than customers
-                result.append(self.customers[i % len(self.customers)])
+                result.append(fresh[i % len(fresh)])
             return result
             
-        elif customer_repeat_rate >= 80:
+        elif customer_repeat_rate >= 0.8:
             # High repeat rate - 80% of invoices go to 20% of customers
             key_customer_count = max(1, len(self.customers) // 5)
             key_customers = random.sample(self.customers, key_customer_count)
@@ -803,8 +860,9 @@ class InvoiceSimulator:
             return key_assignments + regular_assignments
             
         else:
-            # Medium repeat rate - use probability-based assignment
-            used_customers = []
+            # Medium repeat rate - use probability-based assignment; an extended
+            # run's customers count as already seen so they can repeat too
+            used_customers = list(dict.fromkeys(existing))
             result = []
             
             for _ in range(count):
@@ -822,6 +880,26 @@ class InvoiceSimulator:
             return result
 
 
+def _day_weights(mode: str, days: List[date]) -> List[float]:
+    """Relative chance of an invoice landing on each day for a non-uniform mode."""
+    n = len(days)
+    if mode == 'front_loaded':
+        cutoff = max(1, int(n * FRONT_LOADED_SPAN))
+        if cutoff >= n:
+            return [1.0] * n
+        early = FRONT_LOADED_SHARE / cutoff
+        late = (1 - FRONT_LOADED_SHARE) / (n - cutoff)
+        return [early if i < cutoff else late for i in range(n)]
+    if mode == 'back_loaded':
+        # Rises linearly across the range
+        return [(i + 0.5) / n for i in range(n)]
+    if mode == 'weekday_only':
+        return [1.0 if day.weekday() < 5 else 0.0 for day in days]
+    if mode == 'seasonal':
+        # Peaks in December and bottoms out in June
+        return [1 + 0.5 * math.cos(2 * math.pi * (day.month - 12) / 12) for day in days]
+    return [1.0] * n
+
 def simulate_invoices(config: SimulationConfig, catalog: List[Dict]) -> List[Dict]:
     """
     Main entry point for invoice simulation.
@@ -844,27 +922,49 @@ def main():
     """
     parser = argparse.ArgumentParser(description="LedgerFlow Simulation Engine")
     
-    # Arguments for receiving JSON strings directly
-    parser.add_argument("--config_json", type=str, required=True, help="JSON string of the simulation configuration")
-    parser.add_argument("--catalog_json", type=str, required=True, help="JSON string of the item catalog")
-    
-    # Deprecated file-based arguments (for backward compatibility if needed)
-    parser.add_argument("--config", type=str, help="Path to the configuration JSON file (deprecated)")
-    parser.add_argument("--catalog", type=str, help="Path to the catalog JSON file (deprecated)")
-    parser.add_argument("--output", type=str, help="Path to the output JSON file (deprecated)")
+    # Inputs either as JSON strings or as paths to JSON files
+    parser.add_argument("--config_json", type=str, help="JSON string of the simulation configuration")
+    parser.add_argument("--catalog_json", type=str, help="JSON string of the item catalog")
+    parser.add_argument("--config", type=str, help="Path to the configuration JSON file")
+    parser.add_argument("--catalog", type=str, help="Path to the catalog JSON file")
+    parser.add_argument("--stdin", action="store_true",
+                        help="Read the config and catalog JSON from stdin, one document per line")
+
+    # When set the result JSON is written here instead of stdout, gzipped for a .gz path
+    parser.add_argument("--output", type=str, help="Path to the output JSON file")
+    parser.add_argument("--capabilities", action="store_true",
+                        help="Print the supported option values as JSON and exit")
+    parser.add_argument("--selfcheck", action="store_true",
+                        help="Print the engine and Python versions and module availability as JSON and exit")
+    parser.add_argument("--check-deps", action="store_true",
+                        help="Print each required package's import status and version as JSON and exit")
 
     args = parser.parse_args()
 
+    if args.capabilities:
+        print(json.dumps(_capabilities()))
+        return
+    if args.selfcheck:
+        print(json.dumps(_selfcheck()))
+        return
+    if args.check_deps:
+        print(json.dumps(_check_deps()))
+        return
+
     try:
-        # Load from JSON strings first
-        config_data = json.loads(args.config_json)
-        catalog_data = json.loads(args.catalog_json)
+        if args.stdin:
+            config_data, catalog_data = _read_stdin_inputs()
+        else:
+            config_data = _load_json_arg(args.config_json, args.config, "config")
+            catalog_data = _load_json_arg(args.catalog_json, args.catalog, "catalog")
         
         # Convert date strings to date objects
         config_data['start_date'] = datetime.strptime(config_data['start_date'], '%Y-%m-%d').date()
         config_data['end_date'] = datetime.strptime(config_data['end_date'], '%Y-%m-%d').date()
 
-        config = SimulationConfig(**config_data)
+        # Ignore keys only the Rust side uses (timeouts etc.)
+        known = {f.name for f in fields(SimulationConfig)}
+        config = SimulationConfig(**{k: v for k, v in config_data.items() if k in known})
         
         # Run simulation
         simulator = InvoiceSimulator(config, catalog_data)
@@ -874,7 +974,8 @@ def main():
         result = {
             "status": "success",
             "invoices": invoices,
-            "error": None
+            "error": None,
+            "engine_version": ENGINE_VERSION
         }
         
     except Exception as e:
@@ -887,8 +988,76 @@ def main():
         # Print error to stderr for logging in Rust
         print(f"Error in simulation engine: {e}", file=sys.stderr)
         
-    # Print the final result to stdout
-    print(json.dumps(result, indent=4))
+    print("PROGRESS: 100", flush=True)
+    if args.output:
+        opener = gzip.open if args.output.endswith(".gz") else open
+        with opener(args.output, "wt", encoding="utf-8") as f:
+            json.dump(result, f)
+    else:
+        # Print the final result to stdout after the sentinel the Rust shell looks for
+        print("RESULT:")
+        print(json.dumps(result, indent=4))
+
+
+def _capabilities() -> Dict[str, List[str]]:
+    """The values this engine accepts for each enumerated config option."""
+    types = {f.name: f.type for f in fields(SimulationConfig)}
+    return {
+        "invoice_types": list(get_args(types['invoice_type'])),
+        "realism_modes": list(get_args(types['realism_mode'])),
+        "name_types": list(get_args(types['name_type'])),
+        "distribution_modes": DISTRIBUTION_MODES,
+    }
+
+def _selfcheck() -> Dict[str, Any]:
+    """Versions and whether each required module imports, without running a simulation."""
+    modules = {}
+    for name in REQUIRED_MODULES:
+        try:
+            importlib.import_module(name)
+            modules[name] = True
+        except ImportError:
+            modules[name] = False
+    return {
+        "engine_version": ENGINE_VERSION,
+        "python_version": platform.python_version(),
+        "modules": modules,
+    }
+
+def _check_deps() -> Dict[str, List[Dict[str, Any]]]:
+    """Whether each required module imports, and its version where it reports one."""
+    dependencies = []
+    for name in REQUIRED_MODULES:
+        try:
+            module = importlib.import_module(name)
+        except ImportError:
+            dependencies.append({"name": name, "installed": False, "version": None})
+            continue
+        version = getattr(module, "__version__", None)
+        if version is None:
+            try:
+                version = importlib.metadata.version(name)
+            except Exception:
+                # Standard library modules have no distribution of their own
+                version = None
+        dependencies.append({"name": name, "installed": True, "version": version})
+    return {"dependencies": dependencies}
+
+def _load_json_arg(inline: Optional[str], path: Optional[str], name: str) -> Any:
+    """Load a JSON input given either inline or as a file path."""
+    if inline is not None:
+        return json.loads(inline)
+    if path is not None:
+        with open(path, "r", encoding="utf-8") as f:
+            return json.load(f)
+    raise ValueError(f"No {name} provided (use --{name}_json or --{name})")
+
+def _read_stdin_inputs() -> Tuple[Any, Any]:
+    """Read the config and catalog from stdin, sent as two newline-delimited JSON documents."""
+    lines = [line for line in sys.stdin.read().splitlines() if line.strip()]
+    if len(lines) != 2:
+        raise ValueError(f"Expected config and catalog JSON on stdin, got {len(lines)} line(s)")
+    return json.loads(lines[0]), json.loads(lines[1])
 
 if __name__ == "__main__":
     main() 
\ No newline at end of file
@@ -1,131 +1,217 @@
 // Prevents additional console window on Windows in release, DO NOT REMOVE!!
 #![cfg_attr(not(debug_assertions), windows_subsystem = "windows")]
 
-use serde::{Deserialize, Serialize};
-use std::process::Command;
-use std::path::Path;
+// Only the catalog conversion is used, not the display formatting
+#[allow(dead_code)]
+#[path = "../../src-tauri/src/currency.rs"]
+mod currency;
+#[path = "../../src-tauri/src/engine.rs"]
+mod engine;
+// Results aren't saved here, so whether one was compressed never comes up
+#[allow(dead_code)]
+#[path = "../../src-tauri/src/engine_output.rs"]
+mod engine_output;
+#[path = "../../src-tauri/src/engine_path.rs"]
+mod engine_path;
+// Only the variants raised by the shared engine runner are used here
+#[allow(dead_code)]
+#[path = "../../src-tauri/src/error.rs"]
+mod error;
+// This build only uses part of the shared model API
+#[allow(dead_code)]
+#[path = "../../src-tauri/src/models.rs"]
+mod models;
+#[path = "../../src-tauri/src/output_schema.rs"]
+mod output_schema;
+#[path = "../../src-tauri/src/python.rs"]
+mod python;
+// Only the rounding itself is used, not the formatting for exports
+#[allow(dead_code)]
+#[path = "../../src-tauri/src/rounding.rs"]
+mod rounding;
+mod run_files;
+#[path = "../../src-tauri/src/verify.rs"]
+mod verify;
+
+use engine::{ActiveChildren, RunLock, RunOptions};
+use models::{CatalogItem, SimulationConfig, SimulationResult};
+use python::PythonConfig;
+use rounding::RoundingMode;
+use run_files::RunFiles;
+use std::path::PathBuf;
 use std::fs;
+use std::sync::Mutex;
+use std::time::Duration;
+use tauri::path::BaseDirectory;
+use tauri::Manager;
 
-#[derive(Debug, Serialize, Deserialize)]
-struct SimulationConfig {
-    revenue_target: f64,
-    start_date: String,
-    end_date: String,
-    invoice_type: String,
-    min_items: i32,
-    max_items: i32,
-    min_invoice_amount: f64,
-    max_invoice_amount: f64,
-    item_filter_mode: String,
-    selected_items: Vec<String>,
-    name_type: String,
-    realism_mode: String,
-    seed: Option<i32>,
-    invoice_count_mode: Option<String>,
-    manual_invoice_count: Option<i32>,
-    reality_buffer: Option<f64>,         // Added for reality buffer
-    distribution_mode: Option<String>,   // Added for invoice distribution
-    customer_repeat_rate: Option<f64>,   // Added for customer repeat rate
+#[tauri::command]
+fn set_python_path(path: String, python: tauri::State<'_, Mutex<PythonConfig>>) -> Result<(), String> {
+    let mut python = python
+        .lock()
+        .map_err(|_| "Python config lock poisoned".to_string())?;
+    let path = path.trim();
+    // An empty path switches back to auto-detection
+    python.path = if path.is_empty() { None } else { Some(path.to_string()) };
+    Ok(())
 }
 
-#[derive(Debug, Serialize, Deserialize)]
-struct SimulationResult {
-    invoices: Vec<Invoice>,
-    status: String,
-    error: Option<String>,
+#[tauri::command]
+fn cancel_simulation(active: tauri::State<'_, ActiveChildren>) -> Result<bool, String> {
+    let cancelled = engine::cancel(&active);
+    Ok(cancelled)
 }
 
-#[derive(Debug, Serialize, Deserialize)]
-struct Invoice {
-    invoice_id: String,
-    date: String,
-    customer_name: String,
-    items: Vec<InvoiceItem>,
-    tax_breakdown: serde_json::Value,
-    grand_total: f64,
+// Bundled resource first, then $LEDGERFLOW_ENGINE, then the development checkout
+fn engine_script(app: &tauri::AppHandle) -> Result<PathBuf, String> {
+    let resource = app
+        .path()
+        .resolve(engine_path::RESOURCE_PATH, BaseDirectory::Resource)
+        .ok();
+    engine_path::resolve(resource, std::env::var_os(engine_path::ENGINE_ENV))
 }
 
-#[derive(Debug, Serialize, Deserialize)]
-struct InvoiceItem {
-    sku: String,
-    name: String,
-    qty: i32,
-    rate: f64,
-    tax: f64,
-    total: f64,
-}
+#[tauri::command]
+async fn run_simulation(
+    mut config: SimulationConfig,
+    catalog: Vec<CatalogItem>,
+    app: tauri::AppHandle,
+    python: tauri::State<'_, Mutex<PythonConfig>>,
+    active: tauri::State<'_, ActiveChildren>,
+    run_lock: tauri::State<'_, RunLock>,
+) -> Result<SimulationResult, String> {
+    let Some(_guard) = run_lock.try_acquire() else {
+        return Err("A simulation is already running".to_string());
+    };
+    // Always hand the engine an explicit seed so the run can be reproduced
+    let seed = *config
+        .seed
+        .get_or_insert_with(|| (rand::random::<u32>() >> 1) as i32);
 
-#[derive(Debug, Serialize, Deserialize)]
-struct CatalogItem {
-    sku: String,
-    name: String,
-    price: f64,
-    gst_percent: Option<f64>,
-    vat_percent: Option<f64>,
-    category: Option<String>,
+    let mut files = RunFiles::unique(&std::env::temp_dir());
+    if let Some(path) = &config.output_path {
+        let path = PathBuf::from(path);
+        engine::check_output_path(&path)?;
+        files = files.with_output(path);
+    }
+    let result = simulate(&config, &catalog, &files, &app, &python, &active, seed).await;
+    files.finish(result, config.keep_temp_on_error)
 }
 
-#[tauri::command]
-async fn run_simulation(config: SimulationConfig, catalog: Vec<CatalogItem>) -> Result<SimulationResult, String> {
+async fn simulate(
+    config: &SimulationConfig,
+    catalog: &[CatalogItem],
+    files: &RunFiles,
+    app: &tauri::AppHandle,
+    python: &Mutex<PythonConfig>,
+    active: &ActiveChildren,
+    seed: i32,
+) -> Result<SimulationResult, String> {
+    // The only config check in this build besides output_path, since these go straight
+    // onto the command line
+    engine::check_extra_args(config.extra_engine_args.as_deref().unwrap_or_default())?;
+
     // Convert the config and catalog to JSON strings
-    let config_json = serde_json::to_string(&config)
+    let config_json = serde_json::to_string(config)
         .map_err(|e| format!("Failed to serialize config: {}", e))?;
     
-    let catalog_json = serde_json::to_string(&catalog)
+    // Prices in other currencies are converted so the engine only ever sums one
+    let converted = currency::base_catalog(config, catalog)?;
+    let catalog_json = serde_json::to_string(converted.as_deref().unwrap_or(catalog))
         .map_err(|e| format!("Failed to serialize catalog: {}", e))?;
     
     // Create temporary files to store the JSON data
-    let temp_dir = std::env::temp_dir();
-    let config_path = temp_dir.join("ledgerflow_config.json");
-    let catalog_path = temp_dir.join("ledgerflow_catalog.json");
-    let output_path = temp_dir.join("ledgerflow_output.json");
-    
-    fs::write(&config_path, config_json)
+    fs::write(&files.config, config_json)
         .map_err(|e| format!("Failed to write config file: {}", e))?;
     
-    fs::write(&catalog_path, catalog_json)
+    fs::write(&files.catalog, catalog_json)
         .map_err(|e| format!("Failed to write catalog file: {}", e))?;
     
     // Determine the path to the Python script
-    let script_path = Path::new("../backend/src/engine.py");
+    let script_path = engine_script(app)?;
     
     // Run the Python script with the JSON files as arguments
-    let output = Command::new("python")
-        .arg(script_path)
+    let python_config = python
+        .lock()
+        .map_err(|_| "Python config lock poisoned".to_string())?
+        .clone();
+    let mut command = python_config.resolve().await?.command();
+    command
+        .current_dir(python_config.working_dir(&script_path))
+        .arg(&script_path)
         .arg("--config")
-        .arg(&config_path)
+        .arg(&files.config)
         .arg("--catalog")
-        .arg(&catalog_path)
+        .arg(&files.catalog)
         .arg("--output")
-        .arg(&output_path)
-        .output()
-        .map_err(|e| format!("Failed to execute Python script: {}", e))?;
+        .arg(&files.output)
+        .args(config.extra_engine_args.iter().flatten());
+    let max_output_bytes = config
+        .max_output_bytes
+        .unwrap_or(engine::DEFAULT_MAX_OUTPUT_BYTES);
+    // Results come back through the output file, so progress lines are ignored here
+    let options = RunOptions {
+        timeout: config.timeout_secs.map(Duration::from_secs),
+        max_retries: config.max_retries.unwrap_or(0),
+        max_output_bytes: Some(max_output_bytes),
+        output_file: Some(files.output.clone()),
+        ..RunOptions::default()
+    };
+    let output = engine::run_engine(command, options, active, |_| {})
+        .await
+        .map_err(|e| e.to_string())?;
     
     if !output.status.success() {
         let stderr = String::from_utf8_lossy(&output.stderr);
         return Err(format!("Python script failed: {}", stderr));
     }
+    let engine_warnings = engine::stderr_warnings(&output.stderr);
     
-    // Read the output JSON file
-    let output_json = fs::read_to_string(&output_path)
-        .map_err(|e| format!("Failed to read output file: {}", e))?;
-    
-    // Parse the output JSON
-    let result: SimulationResult = serde_json::from_str(&output_json)
-        .map_err(|e| format!("Failed to parse output JSON: {}", e))?;
-    
-    // Clean up temporary files
-    let _ = fs::remove_file(&config_path);
-    let _ = fs::remove_file(&catalog_path);
-    let _ = fs::remove_file(&output_path);
-    
+    // Read and parse the output JSON file
+    let mut result = engine_output::read(&files.output, max_output_bytes)?;
+    if let Some(err) = verify::engine_error(&result.status, result.error.as_deref()) {
+        return Err(err);
+    }
+    result.seed_used = seed;
+    result.warnings = engine_warnings;
+    // What's added from here on is a problem with the output, which strict fails on
+    let checked_from = result.warnings.len();
+    if let Some(duplicates) = verify::duplicate_invoice_numbers(&result.invoices) {
+        if !config.allow_duplicate_invoice_numbers {
+            return Err(duplicates);
+        }
+        result.warnings.push(duplicates);
+    }
+    result.warnings.extend(verify::field_conflicts(&result.invoices));
+    // The config isn't validated in this build, so an unknown mode falls back to the default
+    let rounding = RoundingMode::or_default(config.rounding_mode.as_deref());
+    let drift = verify::rounding_drift(&mut result.invoices, config.fix_rounding, rounding);
+    result.warnings.extend(drift);
+    result.warnings.extend(verify::verify_totals(&result.invoices));
+    result.warnings.extend(verify::verify_tax_breakdown(&result.invoices));
+    result.warnings.extend(verify::catalog_tax(&result.invoices, catalog));
+    result.warnings.extend(verify::item_count_bounds(
+        &result.invoices,
+        config.min_items,
+        config.max_items,
+    ));
+    if config.strict {
+        verify::strict(&result.warnings[checked_from..])?;
+    }
     Ok(result)
 }
 
 fn main() {
     tauri::Builder::default()
         .plugin(tauri_plugin_opener::init())
-        .invoke_handler(tauri::generate_handler![run_simulation])
+        .manage(Mutex::new(PythonConfig::default()))
+        .manage(ActiveChildren::default())
+        .manage(RunLock::default())
+        .invoke_handler(tauri::generate_handler![
+            run_simulation,
+            set_python_path,
+            cancel_simulation
+        ])
         .run(tauri::generate_context!())
         .expect("error while running tauri application");
 } 
\ No newline at end of file
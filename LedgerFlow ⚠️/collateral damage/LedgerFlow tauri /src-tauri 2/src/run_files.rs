@@ -0,0 +1,64 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// The files one run hands to and gets back from the engine.
+pub struct RunFiles {
+    pub config: PathBuf,
+    pub catalog: PathBuf,
+    pub output: PathBuf,
+    // Set when `output` is the user's own path, which stays after the run
+    keep_output: bool,
+}
+
+impl RunFiles {
+    /// Paths in `dir` named for a new UUID, so no two runs ever share a file.
+    pub fn unique(dir: &Path) -> Self {
+        let id = uuid::Uuid::new_v4();
+        RunFiles {
+            config: dir.join(format!("ledgerflow_config_{}.json", id)),
+            catalog: dir.join(format!("ledgerflow_catalog_{}.json", id)),
+            output: dir.join(format!("ledgerflow_output_{}.json", id)),
+            keep_output: false,
+        }
+    }
+
+    /// Has the engine write its result to `path` instead, which `finish` leaves in place.
+    pub fn with_output(self, path: PathBuf) -> Self {
+        RunFiles {
+            output: path,
+            keep_output: true,
+            ..self
+        }
+    }
+
+    /// Removes the files once the run is over, apart from an output set by `with_output`.
+    /// When it failed and `keep_on_error` is set they're left for inspection instead, and
+    /// the error says where they are.
+    pub fn finish<T>(&self, result: Result<T, String>, keep_on_error: bool) -> Result<T, String> {
+        let mut paths = vec![&self.config, &self.catalog];
+        if !self.keep_output {
+            paths.push(&self.output);
+        }
+        match result {
+            Err(e) if keep_on_error => {
+                // Files the run never got as far as writing aren't worth pointing at
+                let kept: Vec<String> = paths
+                    .iter()
+                    .filter(|path| path.exists())
+                    .map(|path| path.display().to_string())
+                    .collect();
+                if kept.is_empty() {
+                    Err(e)
+                } else {
+                    Err(format!("{} (temp files kept: {})", e, kept.join(", ")))
+                }
+            }
+            result => {
+                for path in paths {
+                    let _ = fs::remove_file(path);
+                }
+                result
+            }
+        }
+    }
+}
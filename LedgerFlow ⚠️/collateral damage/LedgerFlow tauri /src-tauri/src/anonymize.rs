@@ -0,0 +1,105 @@
+use crate::models::SimulationResult;
+use rand::rngs::StdRng;
+use rand::seq::SliceRandom;
+use rand::SeedableRng;
+use serde_json::Value;
+use std::collections::HashMap;
+
+/// Replaces every customer with a pseudonym such as "Customer 017", the same one for
+/// each invoice of the same customer so repeat business stays visible. Numbers are
+/// shuffled with `seed` rather than given out in order of appearance, and the same seed
+/// always gives the same pseudonyms. Only the name is kept from a customer object; its
+/// other fields (GSTIN, address, contact details) are dropped since any of them could
+/// identify the customer. Invoice numbers, dates and amounts are untouched.
+pub fn anonymize(mut result: SimulationResult, seed: u64) -> SimulationResult {
+    let mut names: Vec<String> = Vec::new();
+    for invoice in &result.invoices {
+        let name = invoice.customer_name();
+        if !names.iter().any(|seen| seen == name) {
+            names.push(name.to_string());
+        }
+    }
+
+    let mut numbers: Vec<usize> = (1..=names.len()).collect();
+    numbers.shuffle(&mut StdRng::seed_from_u64(seed));
+    let width = names.len().to_string().len().max(3);
+    let pseudonyms: HashMap<String, String> = names
+        .into_iter()
+        .zip(numbers)
+        .map(|(name, number)| (name, format!("Customer {:0width$}", number)))
+        .collect();
+
+    for invoice in &mut result.invoices {
+        let pseudonym = pseudonyms[invoice.customer_name()].clone();
+        invoice.customer = match invoice.customer {
+            Value::String(_) => Value::String(pseudonym),
+            _ => serde_json::json!({ "name": pseudonym }),
+        };
+    }
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn result() -> SimulationResult {
+        serde_json::from_str(include_str!("../../backend/test/test_output.json")).unwrap()
+    }
+
+    fn customers(result: &SimulationResult) -> Vec<&str> {
+        result.invoices.iter().map(|i| i.customer_name()).collect()
+    }
+
+    #[test]
+    fn a_customer_keeps_one_pseudonym_and_nothing_else_changes() {
+        let anonymized = anonymize(result(), 7);
+        let names = customers(&anonymized);
+        // 03575 and 03582 are both Saraswati Limited
+        assert_eq!(names[3], names[10]);
+        let mut distinct = names.clone();
+        distinct.sort();
+        distinct.dedup();
+        assert_eq!(distinct.len(), 11);
+        assert!(names.iter().all(|name| name.starts_with("Customer 0")));
+        // The GSTIN could identify the customer
+        assert_eq!(
+            anonymized.invoices[0].customer,
+            serde_json::json!({ "name": names[0] })
+        );
+
+        for (before, after) in result().invoices.iter().zip(&anonymized.invoices) {
+            assert_eq!(after.invoice_number, before.invoice_number);
+            assert_eq!(after.date, before.date);
+            assert_eq!(
+                (after.subtotal, after.total),
+                (before.subtotal, before.total)
+            );
+        }
+    }
+
+    #[test]
+    fn the_same_seed_gives_the_same_pseudonyms() {
+        let json = |seed| serde_json::to_string(&anonymize(result(), seed)).unwrap();
+        assert_eq!(json(7), json(7));
+        assert_ne!(json(7), json(8));
+    }
+
+    #[test]
+    fn bare_customer_names_stay_bare() {
+        let mut older = result();
+        // The older shape, spelt `customer_name`
+        for invoice in &mut older.invoices {
+            invoice.customer = Value::String(invoice.customer_name().to_string());
+        }
+        let older: SimulationResult = serde_json::from_str(
+            &serde_json::to_string(&older)
+                .unwrap()
+                .replace("\"customer\":", "\"customer_name\":"),
+        )
+        .unwrap();
+        let anonymized = anonymize(older, 7);
+        assert!(anonymized.invoices[0].customer.is_string());
+        assert_eq!(customers(&anonymized), customers(&anonymize(result(), 7)));
+    }
+}
@@ -0,0 +1,27 @@
+use crate::engine_output;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+const AUDIT_DIR: &str = "audit";
+
+/// Copies the engine's output file byte for byte into `<data_dir>/audit/`, named by time
+/// and seed (e.g. `20240401_093012_345_seed42.json.gz`), and returns the copy's path.
+pub fn save(data_dir: &Path, output: &Path, seed: i32) -> Result<PathBuf, String> {
+    let dir = data_dir.join(AUDIT_DIR);
+    fs::create_dir_all(&dir).map_err(|e| format!("Failed to create audit directory: {}", e))?;
+    // Kept in whatever form the engine wrote, so name it for what it is
+    let extension = if engine_output::is_compressed(output) {
+        "json.gz"
+    } else {
+        "json"
+    };
+    let name = format!(
+        "{}_seed{}.{}",
+        chrono::Local::now().format("%Y%m%d_%H%M%S_%3f"),
+        seed,
+        extension
+    );
+    let path = dir.join(name);
+    fs::copy(output, &path).map_err(|e| format!("Failed to write audit file: {}", e))?;
+    Ok(path)
+}
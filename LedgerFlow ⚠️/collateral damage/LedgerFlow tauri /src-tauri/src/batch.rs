@@ -0,0 +1,451 @@
+use crate::models::{
+    BatchCheckpoint, BatchResult, BatchRun, CompletedRun, SimulationConfig, SimulationResult,
+};
+use serde::Serialize;
+use std::fs::{self, File};
+use std::io::{BufReader, BufWriter};
+use std::panic::{self, AssertUnwindSafe};
+use std::path::{Path, PathBuf};
+use std::sync::{mpsc, Mutex};
+use std::thread;
+
+const CHECKPOINT_DIR: &str = "batches";
+const CHECKPOINT_FILE: &str = "checkpoint.json";
+
+/// Gives every config a seed derived from `master_seed` and its position in the batch, so
+/// the same master seed reproduces the whole batch while each run still differs. Without
+/// a master seed one is picked at random and only configs with no seed of their own get a
+/// derived one. Returns the master seed used.
+pub fn derive_seeds(configs: &mut [SimulationConfig], master_seed: Option<u64>) -> u64 {
+    // 53 bits, so a picked seed survives the round trip through a JavaScript number
+    let master = master_seed.unwrap_or_else(|| rand::random::<u64>() >> 11);
+    for (index, config) in configs.iter_mut().enumerate() {
+        if master_seed.is_some() || config.seed.is_none() {
+            config.seed = Some(derived_seed(master, index));
+        }
+    }
+    master
+}
+
+// Top 31 bits, so the seed stays non-negative through the engine's `Option<i32>`
+fn derived_seed(master: u64, index: usize) -> i32 {
+    (splitmix64(master ^ index as u64) >> 33) as i32
+}
+
+// Vigna's SplitMix64 finalizer: neighbouring inputs map to unrelated outputs
+fn splitmix64(x: u64) -> u64 {
+    let mut z = x.wrapping_add(0x9E37_79B9_7F4A_7C15);
+    z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+    z ^ (z >> 31)
+}
+
+/// How many configs to run at once: the machine's available parallelism, capped by
+/// `max_parallel` and by the number of configs, and never less than one.
+pub fn worker_count(max_parallel: Option<usize>, configs: usize) -> usize {
+    let available = thread::available_parallelism().map_or(1, |n| n.get());
+    max_parallel
+        .map_or(available, |max| available.min(max))
+        .min(configs)
+        .max(1)
+}
+
+/// Runs every config through `run` on up to `workers` threads and collects the results in
+/// submission order. A panicking run is recorded as a failed result without affecting
+/// the other workers. `finish` is called with each config's index and result as soon as
+/// it's done, from whichever worker ran it, and decides how the result is kept.
+pub fn run_parallel<F, S>(
+    configs: Vec<SimulationConfig>,
+    workers: usize,
+    run: F,
+    finish: S,
+) -> BatchResult
+where
+    F: Fn(SimulationConfig) -> SimulationResult + Sync,
+    S: Fn(usize, SimulationResult) -> BatchRun + Sync,
+{
+    let seeds: Vec<i32> = configs
+        .iter()
+        .map(|config| config.seed.unwrap_or_default())
+        .collect();
+    let queue = Mutex::new(configs.into_iter().enumerate());
+    let (sender, receiver) = mpsc::channel();
+
+    thread::scope(|scope| {
+        for _ in 0..workers {
+            let sender = sender.clone();
+            let (queue, run, finish) = (&queue, &run, &finish);
+            scope.spawn(move || loop {
+                // Runs never panic while holding the queue, but don't let one stop the rest
+                let next = queue
+                    .lock()
+                    .unwrap_or_else(|poisoned| poisoned.into_inner())
+                    .next();
+                let Some((index, config)) = next else {
+                    break;
+                };
+                let seed = config.seed.unwrap_or_default();
+                let result =
+                    panic::catch_unwind(AssertUnwindSafe(|| run(config))).unwrap_or_else(|_| {
+                        SimulationResult::failed(seed, "Batch worker panicked".to_string())
+                    });
+                let _ = sender.send((index, finish(index, result)));
+            });
+        }
+    });
+    drop(sender);
+
+    let mut finished: Vec<Option<BatchRun>> = seeds.iter().map(|_| None).collect();
+    for (index, run) in receiver {
+        finished[index] = Some(run);
+    }
+
+    let mut batch = BatchResult::default();
+    for (run, seed) in finished.into_iter().zip(seeds) {
+        batch.push(run.unwrap_or_else(|| unfinished(seed)));
+    }
+    batch
+}
+
+/// Saves a new batch's checkpoint in a folder of its own under `<data_dir>/batches/`,
+/// named by time and master seed, and returns its path.
+pub fn create_checkpoint(data_dir: &Path, checkpoint: &BatchCheckpoint) -> Result<PathBuf, String> {
+    let name = format!(
+        "{}_seed{}",
+        chrono::Local::now().format("%Y%m%d_%H%M%S_%3f"),
+        checkpoint.master_seed
+    );
+    let dir = data_dir.join(CHECKPOINT_DIR).join(name);
+    fs::create_dir_all(&dir)
+        .map_err(|e| format!("Failed to create batch checkpoint directory: {}", e))?;
+    let path = dir.join(CHECKPOINT_FILE);
+    write_atomic(&path, checkpoint)
+        .map_err(|e| format!("Failed to write batch checkpoint: {}", e))?;
+    Ok(path)
+}
+
+pub fn load_checkpoint(path: &Path) -> Result<BatchCheckpoint, String> {
+    let file = File::open(path)
+        .map_err(|e| format!("Failed to open batch checkpoint {}: {}", path.display(), e))?;
+    serde_json::from_reader(BufReader::new(file))
+        .map_err(|e| format!("{} is not a batch checkpoint: {}", path.display(), e))
+}
+
+/// Runs the configs of the checkpoint at `path` that haven't succeeded yet, as
+/// `run_parallel` would, and returns the whole batch with the earlier results back in
+/// their places. Each run that succeeds is saved next to the checkpoint and recorded in
+/// it straight away, so an interrupted batch only loses the runs in flight, and the batch
+/// reads it back from there. Until every config has succeeded the result carries the
+/// checkpoint's path for another attempt; once one has, `remove_checkpoint` clears it up.
+pub fn run_checkpointed<F, P>(
+    path: &Path,
+    mut checkpoint: BatchCheckpoint,
+    max_parallel: Option<usize>,
+    run: F,
+    on_finished: P,
+) -> BatchResult
+where
+    F: Fn(SimulationConfig) -> SimulationResult + Sync,
+    P: Fn(usize, &SimulationResult) + Sync,
+{
+    let seeds: Vec<i32> = checkpoint
+        .configs
+        .iter()
+        .map(|config| config.seed.unwrap_or_default())
+        .collect();
+    let total = seeds.len();
+    let mut results: Vec<Option<BatchRun>> = seeds.iter().map(|_| None).collect();
+    checkpoint.completed.retain(|done| {
+        // Read through once, so a damaged file is run again rather than reported later
+        match read_result(Path::new(&done.result_path)) {
+            Ok(_) if done.index < total => {
+                results[done.index] = Some(BatchRun::Saved {
+                    path: PathBuf::from(&done.result_path),
+                    seed: seeds[done.index],
+                });
+                true
+            }
+            Ok(_) => false,
+            // Run again rather than fail a batch that's otherwise nearly done
+            Err(e) => {
+                tracing::warn!(index = done.index, error = %e, "Rerunning batch config");
+                false
+            }
+        }
+    });
+    let pending: Vec<usize> = (0..total).filter(|&i| results[i].is_none()).collect();
+    let configs = pending
+        .iter()
+        .map(|&i| checkpoint.configs[i].clone())
+        .collect();
+    let master_seed = checkpoint.master_seed;
+    let dir = path.parent().unwrap_or(Path::new("."));
+    let checkpoint = Mutex::new(checkpoint);
+
+    let workers = worker_count(max_parallel, pending.len());
+    let rerun = run_parallel(configs, workers, run, |i, result| {
+        let index = pending[i];
+        on_finished(index, &result);
+        if result.error.is_some() {
+            return BatchRun::Finished(Box::new(result));
+        }
+        let saved = dir.join(format!("result_{}.json", index));
+        if let Err(e) = write_atomic(&saved, &result) {
+            tracing::warn!(index, error = %e, "Failed to save batch result, keeping it in memory");
+            return BatchRun::Finished(Box::new(result));
+        }
+        let mut checkpoint = checkpoint.lock().unwrap_or_else(|p| p.into_inner());
+        checkpoint.completed.push(CompletedRun {
+            index,
+            result_path: saved.to_string_lossy().into_owned(),
+        });
+        if let Err(e) = write_atomic(path, &*checkpoint) {
+            tracing::warn!(index, error = %e, "Failed to checkpoint batch result");
+        }
+        BatchRun::Saved {
+            path: saved,
+            seed: result.seed_used,
+        }
+    });
+    for (index, run) in pending.into_iter().zip(rerun.results) {
+        results[index] = Some(run);
+    }
+
+    let mut batch = BatchResult::default();
+    for (run, seed) in results.into_iter().zip(seeds) {
+        batch.push(run.unwrap_or_else(|| unfinished(seed)));
+    }
+    batch.master_seed = master_seed;
+    if batch.failed > 0 {
+        batch.checkpoint_path = Some(path.to_string_lossy().into_owned());
+    }
+    batch
+}
+
+/// Removes the folder of the checkpoint at `path`, for a batch in which every config
+/// succeeded. Only call it once the batch has been serialized, since its saved results
+/// are read from that folder.
+pub fn remove_checkpoint(path: &Path) {
+    let dir = path.parent().unwrap_or(Path::new("."));
+    if let Err(e) = fs::remove_dir_all(dir) {
+        tracing::warn!(error = %e, "Failed to remove finished batch checkpoint");
+    }
+}
+
+fn unfinished(seed: i32) -> BatchRun {
+    BatchRun::Finished(Box::new(SimulationResult::failed(
+        seed,
+        "Batch run did not finish".to_string(),
+    )))
+}
+
+// Written beside `path` and renamed over it, so a crash mid-write leaves the old file
+fn write_atomic<T: Serialize>(path: &Path, value: &T) -> Result<(), String> {
+    let mut partial = path.as_os_str().to_owned();
+    partial.push(".tmp");
+    let partial = PathBuf::from(partial);
+    let written = File::create(&partial)
+        .map_err(|e| e.to_string())
+        .and_then(|f| {
+            let mut writer = BufWriter::new(f);
+            serde_json::to_writer(&mut writer, value).map_err(|e| e.to_string())?;
+            let file = writer.into_inner().map_err(|e| e.to_string())?;
+            file.sync_all().map_err(|e| e.to_string())
+        })
+        .and_then(|()| fs::rename(&partial, path).map_err(|e| e.to_string()));
+    if written.is_err() {
+        let _ = fs::remove_file(&partial);
+    }
+    written
+}
+
+fn read_result(path: &Path) -> Result<SimulationResult, String> {
+    File::open(path)
+        .map_err(|e| e.to_string())
+        .and_then(|f| serde_json::from_reader(BufReader::new(f)).map_err(|e| e.to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::time::Duration;
+
+    fn config() -> SimulationConfig {
+        serde_json::from_str(include_str!("../../backend/test/test_config.json")).unwrap()
+    }
+
+    // A successful result that records the config's seed
+    fn succeed(config: SimulationConfig) -> SimulationResult {
+        let mut result = SimulationResult::failed(config.seed.unwrap_or_default(), String::new());
+        result.error = None;
+        result.status = "success".to_string();
+        result
+    }
+
+    fn loaded(batch: &BatchResult) -> Vec<SimulationResult> {
+        serde_json::from_value(serde_json::to_value(&batch.results).unwrap()).unwrap()
+    }
+
+    fn seeded(count: i32) -> Vec<SimulationConfig> {
+        (0..count)
+            .map(|seed| SimulationConfig {
+                seed: Some(seed),
+                ..config()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn never_runs_more_than_the_worker_count_at_once() {
+        let (running, peak) = (AtomicUsize::new(0), AtomicUsize::new(0));
+        let batch = run_parallel(
+            seeded(8),
+            3,
+            |config| {
+                let now = running.fetch_add(1, Ordering::SeqCst) + 1;
+                peak.fetch_max(now, Ordering::SeqCst);
+                thread::sleep(Duration::from_millis(50));
+                running.fetch_sub(1, Ordering::SeqCst);
+                succeed(config)
+            },
+            |_, result| BatchRun::Finished(Box::new(result)),
+        );
+        assert_eq!(peak.load(Ordering::SeqCst), 3);
+        assert_eq!((batch.succeeded, batch.failed), (8, 0));
+        // Back in submission order, whichever finished first
+        let seeds: Vec<i32> = loaded(&batch).iter().map(|r| r.seed_used).collect();
+        assert_eq!(seeds, (0..8).collect::<Vec<_>>());
+        assert_eq!(worker_count(Some(2), 10), worker_count(None, 10).min(2));
+        assert_eq!(worker_count(None, 0), 1);
+    }
+
+    #[test]
+    fn a_panicking_run_fails_alone_with_its_seed() {
+        let batch = run_parallel(
+            seeded(6),
+            3,
+            |config| match config.seed {
+                Some(2) => panic!("engine wrapper bug"),
+                _ => succeed(config),
+            },
+            |_, result| BatchRun::Finished(Box::new(result)),
+        );
+        assert_eq!((batch.succeeded, batch.failed), (5, 1));
+        let results = loaded(&batch);
+        let seeds: Vec<i32> = results.iter().map(|r| r.seed_used).collect();
+        assert_eq!(seeds, [0, 1, 2, 3, 4, 5]);
+        assert_eq!(results[2].error.as_deref(), Some("Batch worker panicked"));
+    }
+
+    #[test]
+    fn a_master_seed_reproduces_the_batch_byte_for_byte() {
+        // The engine's output is a function of its config, seed included
+        let run = |master_seed: u64| {
+            let mut configs = vec![config(); 4];
+            assert_eq!(derive_seeds(&mut configs, Some(master_seed)), master_seed);
+            let batch = run_parallel(configs, 2, succeed, |_, result| {
+                BatchRun::Finished(Box::new(result))
+            });
+            serde_json::to_vec(&batch).unwrap()
+        };
+        assert_eq!(run(42), run(42));
+        assert_ne!(run(42), run(43));
+
+        let mut configs = vec![config(); 3];
+        derive_seeds(&mut configs, Some(42));
+        let seeds: Vec<i32> = configs.iter().map(|c| c.seed.unwrap()).collect();
+        assert!(seeds.iter().all(|seed| *seed >= 0));
+        assert!(seeds[0] != seeds[1] && seeds[1] != seeds[2]);
+
+        // Without one, only unseeded configs get a seed
+        let mut picked = vec![config(), config()];
+        picked[1].seed = None;
+        let master = derive_seeds(&mut picked, None);
+        assert!(master < 1 << 53);
+        assert_eq!(picked[0].seed, Some(12345));
+        assert_eq!(picked[1].seed, Some(derived_seed(master, 1)));
+    }
+
+    #[test]
+    fn resuming_reruns_only_the_configs_that_had_not_succeeded() {
+        let data_dir =
+            std::env::temp_dir().join(format!("ledgerflow_batch_{}", std::process::id()));
+        let mut configs = vec![config(); 5];
+        let master_seed = derive_seeds(&mut configs, Some(7));
+        let mut checkpoint = BatchCheckpoint {
+            master_seed,
+            configs,
+            catalog: Vec::new(),
+            completed: Vec::new(),
+        };
+        let path = create_checkpoint(&data_dir, &checkpoint).unwrap();
+        let dir = path.parent().unwrap().to_path_buf();
+        // Two of the five finished before the batch was interrupted
+        for index in [1, 3] {
+            let mut done = succeed(checkpoint.configs[index].clone());
+            done.status = format!("done before {}", index);
+            let saved = dir.join(format!("result_{}.json", index));
+            write_atomic(&saved, &done).unwrap();
+            checkpoint.completed.push(CompletedRun {
+                index,
+                result_path: saved.to_string_lossy().into_owned(),
+            });
+        }
+        write_atomic(&path, &checkpoint).unwrap();
+        let seeds: Vec<i32> = checkpoint.configs.iter().map(|c| c.seed.unwrap()).collect();
+
+        let runs = AtomicUsize::new(0);
+        let finished = Mutex::new(Vec::new());
+        let batch = run_checkpointed(
+            &path,
+            load_checkpoint(&path).unwrap(),
+            Some(2),
+            |config| {
+                runs.fetch_add(1, Ordering::SeqCst);
+                if config.seed == Some(seeds[4]) {
+                    SimulationResult::failed(seeds[4], "engine crashed".to_string())
+                } else {
+                    succeed(config)
+                }
+            },
+            |index, _| finished.lock().unwrap().push(index),
+        );
+        assert_eq!(runs.load(Ordering::SeqCst), 3);
+        let mut finished = finished.into_inner().unwrap();
+        finished.sort();
+        assert_eq!(finished, [0, 2, 4]);
+        assert_eq!((batch.succeeded, batch.failed), (4, 1));
+        assert_eq!(batch.checkpoint_path.as_deref(), path.to_str());
+        // Each success is read back from the file the checkpoint records
+        assert!(batch.results[..4]
+            .iter()
+            .all(|run| matches!(run, BatchRun::Saved { .. })));
+        let results = loaded(&batch);
+        assert_eq!(results[1].status, "done before 1");
+        assert_eq!(results[3].status, "done before 3");
+        assert_eq!(results[4].seed_used, seeds[4]);
+
+        // A second attempt only reruns the failure
+        runs.store(0, Ordering::SeqCst);
+        let checkpoint = load_checkpoint(&path).unwrap();
+        assert_eq!(checkpoint.completed.len(), 4);
+        let batch = run_checkpointed(
+            &path,
+            checkpoint,
+            None,
+            |config| {
+                runs.fetch_add(1, Ordering::SeqCst);
+                succeed(config)
+            },
+            |_, _| {},
+        );
+        assert_eq!(runs.load(Ordering::SeqCst), 1);
+        assert_eq!((batch.succeeded, batch.failed), (5, 0));
+        assert_eq!(batch.checkpoint_path, None);
+        assert_eq!(loaded(&batch).len(), 5);
+        remove_checkpoint(&path);
+        assert!(!dir.exists());
+        let _ = fs::remove_dir_all(&data_dir);
+    }
+}
@@ -0,0 +1,112 @@
+use crate::engine::{self, ActiveChildren, RunOptions};
+use crate::python::{Interpreter, PythonConfig};
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::Duration;
+
+// `--capabilities` only prints constants, so anything slower than this has hung
+const QUERY_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// The option values the engine supports, as reported by `engine.py --capabilities`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EngineCapabilities {
+    pub invoice_types: Vec<String>,
+    pub realism_modes: Vec<String>,
+    pub name_types: Vec<String>,
+    pub distribution_modes: Vec<String>,
+}
+
+// Capabilities only change with the engine, so a cached answer is tied to the script,
+// interpreter and working directory it came from
+struct Cached {
+    script: PathBuf,
+    interpreter: String,
+    working_dir: PathBuf,
+    capabilities: EngineCapabilities,
+}
+
+#[derive(Default)]
+pub struct CapabilitiesCache {
+    entry: Mutex<Option<Cached>>,
+    // Held across a query so concurrent callers don't each spawn Python; the timeout
+    // bounds how long they wait
+    querying: tokio::sync::Mutex<()>,
+}
+
+impl CapabilitiesCache {
+    /// Returns the cached capabilities, asking the engine only on the first call or after
+    /// the engine script, interpreter or working directory has changed.
+    pub async fn get(
+        &self,
+        python: &PythonConfig,
+        script: &Path,
+        interpreter: &Interpreter,
+    ) -> Result<EngineCapabilities, String> {
+        let interpreter_name = interpreter.to_string();
+        let working_dir = python.working_dir(script);
+        let _querying = self.querying.lock().await;
+        {
+            let entry = self
+                .entry
+                .lock()
+                .map_err(|_| "Capabilities cache lock poisoned".to_string())?;
+            if let Some(cached) = entry.as_ref() {
+                if cached.script == script
+                    && cached.interpreter == interpreter_name
+                    && cached.working_dir == working_dir
+                {
+                    return Ok(cached.capabilities.clone());
+                }
+            }
+        }
+
+        let capabilities = query(script, interpreter, &working_dir).await?;
+        *self
+            .entry
+            .lock()
+            .map_err(|_| "Capabilities cache lock poisoned".to_string())? = Some(Cached {
+            script: script.to_path_buf(),
+            interpreter: interpreter_name,
+            working_dir,
+            capabilities: capabilities.clone(),
+        });
+        Ok(capabilities)
+    }
+
+    pub fn clear(&self) {
+        if let Ok(mut entry) = self.entry.lock() {
+            *entry = None;
+        }
+    }
+}
+
+async fn query(
+    script: &Path,
+    interpreter: &Interpreter,
+    working_dir: &Path,
+) -> Result<EngineCapabilities, String> {
+    let mut command = interpreter.command();
+    command
+        .current_dir(working_dir)
+        .arg(script)
+        .arg("--capabilities");
+    let options = RunOptions {
+        timeout: Some(QUERY_TIMEOUT),
+        ..RunOptions::default()
+    };
+    // Its own map, so cancelling a simulation doesn't cancel the query
+    let output = engine::run_engine(command, options, &ActiveChildren::default(), |_| {})
+        .await
+        .map_err(|e| e.to_string())?;
+    if !output.status.success() {
+        // Older engines reject the flag as an unrecognized argument
+        return Err(format!(
+            "Engine did not report its capabilities: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        ));
+    }
+    let stdout = engine::decode_output(&output.stdout)?;
+    serde_json::from_str(engine::last_json_object(stdout))
+        .map_err(|e| format!("Failed to parse engine capabilities: {}", e))
+}
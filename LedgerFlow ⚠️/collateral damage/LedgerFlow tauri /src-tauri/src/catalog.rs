@@ -0,0 +1,441 @@
+use crate::models::{
+    CatalogItem, CatalogReport, DedupeReport, ReconcileReport, Severity, SimulationConfig,
+    SkuSuggestion,
+};
+use crate::validation;
+use std::collections::HashMap;
+use std::path::Path;
+
+const REQUIRED_COLUMNS: &[&str] = &["sku", "name", "price"];
+
+/// Reads a catalog CSV with headers
+/// `sku,name,price,gst_percent,vat_percent,category,currency`.
+///
+/// Only `sku`, `name` and `price` are required; the other columns may be left out
+/// entirely, and blank tax cells are read as `None`.
+pub fn load_csv(path: &Path) -> Result<Vec<CatalogItem>, String> {
+    let mut reader = csv::ReaderBuilder::new()
+        .trim(csv::Trim::All)
+        .flexible(true)
+        .from_path(path)
+        .map_err(|e| format!("Failed to open catalog CSV {:?}: {}", path, e))?;
+
+    let headers = reader
+        .headers()
+        .map_err(|e| format!("Failed to read catalog CSV headers: {}", e))?
+        .clone();
+    let column = |name: &str| headers.iter().position(|h| h.eq_ignore_ascii_case(name));
+
+    let missing: Vec<&str> = REQUIRED_COLUMNS
+        .iter()
+        .copied()
+        .filter(|name| column(name).is_none())
+        .collect();
+    if !missing.is_empty() {
+        return Err(format!(
+            "Catalog CSV is missing required column(s): {}",
+            missing.join(", ")
+        ));
+    }
+    let sku_col = column("sku").unwrap_or_default();
+    let name_col = column("name").unwrap_or_default();
+    let price_col = column("price").unwrap_or_default();
+    let gst_col = column("gst_percent");
+    let vat_col = column("vat_percent");
+    let category_col = column("category");
+    let currency_col = column("currency");
+
+    let mut items = Vec::new();
+    for record in reader.records() {
+        let record = record.map_err(|e| format!("Failed to read catalog CSV: {}", e))?;
+        let line = record.position().map_or(0, |p| p.line());
+        let cell = |col: Option<usize>| col.and_then(|i| record.get(i)).unwrap_or("");
+
+        let price_cell = cell(Some(price_col));
+        let price = price_cell
+            .parse::<f64>()
+            .map_err(|_| format!("Line {}: invalid price '{}'", line, price_cell))?;
+
+        items.push(CatalogItem {
+            sku: cell(Some(sku_col)).to_string(),
+            name: cell(Some(name_col)).to_string(),
+            price,
+            gst_percent: parse_optional(cell(gst_col), "gst_percent", line)?,
+            vat_percent: parse_optional(cell(vat_col), "vat_percent", line)?,
+            category: Some(cell(category_col))
+                .filter(|c| !c.is_empty())
+                .map(str::to_string),
+            currency: Some(cell(currency_col))
+                .filter(|c| !c.is_empty())
+                .map(str::to_string),
+        });
+    }
+    Ok(items)
+}
+
+fn parse_optional(value: &str, field: &str, line: u64) -> Result<Option<f64>, String> {
+    if value.is_empty() {
+        return Ok(None);
+    }
+    value
+        .parse::<f64>()
+        .map(Some)
+        .map_err(|_| format!("Line {}: invalid {} '{}'", line, field, value))
+}
+
+/// Combines several catalogs into one, in order of first appearance. SKUs are compared
+/// trimmed and case-insensitively; `on_conflict` decides what happens to duplicates:
+/// `"error"` rejects the merge listing every duplicated SKU, `"first_wins"` keeps the
+/// earliest item and `"last_wins"` replaces it with the latest.
+pub fn merge(
+    catalogs: Vec<Vec<CatalogItem>>,
+    on_conflict: &str,
+) -> Result<Vec<CatalogItem>, String> {
+    if !["error", "first_wins", "last_wins"].contains(&on_conflict) {
+        return Err(format!(
+            "Unknown on_conflict '{}' (expected 'error', 'first_wins' or 'last_wins')",
+            on_conflict
+        ));
+    }
+
+    let mut merged: Vec<CatalogItem> = Vec::new();
+    let mut index: HashMap<String, usize> = HashMap::new();
+    let mut duplicates: Vec<String> = Vec::new();
+    for item in catalogs.into_iter().flatten() {
+        let key = normalize_sku(&item.sku);
+        match index.get(&key) {
+            None => {
+                index.insert(key, merged.len());
+                merged.push(item);
+            }
+            Some(&existing) => {
+                if on_conflict == "last_wins" {
+                    merged[existing] = item;
+                }
+                if !duplicates.contains(&key) {
+                    duplicates.push(key);
+                }
+            }
+        }
+    }
+
+    if on_conflict == "error" && !duplicates.is_empty() {
+        return Err(format!(
+            "Duplicate SKUs across catalogs: {}",
+            duplicates.join(", ")
+        ));
+    }
+    Ok(merged)
+}
+
+/// Collapses items sharing a SKU (compared as in `merge`) into the first one's place.
+/// `strategy` picks the survivor: `"keep_first"` keeps the earliest item,
+/// `"keep_highest_price"` the most expensive (the earliest on a tie), and
+/// `"merge_names"` keeps the earliest but joins every distinct name with " / " so none
+/// is lost. Each duplicate adds a line to the report's `changes`.
+pub fn dedupe(catalog: Vec<CatalogItem>, strategy: &str) -> Result<DedupeReport, String> {
+    if !["keep_first", "keep_highest_price", "merge_names"].contains(&strategy) {
+        return Err(format!(
+            "Unknown strategy '{}' (expected 'keep_first', 'keep_highest_price' or 'merge_names')",
+            strategy
+        ));
+    }
+
+    let mut report = DedupeReport::default();
+    let mut index: HashMap<String, usize> = HashMap::new();
+    for (row, item) in catalog.into_iter().enumerate() {
+        let key = normalize_sku(&item.sku);
+        let Some(&existing) = index.get(&key) else {
+            index.insert(key, report.catalog.len());
+            report.catalog.push(item);
+            continue;
+        };
+        let kept = &mut report.catalog[existing];
+        let change = match strategy {
+            "keep_highest_price" if item.price > kept.price => {
+                let change = format!(
+                    "SKU '{}': kept '{}' at {:.2} from row {}, dropped '{}' at {:.2}",
+                    kept.sku,
+                    item.name,
+                    item.price,
+                    row + 1,
+                    kept.name,
+                    kept.price
+                );
+                *kept = item;
+                change
+            }
+            "merge_names" if kept.name.split(" / ").any(|name| name == item.name) => format!(
+                "SKU '{}': dropped row {}, which repeats the name '{}'",
+                kept.sku,
+                row + 1,
+                item.name
+            ),
+            "merge_names" => {
+                kept.name = format!("{} / {}", kept.name, item.name);
+                format!(
+                    "SKU '{}': merged the name '{}' from row {}",
+                    kept.sku,
+                    item.name,
+                    row + 1
+                )
+            }
+            _ => format!(
+                "SKU '{}': dropped '{}' at {:.2} from row {}, keeping '{}' at {:.2}",
+                kept.sku,
+                item.name,
+                item.price,
+                row + 1,
+                kept.name,
+                kept.price
+            ),
+        };
+        report.changes.push(change);
+    }
+    Ok(report)
+}
+
+/// Checks every item and reports all problems at once, in catalog order: blank or
+/// duplicate SKUs, blank names, prices that aren't positive and items with both a GST and
+/// a VAT rate. A zero price is only a warning, since a free line is rare but legitimate.
+pub fn validate(catalog: &[CatalogItem]) -> CatalogReport {
+    let mut report = CatalogReport::default();
+    let mut first_row: HashMap<String, usize> = HashMap::new();
+    for (index, item) in catalog.iter().enumerate() {
+        let row = index + 1;
+        let sku = item.sku.trim();
+        if sku.is_empty() {
+            report.push(Severity::Error, row, sku, "SKU is blank".to_string());
+        } else if let Some(first) = first_row.get(&normalize_sku(sku)) {
+            report.push(
+                Severity::Error,
+                row,
+                sku,
+                format!("SKU '{}' duplicates row {}", sku, first),
+            );
+        } else {
+            first_row.insert(normalize_sku(sku), row);
+        }
+        if item.name.trim().is_empty() {
+            report.push(Severity::Error, row, sku, "Name is blank".to_string());
+        }
+        if !item.price.is_finite() || item.price < 0.0 {
+            report.push(
+                Severity::Error,
+                row,
+                sku,
+                format!("Price {} is negative or not a number", item.price),
+            );
+        } else if item.price == 0.0 {
+            report.push(Severity::Warning, row, sku, "Price is zero".to_string());
+        }
+        let nonzero = |rate: Option<f64>| rate.is_some_and(|rate| rate != 0.0);
+        if nonzero(item.gst_percent) && nonzero(item.vat_percent) {
+            report.push(
+                Severity::Error,
+                row,
+                sku,
+                "Both a GST and a VAT rate are set".to_string(),
+            );
+        }
+    }
+    report
+}
+
+/// Lists the config's `selected_items` that match no catalog item by SKU or name, and for
+/// each suggests the catalog SKU fewest edits away, as long as that's no more than half
+/// the entry's length; anything further is more likely a different item than a typo.
+pub fn reconcile(config: &SimulationConfig, catalog: &[CatalogItem]) -> ReconcileReport {
+    let missing = validation::unknown_skus(config, catalog);
+    let suggestions = missing
+        .iter()
+        .filter_map(|selected| {
+            let wanted = normalize_sku(selected);
+            let (distance, item) = catalog
+                .iter()
+                .map(|item| (levenshtein(&wanted, &normalize_sku(&item.sku)), item))
+                .min_by_key(|(distance, _)| *distance)?;
+            (distance <= wanted.chars().count() / 2).then(|| SkuSuggestion {
+                selected: selected.clone(),
+                suggestion: item.sku.clone(),
+                distance,
+            })
+        })
+        .collect();
+    ReconcileReport {
+        missing,
+        suggestions,
+    }
+}
+
+/// Edits (insertions, deletions, substitutions) turning `a` into `b`, one row at a time.
+pub fn levenshtein(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut row: Vec<usize> = (0..=b.len()).collect();
+    for (i, ca) in a.chars().enumerate() {
+        let mut diagonal = row[0];
+        row[0] = i + 1;
+        for (j, cb) in b.iter().enumerate() {
+            let substitution = diagonal + usize::from(ca != *cb);
+            diagonal = row[j + 1];
+            row[j + 1] = substitution.min(row[j] + 1).min(diagonal + 1);
+        }
+    }
+    row[b.len()]
+}
+
+fn normalize_sku(sku: &str) -> String {
+    sku.trim().to_uppercase()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::temp_file::TempFile;
+
+    fn csv_file(text: &str) -> TempFile {
+        let file = TempFile::unique("ledgerflow_catalog", "csv");
+        std::fs::write(file.path(), text).unwrap();
+        file
+    }
+
+    #[test]
+    fn loads_optional_columns_when_present() {
+        let file = csv_file(
+            "sku,name,price,gst_percent,category\n\
+             D1, Desk ,4500,18,Furniture\n\
+             C1,Chair,1200,,\n",
+        );
+        let items = load_csv(file.path()).unwrap();
+        assert_eq!(items.len(), 2);
+        assert_eq!((items[0].name.as_str(), items[0].price), ("Desk", 4500.0));
+        assert_eq!(items[0].gst_percent, Some(18.0));
+        assert_eq!(items[0].category.as_deref(), Some("Furniture"));
+        assert_eq!((items[1].gst_percent, items[1].vat_percent), (None, None));
+        assert_eq!(items[1].category, None);
+    }
+
+    #[test]
+    fn names_the_line_of_a_malformed_price() {
+        let file = csv_file("sku,name,price\nD1,Desk,4500\nC1,Chair,12OO\n");
+        assert_eq!(
+            load_csv(file.path()).unwrap_err(),
+            "Line 3: invalid price '12OO'"
+        );
+        let file = csv_file("sku,price\nD1,4500\n");
+        assert_eq!(
+            load_csv(file.path()).unwrap_err(),
+            "Catalog CSV is missing required column(s): name"
+        );
+    }
+
+    #[test]
+    fn reports_every_problem_in_catalog_order() {
+        let catalog: Vec<CatalogItem> = serde_json::from_value(serde_json::json!([
+            {"sku": "D1", "name": "Desk", "price": 4500.0, "gst_percent": 18.0},
+            {"sku": " d1 ", "name": "Desk again", "price": 4500.0},
+            {"sku": "", "name": " ", "price": -1.0},
+            {"sku": "F1", "name": "Freebie", "price": 0.0},
+            {"sku": "B1", "name": "Both", "price": 10.0, "gst_percent": 5.0, "vat_percent": 10.0},
+        ]))
+        .unwrap();
+        let report = validate(&catalog);
+        let issues: Vec<(usize, Severity, &str)> = report
+            .issues
+            .iter()
+            .map(|issue| (issue.row, issue.severity, issue.message.as_str()))
+            .collect();
+        assert_eq!(
+            issues,
+            [
+                (2, Severity::Error, "SKU 'd1' duplicates row 1"),
+                (3, Severity::Error, "SKU is blank"),
+                (3, Severity::Error, "Name is blank"),
+                (3, Severity::Error, "Price -1 is negative or not a number"),
+                (4, Severity::Warning, "Price is zero"),
+                (5, Severity::Error, "Both a GST and a VAT rate are set"),
+            ]
+        );
+        assert_eq!((report.errors, report.warnings), (5, 1));
+    }
+
+    #[test]
+    fn suggests_the_closest_sku_for_a_stale_selection() {
+        let catalog: Vec<CatalogItem> = serde_json::from_value(serde_json::json!([
+            {"sku": "DSK-001", "name": "Desk", "price": 4500.0},
+            {"sku": "CHR-001", "name": "Chair", "price": 1200.0},
+            {"sku": "LMP-001", "name": "Lamp", "price": 800.0},
+        ]))
+        .unwrap();
+        let mut config: SimulationConfig =
+            serde_json::from_str(include_str!("../../backend/test/test_config.json")).unwrap();
+        config.item_filter_mode = "include".to_string();
+        // One by SKU, one by name, and one renamed since the config was saved
+        config.selected_items = vec!["DSK-001".into(), "Chair".into(), "lmp-01".into()];
+        let report = reconcile(&config, &catalog);
+        assert_eq!(report.missing, ["lmp-01"]);
+        assert_eq!(
+            report.suggestions,
+            [SkuSuggestion {
+                selected: "lmp-01".to_string(),
+                suggestion: "LMP-001".to_string(),
+                distance: 1,
+            }]
+        );
+
+        // Too far from anything to be a typo
+        config.selected_items = vec!["XYZZY-9".into()];
+        let report = reconcile(&config, &catalog);
+        assert_eq!(report.missing, ["XYZZY-9"]);
+        assert!(report.suggestions.is_empty());
+    }
+
+    #[test]
+    fn each_dedupe_strategy_picks_its_own_survivor() {
+        let catalog = || -> Vec<CatalogItem> {
+            serde_json::from_value(serde_json::json!([
+                {"sku": "D1", "name": "Desk", "price": 4500.0},
+                {"sku": "C1", "name": "Chair", "price": 1200.0},
+                {"sku": "d1", "name": "Standing Desk", "price": 9000.0},
+            ]))
+            .unwrap()
+        };
+        let survivor = |strategy| {
+            let report = dedupe(catalog(), strategy).unwrap();
+            assert_eq!(report.catalog.len(), 2);
+            assert_eq!(report.changes.len(), 1);
+            // Still in the first one's place
+            assert_eq!(report.catalog[1].sku, "C1");
+            let desk = &report.catalog[0];
+            (desk.name.clone(), desk.price, report.changes[0].clone())
+        };
+
+        assert_eq!(
+            survivor("keep_first"),
+            (
+                "Desk".to_string(),
+                4500.0,
+                "SKU 'D1': dropped 'Standing Desk' at 9000.00 from row 3, keeping 'Desk' at 4500.00"
+                    .to_string()
+            )
+        );
+        assert_eq!(
+            survivor("keep_highest_price"),
+            (
+                "Standing Desk".to_string(),
+                9000.0,
+                "SKU 'D1': kept 'Standing Desk' at 9000.00 from row 3, dropped 'Desk' at 4500.00"
+                    .to_string()
+            )
+        );
+        assert_eq!(
+            survivor("merge_names"),
+            (
+                "Desk / Standing Desk".to_string(),
+                4500.0,
+                "SKU 'D1': merged the name 'Standing Desk' from row 3".to_string()
+            )
+        );
+        assert!(dedupe(catalog(), "keep_last").is_err());
+    }
+}
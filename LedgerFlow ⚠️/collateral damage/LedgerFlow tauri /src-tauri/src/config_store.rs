@@ -0,0 +1,226 @@
+use crate::catalog;
+use crate::models::SimulationConfig;
+use serde::de::{self, Deserialize, Deserializer, Visitor};
+use std::fs;
+use std::io::ErrorKind;
+use std::path::Path;
+
+const CONFIG_FILE: &str = "config.json";
+
+// Edits within which an unknown key is taken for a typo of a known one
+const MAX_TYPO_DISTANCE: usize = 3;
+
+/// Saves `config` as the last-used configuration, replacing any previous one.
+pub fn save(dir: &Path, config: &SimulationConfig) -> Result<(), String> {
+    fs::create_dir_all(dir).map_err(|e| format!("Failed to create config directory: {}", e))?;
+    let json = serde_json::to_string_pretty(config)
+        .map_err(|e| format!("Failed to serialize config: {}", e))?;
+
+    // Written alongside and renamed over so a crash mid-write can't leave half a file
+    let path = dir.join(CONFIG_FILE);
+    let staging = path.with_extension("json.tmp");
+    fs::write(&staging, json).map_err(|e| format!("Failed to write config file: {}", e))?;
+    fs::rename(&staging, &path).map_err(|e| format!("Failed to save config file: {}", e))
+}
+
+/// Loads the last-saved configuration, or None if nothing has been saved yet.
+///
+/// Files saved by older versions lack the newer optional fields; those load as None.
+/// A `reality_buffer` or `customer_repeat_rate` saved as a percentage is converted to a
+/// fraction.
+pub fn load(dir: &Path) -> Result<Option<SimulationConfig>, String> {
+    let json = match fs::read_to_string(dir.join(CONFIG_FILE)) {
+        Ok(json) => json,
+        Err(e) if e.kind() == ErrorKind::NotFound => return Ok(None),
+        Err(e) => return Err(format!("Failed to read config file: {}", e)),
+    };
+    let mut config: SimulationConfig =
+        serde_json::from_str(&json).map_err(|e| format!("Failed to parse saved config: {}", e))?;
+    // Saved while these were percentages
+    for fraction in [&mut config.reality_buffer, &mut config.customer_repeat_rate] {
+        if let Some(value) = fraction.as_mut() {
+            if *value > 1.0 && *value <= 100.0 {
+                *value /= 100.0;
+            }
+        }
+    }
+    Ok(Some(config))
+}
+
+/// Reads a config written by hand as TOML, with the same snake_case keys as the JSON.
+/// A key the config doesn't have is an error naming its line and the key it was probably
+/// meant to be, rather than being silently dropped.
+pub fn load_toml(path: &Path) -> Result<SimulationConfig, String> {
+    let text = fs::read_to_string(path)
+        .map_err(|e| format!("Failed to read config file {}: {}", path.display(), e))?;
+    let table: toml::Table = text
+        .parse()
+        .map_err(|e| format!("Failed to parse {}: {}", path.display(), e))?;
+    let known = field_names();
+    if let Some(key) = table.keys().find(|key| !known.contains(&key.as_str())) {
+        return Err(unknown_key(&text, key, known, path));
+    }
+    toml::from_str(&text).map_err(|e| format!("Invalid config in {}: {}", path.display(), e))
+}
+
+fn unknown_key(text: &str, key: &str, known: &[&str], path: &Path) -> String {
+    let line = text
+        .lines()
+        .position(|line| {
+            let line = line.trim_start();
+            [key.to_string(), format!("\"{}\"", key)]
+                .iter()
+                .any(|spelling| {
+                    line.strip_prefix(spelling.as_str())
+                        .is_some_and(|rest| rest.trim_start().starts_with('='))
+                })
+        })
+        .map_or_else(String::new, |index| format!(" on line {}", index + 1));
+    let closest = known
+        .iter()
+        .map(|name| (catalog::levenshtein(key, name), name))
+        .filter(|(distance, _)| *distance <= MAX_TYPO_DISTANCE)
+        .min();
+    let hint = closest.map_or_else(String::new, |(_, name)| {
+        format!("; did you mean '{}'?", name)
+    });
+    format!(
+        "Unknown config key '{}'{} of {}{}",
+        key,
+        line,
+        path.display(),
+        hint
+    )
+}
+
+// The keys `SimulationConfig` deserializes, straight from serde so they can't drift from
+// the struct: its derived impl hands them to `deserialize_struct`, which stops there
+fn field_names() -> &'static [&'static str] {
+    let mut fields: &'static [&'static str] = &[];
+    let _ = SimulationConfig::deserialize(FieldNames(&mut fields));
+    fields
+}
+
+struct FieldNames<'a>(&'a mut &'static [&'static str]);
+
+impl<'de> Deserializer<'de> for FieldNames<'_> {
+    type Error = de::value::Error;
+
+    fn deserialize_any<V: Visitor<'de>>(self, _visitor: V) -> Result<V::Value, Self::Error> {
+        Err(de::Error::custom("not a struct"))
+    }
+
+    fn deserialize_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        fields: &'static [&'static str],
+        _visitor: V,
+    ) -> Result<V::Value, Self::Error> {
+        *self.0 = fields;
+        Err(de::Error::custom("only the field names were wanted"))
+    }
+
+    serde::forward_to_deserialize_any! {
+        bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string bytes byte_buf
+        option unit unit_struct newtype_struct seq tuple tuple_struct map enum identifier
+        ignored_any
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::temp_file::TempFile;
+
+    const CONFIG: &str = r#"revenue_target = 100000
+start_date = "2023-01-01"
+end_date = "2023-01-31"
+invoice_type = "gst"
+min_items = 1
+max_items = 5
+min_invoice_amount = 1000
+max_invoice_amount = 10000
+item_filter_mode = "all"
+selected_items = []
+name_type = "indian_company"
+realism_mode = "random"
+"#;
+
+    fn config() -> SimulationConfig {
+        serde_json::from_str(include_str!("../../backend/test/test_config.json")).unwrap()
+    }
+
+    fn data_dir(name: &str) -> std::path::PathBuf {
+        std::env::temp_dir().join(format!("ledgerflow_{}_{}", name, std::process::id()))
+    }
+
+    fn write(text: &str) -> TempFile {
+        let file = TempFile::unique("ledgerflow_config", "toml");
+        fs::write(file.path(), text).unwrap();
+        file
+    }
+
+    #[test]
+    fn saved_config_loads_back() {
+        let dir = data_dir("config_saved");
+        assert!(load(&dir).unwrap().is_none());
+        let mut config = config();
+        config.reality_buffer = Some(0.25);
+        save(&dir, &config).unwrap();
+        let loaded = load(&dir).unwrap().unwrap();
+        assert_eq!(
+            serde_json::to_value(&loaded).unwrap(),
+            serde_json::to_value(&config).unwrap()
+        );
+        assert!(!dir.join("config.json.tmp").exists());
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn percentages_saved_by_older_versions_load_as_fractions() {
+        let dir = data_dir("config_percent");
+        let mut config = config();
+        config.reality_buffer = Some(15.0);
+        config.customer_repeat_rate = Some(30.0);
+        save(&dir, &config).unwrap();
+        let loaded = load(&dir).unwrap().unwrap();
+        assert_eq!(loaded.reality_buffer, Some(0.15));
+        assert_eq!(loaded.customer_repeat_rate, Some(0.3));
+        // Already a fraction, or the largest fraction there is
+        for buffer in [0.3, 1.0] {
+            config.reality_buffer = Some(buffer);
+            save(&dir, &config).unwrap();
+            assert_eq!(load(&dir).unwrap().unwrap().reality_buffer, Some(buffer));
+        }
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn loads_a_handwritten_config() {
+        let file = write(&format!("{}seed = 7\n", CONFIG));
+        let config = load_toml(file.path()).unwrap();
+        assert_eq!(config.revenue_target, 100000.0);
+        assert_eq!(config.seed, Some(7));
+        assert_eq!(config.reality_buffer, None);
+    }
+
+    #[test]
+    fn names_the_line_and_likely_key_of_a_typo() {
+        let file = write(&format!("{}reality_bufer = 0.2\n", CONFIG));
+        let error = load_toml(file.path()).unwrap_err();
+        assert!(
+            error.starts_with("Unknown config key 'reality_bufer' on line 13"),
+            "{}",
+            error
+        );
+        assert!(
+            error.ends_with("did you mean 'reality_buffer'?"),
+            "{}",
+            error
+        );
+
+        let file = write(&format!("{}colour_scheme = \"dark\"\n", CONFIG));
+        let error = load_toml(file.path()).unwrap_err();
+        assert!(!error.contains("did you mean"), "{}", error);
+    }
+}
@@ -0,0 +1,259 @@
+use crate::models::{CatalogItem, SimulationConfig};
+use crate::rounding::RoundingMode;
+use std::collections::{BTreeMap, BTreeSet};
+
+// Display rules for the currencies LedgerFlow's invoice types use. Anything else is
+// shown with two decimals and the ISO code after the number.
+
+#[derive(Clone, Copy)]
+enum Grouping {
+    // 1,234,567
+    Thousands,
+    // 12,34,567: hundreds, then pairs, as used for rupees
+    Indian,
+}
+
+struct Currency {
+    code: &'static str,
+    symbol: &'static str,
+    decimals: usize,
+    grouping: Grouping,
+}
+
+const CURRENCIES: &[Currency] = &[
+    Currency {
+        code: "INR",
+        symbol: "₹",
+        decimals: 2,
+        grouping: Grouping::Indian,
+    },
+    Currency {
+        code: "USD",
+        symbol: "$",
+        decimals: 2,
+        grouping: Grouping::Thousands,
+    },
+    Currency {
+        code: "EUR",
+        symbol: "€",
+        decimals: 2,
+        grouping: Grouping::Thousands,
+    },
+    Currency {
+        code: "GBP",
+        symbol: "£",
+        decimals: 2,
+        grouping: Grouping::Thousands,
+    },
+    Currency {
+        code: "JPY",
+        symbol: "¥",
+        decimals: 0,
+        grouping: Grouping::Thousands,
+    },
+    Currency {
+        code: "BHD",
+        symbol: "BD ",
+        decimals: 3,
+        grouping: Grouping::Thousands,
+    },
+    Currency {
+        code: "AED",
+        symbol: "AED ",
+        decimals: 2,
+        grouping: Grouping::Thousands,
+    },
+];
+
+/// Returns true for a well-formed ISO 4217 code (three uppercase letters).
+pub fn is_valid_code(code: &str) -> bool {
+    code.len() == 3 && code.bytes().all(|b| b.is_ascii_uppercase())
+}
+
+/// Formats `value` for display, e.g. `₹1,23,456.70`, `$123,456.70` or `¥123,457`.
+/// The currency code is matched case-insensitively; unknown codes give `1,234.50 XYZ`.
+/// The value is rounded to the currency's decimals with `rounding`.
+pub fn format_amount(value: f64, currency: &str, rounding: RoundingMode) -> String {
+    let code = currency.trim().to_ascii_uppercase();
+    let known = lookup(&code);
+    let decimals = known.map_or(2, |c| c.decimals);
+    let grouping = known.map_or(Grouping::Thousands, |c| c.grouping);
+
+    let fixed = rounding.format(value.abs(), decimals as u32);
+    let (whole, fraction) = fixed.split_once('.').unwrap_or((&fixed, ""));
+    let mut number = group(whole, grouping);
+    if !fraction.is_empty() {
+        number.push('.');
+        number.push_str(fraction);
+    }
+    // Don't show "-0.00" for values that round to zero
+    let sign = if value < 0.0 && fixed.bytes().any(|b| b.is_ascii_digit() && b != b'0') {
+        "-"
+    } else {
+        ""
+    };
+
+    match known {
+        Some(c) => format!("{}{}{}", sign, c.symbol, number),
+        None => format!("{}{} {}", sign, number, code),
+    }
+}
+
+/// The catalog with every price in the config's base currency (`base_currency`, else
+/// `currency`), or `None` when no item is priced in another currency and it can be sent
+/// to the engine as it is. Fails listing each currency without a usable exchange rate.
+pub fn base_catalog(
+    config: &SimulationConfig,
+    catalog: &[CatalogItem],
+) -> Result<Option<Vec<CatalogItem>>, String> {
+    let base = config
+        .base_currency
+        .as_deref()
+        .or(config.currency.as_deref())
+        .map(|code| code.trim().to_ascii_uppercase());
+    let foreign: BTreeSet<String> = catalog
+        .iter()
+        .filter_map(|item| item.currency.as_deref())
+        .map(|code| code.trim().to_ascii_uppercase())
+        .filter(|code| base.as_ref() != Some(code))
+        .collect();
+    if foreign.is_empty() {
+        return Ok(None);
+    }
+    let Some(base) = base else {
+        return Err(format!(
+            "The catalog has prices in {}, but the config has no base_currency to convert them to",
+            foreign.into_iter().collect::<Vec<_>>().join(", ")
+        ));
+    };
+    convert_catalog(catalog, &base, &config.exchange_rates).map(Some)
+}
+
+/// Converts prices into `base` using `rates`, the units of `base` per unit of each other
+/// currency. Codes are matched case-insensitively, and items without a currency are taken
+/// to be in `base` already.
+pub fn convert_catalog(
+    catalog: &[CatalogItem],
+    base: &str,
+    rates: &BTreeMap<String, f64>,
+) -> Result<Vec<CatalogItem>, String> {
+    let base = base.trim().to_ascii_uppercase();
+    let rate = |code: &str| {
+        rates
+            .iter()
+            .find(|(known, _)| known.trim().eq_ignore_ascii_case(code))
+            .map(|(_, rate)| *rate)
+            .filter(|rate| rate.is_finite() && *rate > 0.0)
+    };
+    let mut missing = BTreeSet::new();
+    let converted = catalog
+        .iter()
+        .map(|item| {
+            let code = item
+                .currency
+                .as_deref()
+                .map(|code| code.trim().to_ascii_uppercase())
+                .filter(|code| *code != base);
+            let price = match code {
+                None => item.price,
+                Some(code) => match rate(&code) {
+                    Some(rate) => item.price * rate,
+                    None => {
+                        missing.insert(code);
+                        item.price
+                    }
+                },
+            };
+            CatalogItem {
+                price,
+                currency: Some(base.clone()),
+                ..item.clone()
+            }
+        })
+        .collect();
+    if !missing.is_empty() {
+        return Err(format!(
+            "No exchange rate to {} for {}; add a positive rate to exchange_rates for each",
+            base,
+            missing.into_iter().collect::<Vec<_>>().join(", ")
+        ));
+    }
+    Ok(converted)
+}
+
+fn lookup(code: &str) -> Option<&'static Currency> {
+    CURRENCIES
+        .iter()
+        .find(|c| c.code.eq_ignore_ascii_case(code))
+}
+
+fn group(digits: &str, grouping: Grouping) -> String {
+    let (head, tail) = match grouping {
+        Grouping::Thousands => (digits, ""),
+        // The last three digits form one group and the rest are grouped in pairs
+        Grouping::Indian if digits.len() > 3 => digits.split_at(digits.len() - 3),
+        Grouping::Indian => return digits.to_string(),
+    };
+    let size = match grouping {
+        Grouping::Thousands => 3,
+        Grouping::Indian => 2,
+    };
+
+    let mut out = String::new();
+    for (i, c) in head.chars().enumerate() {
+        if i > 0 && (head.len() - i) % size == 0 {
+            out.push(',');
+        }
+        out.push(c);
+    }
+    if !tail.is_empty() {
+        out.push(',');
+        out.push_str(tail);
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn catalog() -> Vec<CatalogItem> {
+        serde_json::from_value(serde_json::json!([
+            {"sku": "D1", "name": "Desk", "price": 10.0, "currency": "USD"},
+            {"sku": "C1", "name": "Chair", "price": 20.0, "currency": "eur"},
+            {"sku": "L1", "name": "Lamp", "price": 500.0},
+        ]))
+        .unwrap()
+    }
+
+    #[test]
+    fn converts_two_currencies_into_the_base() {
+        let rates = BTreeMap::from([("USD".to_string(), 83.0), ("Eur".to_string(), 90.0)]);
+        let converted = convert_catalog(&catalog(), "inr", &rates).unwrap();
+        let prices: Vec<f64> = converted.iter().map(|item| item.price).collect();
+        assert_eq!(prices, [830.0, 1800.0, 500.0]);
+        assert!(converted
+            .iter()
+            .all(|item| item.currency.as_deref() == Some("INR")));
+    }
+
+    #[test]
+    fn names_every_currency_without_a_rate() {
+        let rates = BTreeMap::from([("USD".to_string(), 83.0), ("EUR".to_string(), 0.0)]);
+        assert_eq!(
+            convert_catalog(&catalog(), "INR", &rates).unwrap_err(),
+            "No exchange rate to INR for EUR; add a positive rate to exchange_rates for each"
+        );
+    }
+
+    #[test]
+    fn groups_rupees_in_lakhs_and_crores() {
+        let format = |value, currency| format_amount(value, currency, RoundingMode::HalfUp);
+        assert_eq!(format(12345678.9, "INR"), "₹1,23,45,678.90");
+        assert_eq!(format(-1234567.5, "inr"), "-₹12,34,567.50");
+        assert_eq!(format(999.0, "INR"), "₹999.00");
+        assert_eq!(format(-0.001, "INR"), "₹0.00");
+        assert_eq!(format(-1234567.5, "USD"), "-$1,234,567.50");
+        assert_eq!(format(1234.5, "XYZ"), "1,234.50 XYZ");
+    }
+}
@@ -0,0 +1,138 @@
+use crate::engine::{self, ActiveChildren, RunOptions};
+use crate::python::{Interpreter, PythonConfig};
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::Duration;
+
+// `--check-deps` imports each dependency, which can take a while on a cold start
+const QUERY_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// One module the engine imports, as reported by `engine.py --check-deps`. `version` is
+/// None for standard library modules, which don't report one.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DependencyStatus {
+    pub name: String,
+    pub installed: bool,
+    pub version: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct Report {
+    dependencies: Vec<DependencyStatus>,
+}
+
+// Like the capabilities, the answer only changes with the engine, the interpreter or the
+// directory it runs in
+struct Cached {
+    script: PathBuf,
+    interpreter: String,
+    working_dir: PathBuf,
+    dependencies: Vec<DependencyStatus>,
+}
+
+#[derive(Default)]
+pub struct DependencyCache {
+    entry: Mutex<Option<Cached>>,
+    // Held across a query so concurrent callers don't each spawn Python; the timeout
+    // bounds how long they wait
+    querying: tokio::sync::Mutex<()>,
+}
+
+impl DependencyCache {
+    /// Returns the cached statuses, asking the engine only on the first call or after the
+    /// engine script, interpreter or working directory has changed.
+    pub async fn get(
+        &self,
+        python: &PythonConfig,
+        script: &Path,
+        interpreter: &Interpreter,
+    ) -> Result<Vec<DependencyStatus>, String> {
+        let interpreter_name = interpreter.to_string();
+        let working_dir = python.working_dir(script);
+        let _querying = self.querying.lock().await;
+        {
+            let entry = self
+                .entry
+                .lock()
+                .map_err(|_| "Dependency cache lock poisoned".to_string())?;
+            if let Some(cached) = entry.as_ref() {
+                if cached.script == script
+                    && cached.interpreter == interpreter_name
+                    && cached.working_dir == working_dir
+                {
+                    return Ok(cached.dependencies.clone());
+                }
+            }
+        }
+
+        let dependencies = query(script, interpreter, &working_dir).await?;
+        *self
+            .entry
+            .lock()
+            .map_err(|_| "Dependency cache lock poisoned".to_string())? = Some(Cached {
+            script: script.to_path_buf(),
+            interpreter: interpreter_name,
+            working_dir,
+            dependencies: dependencies.clone(),
+        });
+        Ok(dependencies)
+    }
+
+    pub fn clear(&self) {
+        if let Ok(mut entry) = self.entry.lock() {
+            *entry = None;
+        }
+    }
+}
+
+/// Reads the engine's `--check-deps` output.
+pub fn parse(stdout: &str) -> Result<Vec<DependencyStatus>, String> {
+    serde_json::from_str::<Report>(engine::last_json_object(stdout))
+        .map(|report| report.dependencies)
+        .map_err(|e| format!("Failed to parse engine dependency check: {}", e))
+}
+
+/// One message naming every missing dependency, or None when they're all installed.
+pub fn install_message(dependencies: &[DependencyStatus]) -> Option<String> {
+    let missing: Vec<&str> = dependencies
+        .iter()
+        .filter(|dependency| !dependency.installed)
+        .map(|dependency| dependency.name.as_str())
+        .collect();
+    (!missing.is_empty()).then(|| {
+        format!(
+            "The engine needs these Python packages, which the selected interpreter can't import: {}. Install them (e.g. `python -m pip install {}`) or choose another interpreter.",
+            missing.join(", "),
+            missing.join(" ")
+        )
+    })
+}
+
+async fn query(
+    script: &Path,
+    interpreter: &Interpreter,
+    working_dir: &Path,
+) -> Result<Vec<DependencyStatus>, String> {
+    let mut command = interpreter.command();
+    command
+        .current_dir(working_dir)
+        .arg(script)
+        .arg("--check-deps");
+    let options = RunOptions {
+        timeout: Some(QUERY_TIMEOUT),
+        ..RunOptions::default()
+    };
+    // Its own map, so cancelling a simulation doesn't cancel the query
+    let output = engine::run_engine(command, options, &ActiveChildren::default(), |_| {})
+        .await
+        .map_err(|e| e.to_string())?;
+    if !output.status.success() {
+        // Older engines reject the flag as an unrecognized argument
+        return Err(format!(
+            "Engine did not report its dependencies: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        ));
+    }
+    parse(engine::decode_output(&output.stdout)?)
+}
@@ -0,0 +1,115 @@
+use crate::models::{AmountChange, Invoice, ResultDiff, SimulationResult};
+use std::collections::BTreeMap;
+
+// Amounts are rounded to cents, so anything smaller is float noise rather than a change
+const HALF_CENT: f64 = 0.005;
+
+/// Compares two runs' invoices by invoice number and their revenue by customer.
+/// Invoice numbers and customers are listed in sorted order.
+pub fn diff(a: &SimulationResult, b: &SimulationResult) -> ResultDiff {
+    let before = by_number(&a.invoices);
+    let after = by_number(&b.invoices);
+
+    let added = after
+        .keys()
+        .filter(|number| !before.contains_key(*number))
+        .map(|number| number.to_string())
+        .collect();
+    let removed = before
+        .keys()
+        .filter(|number| !after.contains_key(*number))
+        .map(|number| number.to_string())
+        .collect();
+    let changed = before
+        .iter()
+        .filter_map(|(number, old)| {
+            after
+                .get(number)
+                .and_then(|new| change(number, old.total, new.total))
+        })
+        .collect();
+
+    let total = |invoices: &[Invoice]| invoices.iter().map(|invoice| invoice.total).sum::<f64>();
+    ResultDiff {
+        added,
+        removed,
+        changed,
+        revenue_delta: total(&b.invoices) - total(&a.invoices),
+        customers: customer_changes(&a.invoices, &b.invoices),
+    }
+}
+
+// Invoice numbers should be unique within a run; if not, the last one wins
+fn by_number(invoices: &[Invoice]) -> BTreeMap<&str, &Invoice> {
+    invoices
+        .iter()
+        .map(|invoice| (invoice.invoice_number.as_str(), invoice))
+        .collect()
+}
+
+fn customer_changes(a: &[Invoice], b: &[Invoice]) -> Vec<AmountChange> {
+    // Customer -> (revenue in a, revenue in b)
+    let mut revenue: BTreeMap<&str, (f64, f64)> = BTreeMap::new();
+    for invoice in a {
+        revenue.entry(invoice.customer_name()).or_default().0 += invoice.total;
+    }
+    for invoice in b {
+        revenue.entry(invoice.customer_name()).or_default().1 += invoice.total;
+    }
+    revenue
+        .into_iter()
+        .filter_map(|(customer, (before, after))| change(customer, before, after))
+        .collect()
+}
+
+fn change(key: &str, before: f64, after: f64) -> Option<AmountChange> {
+    let delta = after - before;
+    (delta.abs() >= HALF_CENT).then(|| AmountChange {
+        key: key.to_string(),
+        before,
+        after,
+        delta,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn result() -> SimulationResult {
+        serde_json::from_str(include_str!("../../backend/test/test_output.json")).unwrap()
+    }
+
+    #[test]
+    fn reports_added_removed_and_changed_invoices() {
+        let before = result();
+        let mut after = result();
+        after.invoices.remove(0);
+        after.invoices[0].total += 100.0;
+        // Rounding noise isn't a change
+        after.invoices[1].total += 0.001;
+        let mut added = result().invoices.remove(10);
+        added.invoice_number = "GST/FY24-25/09999".to_string();
+        after.invoices.push(added);
+
+        let diff = diff(&before, &after);
+        assert_eq!(diff.added, ["GST/FY24-25/09999"]);
+        assert_eq!(diff.removed, ["GST/FY24-25/03572"]);
+        assert_eq!(diff.changed.len(), 1);
+        assert_eq!(diff.changed[0].key, "GST/FY24-25/03573");
+        assert!((diff.changed[0].delta - 100.0).abs() < 1e-9);
+        assert!((diff.revenue_delta - (100.0 + 36529.18 - 66563.86)).abs() < 0.01);
+
+        let customers: Vec<(&str, f64)> = diff
+            .customers
+            .iter()
+            .map(|change| (change.key.as_str(), change.after))
+            .collect();
+        assert_eq!(customers.len(), 3);
+        assert_eq!(customers[0], ("Prateek Marketing Corporation", 0.0));
+        // Saraswati Limited already had two invoices
+        assert_eq!(customers[1].0, "Saraswati Limited");
+        assert!((customers[1].1 - (56719.65 + 2.0 * 36529.18)).abs() < 0.01);
+        assert_eq!(customers[2].0, "Surya Ventures India Pvt Ltd");
+    }
+}
@@ -0,0 +1,276 @@
+use crate::models::{InvoiceCountEstimate, SimulationConfig};
+use crate::validation;
+use chrono::{Datelike, Duration, NaiveDate, Weekday};
+use std::f64::consts::PI;
+
+/// The spreads `engine.py` knows, mirrored from its `DISTRIBUTION_MODES`.
+pub const DISTRIBUTION_MODES: &[&str] = &[
+    "uniform",
+    "front_loaded",
+    "back_loaded",
+    "weekday_only",
+    "seasonal",
+];
+
+// What older frontends and saved configs call the same spreads
+const ALIASES: &[(&str, &str)] = &[
+    ("even", "uniform"),
+    ("weighted", "back_loaded"),
+    ("burst", "front_loaded"),
+];
+
+// Kept in step with FRONT_LOADED_SHARE and FRONT_LOADED_SPAN in engine.py
+const FRONT_LOADED_SHARE: f64 = 0.8;
+const FRONT_LOADED_SPAN: f64 = 0.2;
+
+// The engine's auto mode never plans more than this many invoices per day
+const MAX_INVOICES_PER_DAY: u32 = 5;
+
+/// Maps a mode, or one of its legacy names, to the name in `DISTRIBUTION_MODES`.
+pub fn canonical(mode: &str) -> Option<&'static str> {
+    DISTRIBUTION_MODES
+        .iter()
+        .copied()
+        .find(|known| *known == mode)
+        .or_else(|| {
+            ALIASES
+                .iter()
+                .find(|(alias, _)| *alias == mode)
+                .map(|(_, known)| *known)
+        })
+}
+
+/// The number of invoices the engine will generate on each day of the range, without
+/// running it. 'uniform' is spaced deterministically so its counts are exact; the other
+/// modes draw days at random and their counts are the expected share, rounded so they
+/// still add up to the invoice count. An adjustment invoice the engine may add to reach
+/// the revenue target isn't included.
+pub fn preview(config: &SimulationConfig) -> Result<Vec<(String, u32)>, String> {
+    validation::validate(config)?;
+    let start = validation::parse_date("start_date", &config.start_date)?;
+    let end = validation::parse_date("end_date", &config.end_date)?;
+    let days: Vec<NaiveDate> = (0..=(end - start).num_days())
+        .map(|offset| start + Duration::days(offset))
+        .collect();
+    let count = invoice_count(config, days.len() as u32);
+    let mode = config
+        .distribution_mode
+        .as_deref()
+        .and_then(canonical)
+        .unwrap_or("uniform");
+
+    let counts = if mode == "uniform" {
+        uniform_counts(count, days.len())
+    } else {
+        apportion(count, &day_weights(mode, &days))
+    };
+    Ok(days
+        .iter()
+        .map(|day| day.format("%Y-%m-%d").to_string())
+        .zip(counts)
+        .collect())
+}
+
+/// Estimates how many invoices a config will produce without running the engine. A
+/// manual count is exact; otherwise the revenue target is divided by the midpoint invoice
+/// amount, and by the midpoint moved `reality_buffer` either way for the range. All three
+/// respect the engine's cap of five invoices a day.
+pub fn estimate(config: &SimulationConfig) -> Result<InvoiceCountEstimate, String> {
+    validation::validate(config)?;
+    let start = validation::parse_date("start_date", &config.start_date)?;
+    let end = validation::parse_date("end_date", &config.end_date)?;
+    let days = (end - start).num_days() as u32 + 1;
+    let expected = invoice_count(config, days);
+    if manual_count(config).is_some() {
+        return Ok(InvoiceCountEstimate {
+            low: expected,
+            expected,
+            high: expected,
+        });
+    }
+
+    let buffer = config
+        .reality_buffer
+        .unwrap_or(validation::DEFAULT_REALITY_BUFFER);
+    let midpoint = midpoint(config);
+    let cap = days * MAX_INVOICES_PER_DAY;
+    let count = |average: f64| ((config.revenue_target / average).ceil() as u32).clamp(1, cap);
+    Ok(InvoiceCountEstimate {
+        low: count(midpoint * (1.0 + buffer)).min(expected),
+        expected,
+        // A buffer of 1 would mean invoices of nothing, so the range stops short of it
+        high: count(midpoint * (1.0 - buffer).max(f64::EPSILON)).max(expected),
+    })
+}
+
+/// How many invoices it would take to reach `revenue_target` at the midpoint invoice
+/// amount, or the manual count. Unlike `estimate` this isn't held to the engine's five a
+/// day, past which the engine makes up the rest with one outsized adjustment invoice.
+pub fn needed(config: &SimulationConfig) -> u64 {
+    match manual_count(config) {
+        Some(count) => count as u64,
+        None => (config.revenue_target / midpoint(config)).ceil() as u64,
+    }
+}
+
+// Same rule as the start of `InvoiceSimulator.simulate`
+fn invoice_count(config: &SimulationConfig, days: u32) -> u32 {
+    match manual_count(config) {
+        Some(count) => count,
+        None => {
+            let estimate = (config.revenue_target / midpoint(config)).ceil() as u32;
+            estimate.min(days * MAX_INVOICES_PER_DAY)
+        }
+    }
+}
+
+fn manual_count(config: &SimulationConfig) -> Option<u32> {
+    let manual = config.invoice_count_mode.as_deref() == Some("manual");
+    config
+        .manual_invoice_count
+        .filter(|count| manual && *count > 0)
+        .map(|count| count as u32)
+}
+
+fn midpoint(config: &SimulationConfig) -> f64 {
+    (config.min_invoice_amount + config.max_invoice_amount) / 2.0
+}
+
+// Invoice i of n sits i/(n-1) of the way through the range, or mid-range on its own
+fn uniform_counts(count: u32, days: usize) -> Vec<u32> {
+    let mut counts = vec![0; days];
+    let span = days as u64 - 1;
+    for i in 0..count as u64 {
+        let day = if count > 1 {
+            i * span / (count as u64 - 1)
+        } else {
+            span / 2
+        };
+        counts[day as usize] += 1;
+    }
+    counts
+}
+
+// Mirrors `_day_weights` in engine.py
+fn day_weights(mode: &str, days: &[NaiveDate]) -> Vec<f64> {
+    let n = days.len();
+    match mode {
+        "front_loaded" => {
+            let cutoff = ((n as f64 * FRONT_LOADED_SPAN) as usize).max(1);
+            if cutoff >= n {
+                return vec![1.0; n];
+            }
+            let early = FRONT_LOADED_SHARE / cutoff as f64;
+            let late = (1.0 - FRONT_LOADED_SHARE) / (n - cutoff) as f64;
+            (0..n)
+                .map(|i| if i < cutoff { early } else { late })
+                .collect()
+        }
+        "back_loaded" => (0..n).map(|i| (i as f64 + 0.5) / n as f64).collect(),
+        "weekday_only" => days
+            .iter()
+            .map(|day| match day.weekday() {
+                Weekday::Sat | Weekday::Sun => 0.0,
+                _ => 1.0,
+            })
+            .collect(),
+        "seasonal" => days
+            .iter()
+            .map(|day| 1.0 + 0.5 * (2.0 * PI * (day.month() as f64 - 12.0) / 12.0).cos())
+            .collect(),
+        _ => vec![1.0; n],
+    }
+}
+
+// Splits `count` in proportion to `weights` by rounding the running total, so a long run
+// of days that each expect a fraction of an invoice still gets its share
+fn apportion(count: u32, weights: &[f64]) -> Vec<u32> {
+    let total: f64 = weights.iter().sum();
+    if total <= 0.0 {
+        return vec![0; weights.len()];
+    }
+    let mut cumulative = 0.0;
+    let mut assigned = 0;
+    weights
+        .iter()
+        .map(|weight| {
+            cumulative += weight;
+            let target = (count as f64 * cumulative / total).round() as u32;
+            let day = target.saturating_sub(assigned);
+            assigned += day;
+            day
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config() -> SimulationConfig {
+        serde_json::from_str(include_str!("../../backend/test/test_config.json")).unwrap()
+    }
+
+    #[test]
+    fn legacy_names_map_to_known_modes() {
+        assert_eq!(canonical("even"), Some("uniform"));
+        assert_eq!(canonical("seasonal"), Some("seasonal"));
+        assert_eq!(canonical("sideways"), None);
+    }
+
+    #[test]
+    fn every_mode_previews_the_same_invoice_count() {
+        // 100000 at a 5500 midpoint
+        for mode in DISTRIBUTION_MODES {
+            let mut config = config();
+            config.distribution_mode = Some(mode.to_string());
+            let days = preview(&config).unwrap();
+            assert_eq!(days.len(), 31);
+            assert_eq!(days.iter().map(|(_, n)| n).sum::<u32>(), 19, "{}", mode);
+        }
+    }
+
+    #[test]
+    fn weekday_only_leaves_weekends_empty() {
+        let mut config = config();
+        config.distribution_mode = Some("weekday_only".to_string());
+        let days = preview(&config).unwrap();
+        let mut weekend_days = 0;
+        for (day, count) in &days {
+            let date = NaiveDate::parse_from_str(day, "%Y-%m-%d").unwrap();
+            if matches!(date.weekday(), Weekday::Sat | Weekday::Sun) {
+                assert_eq!(*count, 0, "{}", day);
+                weekend_days += 1;
+            }
+        }
+        // January 2023 started on a Sunday
+        assert_eq!(weekend_days, 9);
+        assert_eq!(days.iter().map(|(_, n)| n).sum::<u32>(), 19);
+    }
+
+    #[test]
+    fn the_estimate_range_brackets_the_midpoint_count() {
+        // 100000 at 5500, and at 10% either side of it
+        let estimate = estimate(&config()).unwrap();
+        assert_eq!(
+            (estimate.low, estimate.expected, estimate.high),
+            (17, 19, 21)
+        );
+
+        let mut wide = config();
+        wide.reality_buffer = Some(1.0);
+        let estimate = super::estimate(&wide).unwrap();
+        assert_eq!((estimate.low, estimate.expected), (10, 19));
+        // Five a day for 31 days
+        assert_eq!(estimate.high, 155);
+
+        let mut manual = config();
+        manual.invoice_count_mode = Some("manual".to_string());
+        manual.manual_invoice_count = Some(40);
+        let estimate = super::estimate(&manual).unwrap();
+        assert_eq!(
+            (estimate.low, estimate.expected, estimate.high),
+            (40, 40, 40)
+        );
+    }
+}
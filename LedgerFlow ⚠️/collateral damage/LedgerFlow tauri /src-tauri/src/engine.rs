@@ -0,0 +1,562 @@
+use crate::error::SimulationError;
+use std::collections::HashMap;
+use std::fs;
+use std::future;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Output, Stdio};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::time::Duration;
+use tokio::io::{AsyncBufReadExt, AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt, BufReader};
+use tokio::process::Child;
+use tokio::task::JoinHandle;
+use tokio::time::{self, Instant};
+use tokio_util::sync::CancellationToken;
+
+const POLL_INTERVAL: Duration = Duration::from_millis(50);
+// Spawn retries wait 100ms, 200ms, 400ms, ... up to this cap
+const INITIAL_BACKOFF: Duration = Duration::from_millis(100);
+const MAX_BACKOFF: Duration = Duration::from_secs(5);
+
+// Engine stdout protocol: `PROGRESS: <percent>` lines may appear at any point, and the
+// JSON payload follows a `RESULT:` sentinel. Older engines that print bare JSON still
+// work because without a sentinel every non-progress line is treated as payload.
+const PROGRESS_PREFIX: &[u8] = b"PROGRESS:";
+const RESULT_SENTINEL: &[u8] = b"RESULT:";
+// stderr lines the engine means for the user; everything else there is debug output
+const WARNING_PREFIX: &str = "WARNING:";
+
+/// Used when a config leaves `max_output_bytes` out. Far beyond any real run, but low
+/// enough that a runaway engine is stopped before it exhausts memory or disk.
+pub const DEFAULT_MAX_OUTPUT_BYTES: u64 = 512 * 1024 * 1024;
+
+/// The flags the app passes the engine itself to hand over the run's config and catalog
+/// and collect its result.
+pub const RESERVED_ARGS: &[&str] = &["--config", "--catalog", "--output", "--stdin"];
+
+/// Checks that the engine will be able to write its result to `path`, a config's
+/// `output_path`, by creating and removing a probe file next to it. Fails for a path
+/// that's a directory or whose directory is missing or read-only.
+pub fn check_output_path(path: &Path) -> Result<(), String> {
+    if path.is_dir() {
+        return Err(format!(
+            "output_path {} is a directory, not a file",
+            path.display()
+        ));
+    }
+    let dir = match path.parent() {
+        Some(dir) if !dir.as_os_str().is_empty() => dir,
+        _ => Path::new("."),
+    };
+    if !dir.is_dir() {
+        return Err(format!(
+            "output_path directory {} does not exist",
+            dir.display()
+        ));
+    }
+    let probe = dir.join(format!(".ledgerflow_write_check_{}", std::process::id()));
+    fs::File::create(&probe).map_err(|e| {
+        format!(
+            "output_path directory {} is not writable: {}",
+            dir.display(),
+            e
+        )
+    })?;
+    let _ = fs::remove_file(&probe);
+    Ok(())
+}
+
+/// Checks a config's `extra_engine_args`, which are appended after the app's own
+/// arguments. Anything starting with one of `RESERVED_ARGS`, `--output=x` included, is
+/// rejected since it would override where the run's data comes from or goes.
+pub fn check_extra_args(args: &[String]) -> Result<(), String> {
+    match args
+        .iter()
+        .find(|arg| RESERVED_ARGS.iter().any(|reserved| arg.starts_with(reserved)))
+    {
+        Some(arg) => Err(format!(
+            "extra_engine_args can't include '{}': {} are set by LedgerFlow to pass the run's config, catalog and result, and extra arguments are appended after them",
+            arg,
+            RESERVED_ARGS.join(", ")
+        )),
+        None => Ok(()),
+    }
+}
+
+// Cancellation tokens for the engine runs in flight keyed by pid, shared with
+// `cancel_simulation`. Batch runs can have several at once; each run kills its own engine
+// when its token fires.
+pub type ActiveChildren = Arc<Mutex<HashMap<u32, CancellationToken>>>;
+
+/// Lets only one simulation command run at a time, so a double-clicked Run doesn't start
+/// two engines that race each other. A batch takes it once for all of its runs.
+#[derive(Debug, Default)]
+pub struct RunLock(AtomicBool);
+
+impl RunLock {
+    /// Takes the lock until the returned guard is dropped, or returns None while another
+    /// run holds it.
+    pub fn try_acquire(&self) -> Option<RunGuard<'_>> {
+        self.0
+            .compare_exchange(false, true, Ordering::Acquire, Ordering::Relaxed)
+            .ok()
+            .map(|_| RunGuard(&self.0))
+    }
+}
+
+pub struct RunGuard<'a>(&'a AtomicBool);
+
+impl Drop for RunGuard<'_> {
+    fn drop(&mut self) {
+        self.0.store(false, Ordering::Release);
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct RunOptions {
+    /// Written to the engine's stdin, which is then closed. stdin is null when None.
+    pub input: Option<Vec<u8>>,
+    /// Kill the engine once this elapses.
+    pub timeout: Option<Duration>,
+    /// How many more times to try if the process can't be spawned at all (e.g. ENOENT
+    /// or EAGAIN). An engine that starts and then fails is never retried.
+    pub max_retries: u32,
+    /// Kill the engine once its stdout payload, or the file at `output_file`, grows past
+    /// this many bytes. No limit when None.
+    pub max_output_bytes: Option<u64>,
+    /// Where the engine writes its result, if not to stdout.
+    pub output_file: Option<PathBuf>,
+}
+
+/// Spawns the engine and waits for it, as configured by `options`. Must be awaited
+/// inside a Tokio runtime.
+///
+/// Progress lines are passed to `on_progress` as they arrive and stripped from the
+/// returned stdout, which holds only the result payload.
+///
+/// An engine that exits before reading all of its input is reported as an error along
+/// with its stderr, rather than as a bare broken pipe.
+///
+/// stdout and stderr are drained on their own tasks so a chatty engine can't block on a
+/// full pipe, and both are read to EOF before a normal exit returns. When the child is
+/// killed (on cancel, timeout or too much output) those tasks are left to finish on
+/// their own in case a grandchild process is holding the write end open. Dropping the
+/// returned future kills the engine too.
+///
+/// Output past `max_output_bytes` is checked as it's read from stdout and every
+/// `POLL_INTERVAL` for the output file, and fails the run with `OutputTooLarge`.
+pub async fn run_engine<F>(
+    cmd: Command,
+    options: RunOptions,
+    active: &ActiveChildren,
+    on_progress: F,
+) -> Result<Output, SimulationError>
+where
+    F: FnMut(f64) + Send + 'static,
+{
+    let RunOptions {
+        input,
+        timeout,
+        max_retries,
+        max_output_bytes,
+        output_file,
+    } = options;
+    let stdin = if input.is_some() {
+        Stdio::piped()
+    } else {
+        Stdio::null()
+    };
+    let mut cmd = tokio::process::Command::from(cmd);
+    cmd.stdin(stdin)
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .kill_on_drop(true);
+    let mut child = spawn(&mut cmd, max_retries).await?;
+
+    let writer = input.map(|input| write_stdin(child.stdin.take(), input));
+    let overflowed = Arc::new(AtomicBool::new(false));
+    let stdout = read_stdout(
+        child.stdout.take(),
+        on_progress,
+        max_output_bytes,
+        Arc::clone(&overflowed),
+    );
+    let too_large = || {
+        overflowed.load(Ordering::Relaxed)
+            || max_output_bytes.is_some_and(|limit| {
+                output_file
+                    .as_ref()
+                    .and_then(|path| fs::metadata(path).ok())
+                    .is_some_and(|metadata| metadata.len() > limit)
+            })
+    };
+    let stderr = drain(child.stderr.take());
+    let token = CancellationToken::new();
+    let _registered = Registered::new(active, child.id(), token.clone());
+
+    let deadline = timeout.map(|limit| Instant::now() + limit);
+    let timed_out = async {
+        match deadline {
+            Some(deadline) => time::sleep_until(deadline).await,
+            None => future::pending().await,
+        }
+    };
+    tokio::pin!(timed_out);
+    let mut poll = time::interval(POLL_INTERVAL);
+    let status = loop {
+        tokio::select! {
+            status = child.wait() => match status {
+                Ok(status) => break status,
+                Err(e) => {
+                    kill(&mut child).await;
+                    return Err(SimulationError::Internal(format!(
+                        "Failed to wait for Python engine: {}",
+                        e
+                    )));
+                }
+            },
+            () = token.cancelled() => {
+                kill(&mut child).await;
+                return Err(SimulationError::Cancelled);
+            }
+            () = &mut timed_out => {
+                kill(&mut child).await;
+                return Err(SimulationError::Timeout {
+                    seconds: timeout.unwrap_or_default().as_secs(),
+                });
+            }
+            _ = poll.tick() => {
+                if let Some(limit) = max_output_bytes.filter(|_| too_large()) {
+                    kill(&mut child).await;
+                    return Err(SimulationError::OutputTooLarge { limit });
+                }
+            }
+        }
+    };
+
+    let output = Output {
+        status,
+        stdout: stdout.await.unwrap_or_default(),
+        stderr: stderr.await.unwrap_or_default(),
+    };
+    // Whatever was written between the last poll and the exit
+    if let Some(limit) = max_output_bytes.filter(|_| too_large()) {
+        return Err(SimulationError::OutputTooLarge { limit });
+    }
+    let written = match writer {
+        Some(handle) => handle.await.ok(),
+        None => None,
+    };
+    match written {
+        Some(Err(e)) if e.kind() == io::ErrorKind::BrokenPipe => {
+            Err(SimulationError::EngineFailed {
+                code: output.status.code(),
+                stderr: format!(
+                    "exited before reading its input: {}",
+                    String::from_utf8_lossy(&output.stderr).trim()
+                ),
+            })
+        }
+        Some(Err(e)) => Err(SimulationError::Internal(format!(
+            "Failed to send input to Python engine: {}",
+            e
+        ))),
+        _ => Ok(output),
+    }
+}
+
+// A run's entry in `ActiveChildren`, removed however the run ends
+struct Registered<'a> {
+    active: &'a ActiveChildren,
+    pid: Option<u32>,
+}
+
+impl<'a> Registered<'a> {
+    fn new(active: &'a ActiveChildren, pid: Option<u32>, token: CancellationToken) -> Self {
+        if let Some(pid) = pid {
+            lock(active).insert(pid, token);
+        }
+        Registered { active, pid }
+    }
+}
+
+impl Drop for Registered<'_> {
+    fn drop(&mut self) {
+        if let Some(pid) = self.pid {
+            lock(self.active).remove(&pid);
+        }
+    }
+}
+
+async fn spawn(
+    cmd: &mut tokio::process::Command,
+    max_retries: u32,
+) -> Result<Child, SimulationError> {
+    let mut attempt = 0;
+    loop {
+        match cmd.spawn() {
+            Ok(child) => return Ok(child),
+            Err(e) if attempt < max_retries => {
+                let delay = backoff(attempt);
+                tracing::warn!(error = %e, retry_in = ?delay, "Failed to start Python engine");
+                time::sleep(delay).await;
+                attempt += 1;
+            }
+            Err(e) => {
+                return Err(SimulationError::EngineNotFound(format!(
+                    "Failed to execute Python engine: {}",
+                    e
+                )))
+            }
+        }
+    }
+}
+
+fn backoff(attempt: u32) -> Duration {
+    INITIAL_BACKOFF
+        .saturating_mul(2u32.saturating_pow(attempt))
+        .min(MAX_BACKOFF)
+}
+
+/// The `WARNING:` lines from the engine's stderr, without the prefix. Other lines are
+/// debug chatter and dropped.
+pub fn stderr_warnings(stderr: &[u8]) -> Vec<String> {
+    String::from_utf8_lossy(stderr)
+        .lines()
+        .filter_map(|line| line.trim().strip_prefix(WARNING_PREFIX))
+        .map(|warning| warning.trim().to_string())
+        .filter(|warning| !warning.is_empty())
+        .collect()
+}
+
+/// Checks that the engine's result is valid UTF-8. A lossy conversion would quietly turn
+/// bad bytes in names or amounts into U+FFFD, so this fails instead and says where.
+///
+/// A leading byte-order mark and whitespace are dropped: some Windows Python setups
+/// start stdout with a BOM, which serde_json would otherwise reject as "expected value at
+/// line 1".
+pub fn decode_output(bytes: &[u8]) -> Result<&str, String> {
+    let text = std::str::from_utf8(bytes).map_err(|e| {
+        format!(
+            "Engine produced non-UTF-8 output: invalid byte sequence at offset {}",
+            e.valid_up_to()
+        )
+    })?;
+    Ok(text.trim_start_matches('\u{feff}').trim_start())
+}
+
+/// Returns the last complete top-level JSON object in `text` when it holds more than one,
+/// as when an engine prints debug objects before its result. Otherwise `text` is
+/// returned whole, so a single payload is parsed as strictly as before.
+pub fn last_json_object(text: &str) -> &str {
+    let mut objects = 0;
+    let mut last = text;
+    let mut depth = 0usize;
+    let mut start = 0;
+    let mut in_string = false;
+    let mut escaped = false;
+    for (i, byte) in text.bytes().enumerate() {
+        if in_string {
+            match byte {
+                _ if escaped => escaped = false,
+                b'\\' => escaped = true,
+                b'"' => in_string = false,
+                _ => {}
+            }
+            continue;
+        }
+        match byte {
+            // Quotes outside an object are log text, not JSON strings
+            b'"' if depth > 0 => in_string = true,
+            b'{' => {
+                if depth == 0 {
+                    start = i;
+                }
+                depth += 1;
+            }
+            b'}' if depth > 0 => {
+                depth -= 1;
+                if depth == 0 {
+                    objects += 1;
+                    last = &text[start..=i];
+                }
+            }
+            _ => {}
+        }
+    }
+    if objects > 1 {
+        last
+    } else {
+        text
+    }
+}
+
+/// Cancels every engine in flight, returning false if nothing was running. Each run
+/// kills its engine and returns `Cancelled`.
+pub fn cancel(active: &ActiveChildren) -> bool {
+    let tokens: Vec<CancellationToken> = lock(active).drain().map(|(_, token)| token).collect();
+    for token in &tokens {
+        token.cancel();
+    }
+    !tokens.is_empty()
+}
+
+async fn kill(child: &mut Child) {
+    // Also reaps it, so no zombie is left behind
+    if let Err(e) = child.kill().await {
+        tracing::warn!(error = %e, "Failed to kill Python engine");
+    }
+}
+
+fn lock(active: &ActiveChildren) -> MutexGuard<'_, HashMap<u32, CancellationToken>> {
+    // A panic while holding the map doesn't leave the tokens in a bad state
+    active
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner())
+}
+
+fn write_stdin<W: AsyncWrite + Unpin + Send + 'static>(
+    pipe: Option<W>,
+    input: Vec<u8>,
+) -> JoinHandle<io::Result<()>> {
+    // Dropping the pipe at the end closes stdin so the engine sees EOF
+    tokio::spawn(async move {
+        match pipe {
+            Some(mut pipe) => pipe.write_all(&input).await,
+            None => Ok(()),
+        }
+    })
+}
+
+// Stops keeping payload once it passes `limit` and raises `overflowed` instead, still
+// reading to EOF so the engine isn't left blocked on a full pipe until it's killed
+fn read_stdout<R, F>(
+    pipe: Option<R>,
+    mut on_progress: F,
+    limit: Option<u64>,
+    overflowed: Arc<AtomicBool>,
+) -> JoinHandle<Vec<u8>>
+where
+    R: AsyncRead + Unpin + Send + 'static,
+    F: FnMut(f64) + Send + 'static,
+{
+    tokio::spawn(async move {
+        let mut payload = Vec::new();
+        let Some(pipe) = pipe else {
+            return payload;
+        };
+
+        let mut reader = BufReader::new(pipe);
+        let mut line = Vec::new();
+        let mut in_result = false;
+        loop {
+            line.clear();
+            match reader.read_until(b'\n', &mut line).await {
+                Ok(0) | Err(_) => break,
+                Ok(_) => {}
+            }
+
+            if overflowed.load(Ordering::Relaxed) {
+                continue;
+            }
+            if in_result {
+                payload.extend_from_slice(&line);
+            } else if let Some(rest) = line.strip_prefix(PROGRESS_PREFIX) {
+                match parse_progress(rest) {
+                    Some(percent) => on_progress(percent),
+                    None => tracing::warn!(
+                        line = %String::from_utf8_lossy(&line).trim_end(),
+                        "Ignoring malformed progress line"
+                    ),
+                }
+            } else if let Some(rest) = line.strip_prefix(RESULT_SENTINEL) {
+                // Anything printed before the sentinel was debug chatter, not payload
+                in_result = true;
+                payload.clear();
+                payload.extend_from_slice(rest);
+            } else {
+                payload.extend_from_slice(&line);
+            }
+            if limit.is_some_and(|limit| payload.len() as u64 > limit) {
+                overflowed.store(true, Ordering::Relaxed);
+                payload = Vec::new();
+            }
+        }
+        payload
+    })
+}
+
+fn parse_progress(rest: &[u8]) -> Option<f64> {
+    let text = std::str::from_utf8(rest).ok()?.trim().trim_end_matches('%');
+    let percent: f64 = text.parse().ok()?;
+    percent.is_finite().then(|| percent.clamp(0.0, 100.0))
+}
+
+fn drain<R: AsyncRead + Unpin + Send + 'static>(pipe: Option<R>) -> JoinHandle<Vec<u8>> {
+    tokio::spawn(async move {
+        let mut buf = Vec::new();
+        if let Some(mut pipe) = pipe {
+            let _ = pipe.read_to_end(&mut buf).await;
+        }
+        buf
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn shell(script: &str) -> Command {
+        let mut command = Command::new("sh");
+        command.arg("-c").arg(script);
+        command
+    }
+
+    #[tokio::test]
+    async fn kills_an_engine_that_runs_past_its_timeout() {
+        let options = RunOptions {
+            timeout: Some(Duration::from_secs(1)),
+            ..RunOptions::default()
+        };
+        let started = Instant::now();
+        let active = ActiveChildren::default();
+        let error = run_engine(shell("echo started; sleep 10"), options, &active, |_| {})
+            .await
+            .unwrap_err();
+        assert!(
+            matches!(error, SimulationError::Timeout { seconds: 1 }),
+            "{}",
+            error
+        );
+        assert!(started.elapsed() < Duration::from_secs(5));
+        assert!(active.lock().unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn cancel_stops_the_engine_in_flight() {
+        let active = ActiveChildren::default();
+        assert!(!cancel(&active));
+        let run = tokio::spawn({
+            let active = active.clone();
+            async move { run_engine(shell("sleep 10"), RunOptions::default(), &active, |_| {}).await }
+        });
+        while active.lock().unwrap().is_empty() {
+            time::sleep(Duration::from_millis(10)).await;
+        }
+        assert!(cancel(&active));
+        let error = run.await.unwrap().unwrap_err();
+        assert!(matches!(error, SimulationError::Cancelled), "{}", error);
+        assert!(!cancel(&active));
+    }
+
+    #[test]
+    fn run_lock_admits_one_run_at_a_time() {
+        let lock = RunLock::default();
+        let guard = lock.try_acquire();
+        assert!(guard.is_some());
+        assert!(lock.try_acquire().is_none());
+        drop(guard);
+        assert!(lock.try_acquire().is_some());
+    }
+}
@@ -0,0 +1,82 @@
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+const LOG_NAME: &str = "engine";
+// engine.log plus engine.1.log .. engine.4.log
+const MAX_LOG_FILES: usize = 5;
+const MAX_LOG_BYTES: u64 = 1024 * 1024;
+const ENTRY_MARKER: &str = "=== ";
+
+fn log_path(dir: &Path, index: usize) -> PathBuf {
+    if index == 0 {
+        dir.join(format!("{}.log", LOG_NAME))
+    } else {
+        dir.join(format!("{}.{}.log", LOG_NAME, index))
+    }
+}
+
+/// Shifts engine.log -> engine.1.log -> ... once the current file is over the size cap,
+/// dropping the oldest so at most `MAX_LOG_FILES` remain.
+fn rotate(dir: &Path) -> std::io::Result<()> {
+    let current = log_path(dir, 0);
+    match fs::metadata(&current) {
+        Ok(meta) if meta.len() >= MAX_LOG_BYTES => {}
+        _ => return Ok(()),
+    }
+
+    let oldest = log_path(dir, MAX_LOG_FILES - 1);
+    if oldest.exists() {
+        fs::remove_file(&oldest)?;
+    }
+    for index in (0..MAX_LOG_FILES - 1).rev() {
+        let from = log_path(dir, index);
+        if from.exists() {
+            fs::rename(&from, log_path(dir, index + 1))?;
+        }
+    }
+    Ok(())
+}
+
+/// Appends one run's engine output to the log, tagged with a timestamp and the seed.
+pub fn append(dir: &Path, seed: Option<i32>, stdout: &[u8], stderr: &[u8]) -> std::io::Result<()> {
+    fs::create_dir_all(dir)?;
+    rotate(dir)?;
+
+    let seed = seed.map_or_else(|| "none".to_string(), |s| s.to_string());
+    let mut file = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(log_path(dir, 0))?;
+    writeln!(
+        file,
+        "{}{} seed={}",
+        ENTRY_MARKER,
+        chrono::Local::now().format("%Y-%m-%d %H:%M:%S"),
+        seed
+    )?;
+    writeln!(file, "--- stdout")?;
+    file.write_all(stdout)?;
+    writeln!(file)?;
+    writeln!(file, "--- stderr")?;
+    file.write_all(stderr)?;
+    writeln!(file)?;
+    Ok(())
+}
+
+/// Returns the most recent run's entry, or an empty string if nothing has been logged.
+pub fn last_entry(dir: &Path) -> Result<String, String> {
+    let path = log_path(dir, 0);
+    if !path.exists() {
+        return Ok(String::new());
+    }
+    let bytes = fs::read(&path).map_err(|e| format!("Failed to read engine log: {}", e))?;
+    let text = String::from_utf8_lossy(&bytes);
+    let start = text
+        .match_indices(ENTRY_MARKER)
+        .map(|(i, _)| i)
+        .filter(|&i| i == 0 || text.as_bytes()[i - 1] == b'\n')
+        .last()
+        .unwrap_or(0);
+    Ok(text[start..].to_string())
+}
@@ -0,0 +1,179 @@
+use crate::engine;
+use crate::models::SimulationResult;
+use crate::output_schema;
+use flate2::bufread::GzDecoder;
+use serde_json::Value;
+use std::fs::File;
+use std::io::{BufRead, BufReader, Read, Seek};
+use std::path::Path;
+
+// Every gzip stream starts with these two bytes, and JSON text never does
+const GZIP_MAGIC: [u8; 2] = [0x1f, 0x8b];
+
+/// Reads the engine's result file, decompressing it first if the engine gzipped it.
+/// Engines that predate compression write plain JSON, which is read as-is.
+///
+/// More than `max_bytes` of JSON is an error. The limit applies after decompression, since
+/// a small gzip file can expand to far more than the engine was allowed to write.
+pub fn read(path: &Path, max_bytes: u64) -> Result<SimulationResult, String> {
+    let file = File::open(path).map_err(|e| format!("Failed to read engine output file: {}", e))?;
+    parse(file, max_bytes)
+}
+
+/// True if the file at `path` starts with the gzip magic bytes.
+pub fn is_compressed(path: &Path) -> bool {
+    let mut magic = [0; 2];
+    File::open(path)
+        .and_then(|mut file| file.read_exact(&mut magic))
+        .is_ok_and(|()| magic == GZIP_MAGIC)
+}
+
+/// Deserializes a result straight from `source`, so a large one is only ever held once.
+/// Output that doesn't parse that way is read again in full to say why: a bad UTF-8 byte
+/// by its offset, a format change by the fields it broke, or debug objects printed before
+/// the result, in which case the last object is used after all.
+pub fn parse<R: Read + Seek>(mut source: R, max_bytes: u64) -> Result<SimulationResult, String> {
+    if let Some(result) = stream(BufReader::new(&mut source), max_bytes)? {
+        return Ok(result);
+    }
+    source
+        .rewind()
+        .map_err(|e| format!("Failed to read engine output file: {}", e))?;
+    diagnose(BufReader::new(source), max_bytes)
+}
+
+// `None` when the output didn't deserialize and needs `diagnose`
+fn stream<R: BufRead>(mut reader: R, max_bytes: u64) -> Result<Option<SimulationResult>, String> {
+    // One byte over the limit is enough to know it was exceeded
+    let limit = max_bytes.saturating_add(1);
+    let (parsed, unread) = if compressed(&mut reader)? {
+        let mut limited = BufReader::new(GzDecoder::new(reader)).take(limit);
+        (serde_json::from_reader(&mut limited), limited.limit())
+    } else {
+        let mut limited = reader.take(limit);
+        (serde_json::from_reader(&mut limited), limited.limit())
+    };
+    if unread == 0 {
+        return Err(too_large(max_bytes));
+    }
+    Ok(parsed.ok())
+}
+
+fn diagnose<R: BufRead>(mut reader: R, max_bytes: u64) -> Result<SimulationResult, String> {
+    let mut bytes = Vec::new();
+    let read = if compressed(&mut reader)? {
+        GzDecoder::new(reader)
+            .take(max_bytes.saturating_add(1))
+            .read_to_end(&mut bytes)
+    } else {
+        reader
+            .take(max_bytes.saturating_add(1))
+            .read_to_end(&mut bytes)
+    };
+    read.map_err(|e| format!("Failed to read engine output file: {}", e))?;
+    if bytes.len() as u64 > max_bytes {
+        return Err(too_large(max_bytes));
+    }
+    // Decoded up front so a bad byte is reported as such rather than as a JSON error
+    let text = engine::decode_output(&bytes)?;
+    let output: Value = serde_json::from_str(engine::last_json_object(text))
+        .map_err(|e| format!("Failed to parse engine output: {}", e))?;
+    // Names the offending fields where serde would only give a line and column
+    output_schema::validate(&output)?;
+    serde_json::from_value(output).map_err(|e| format!("Failed to parse engine output: {}", e))
+}
+
+fn compressed<R: BufRead>(reader: &mut R) -> Result<bool, String> {
+    Ok(reader
+        .fill_buf()
+        .map_err(|e| format!("Failed to read engine output file: {}", e))?
+        .starts_with(&GZIP_MAGIC))
+}
+
+fn too_large(max_bytes: u64) -> String {
+    format!(
+        "Engine output exceeded the max_output_bytes limit of {} bytes",
+        max_bytes
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use flate2::write::GzEncoder;
+    use flate2::Compression;
+    use std::io::{Cursor, Write};
+
+    const OUTPUT: &str = include_str!("../../backend/test/test_output.json");
+
+    fn parse_bytes(bytes: &[u8]) -> Result<SimulationResult, String> {
+        parse(Cursor::new(bytes), u64::MAX)
+    }
+
+    fn gzip(bytes: &[u8]) -> Vec<u8> {
+        let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+        encoder.write_all(bytes).unwrap();
+        encoder.finish().unwrap()
+    }
+
+    #[test]
+    fn parses_plain_and_gzipped_output_alike() {
+        let plain = parse_bytes(OUTPUT.as_bytes()).unwrap();
+        assert_eq!(plain.invoices.len(), 12);
+        let gzipped = parse_bytes(&gzip(OUTPUT.as_bytes())).unwrap();
+        assert_eq!(
+            serde_json::to_value(&plain).unwrap(),
+            serde_json::to_value(&gzipped).unwrap()
+        );
+    }
+
+    #[test]
+    fn uses_the_last_object_after_debug_output() {
+        let noisy = format!("{{\"debug\": \"warming up\"}}\n{}", OUTPUT);
+        assert_eq!(parse_bytes(noisy.as_bytes()).unwrap().invoices.len(), 12);
+        let with_bom = format!("\u{feff}{}", OUTPUT);
+        assert_eq!(parse_bytes(with_bom.as_bytes()).unwrap().invoices.len(), 12);
+    }
+
+    #[test]
+    fn reports_the_offset_of_a_bad_utf8_byte() {
+        let bytes = b"{\"status\": \"\xC3\x28\", \"invoices\": []}";
+        let error = parse_bytes(bytes).unwrap_err();
+        assert!(error.contains("offset 12"), "{}", error);
+        assert!(parse_bytes(&gzip(bytes)).unwrap_err().contains("offset 12"));
+    }
+
+    #[test]
+    fn names_the_fields_of_a_malformed_payload() {
+        let mut output: Value = serde_json::from_str(OUTPUT).unwrap();
+        output["invoices"][3]["total"] = Value::from("lots");
+        output["invoices"][5]["items"][0]
+            .as_object_mut()
+            .unwrap()
+            .remove("rate");
+        let error = parse_bytes(output.to_string().as_bytes()).unwrap_err();
+        assert!(
+            error.contains("invoices[3].total: expected number, got string"),
+            "{}",
+            error
+        );
+        assert!(
+            error.contains("invoices[5].items[0]: missing field \"rate\""),
+            "{}",
+            error
+        );
+        assert!(parse_bytes(b"{bad")
+            .unwrap_err()
+            .starts_with("Failed to parse engine output"));
+    }
+
+    #[test]
+    fn rejects_output_over_the_limit() {
+        let limit = OUTPUT.len() as u64 - 1;
+        for bytes in [OUTPUT.as_bytes().to_vec(), gzip(OUTPUT.as_bytes())] {
+            let error = parse(Cursor::new(bytes), limit).unwrap_err();
+            assert!(error.contains("max_output_bytes"), "{}", error);
+        }
+        assert!(parse(Cursor::new(OUTPUT.as_bytes()), OUTPUT.len() as u64).is_ok());
+    }
+}
@@ -0,0 +1,75 @@
+use std::ffi::OsString;
+use std::path::PathBuf;
+
+/// Overrides the engine location, e.g. when running a packaged build against a checkout.
+pub const ENGINE_ENV: &str = "LEDGERFLOW_ENGINE";
+/// Where `tauri.conf.json` bundles the engine, relative to the resource directory.
+pub const RESOURCE_PATH: &str = "backend/src/engine.py";
+/// Where the engine lives relative to `src-tauri` during development.
+pub const RELATIVE_PATH: &str = "../backend/src/engine.py";
+
+/// Picks the engine script from, in order, the bundled resource, `$LEDGERFLOW_ENGINE` and
+/// the development-relative path, returning the first that exists.
+///
+/// `resource` is None when the resource directory can't be resolved, and an empty `env`
+/// value is treated as unset. The path returned is absolute, so it still names the script
+/// once the engine runs in another working directory.
+pub fn resolve(resource: Option<PathBuf>, env: Option<OsString>) -> Result<PathBuf, String> {
+    let env = env.filter(|value| !value.is_empty()).map(PathBuf::from);
+    let candidates: Vec<PathBuf> = resource
+        .into_iter()
+        .chain(env)
+        .chain(Some(PathBuf::from(RELATIVE_PATH)))
+        .collect();
+
+    if let Some(found) = candidates.iter().find(|path| path.is_file()) {
+        return Ok(std::path::absolute(found).unwrap_or_else(|_| found.clone()));
+    }
+    let tried: Vec<String> = candidates
+        .iter()
+        .map(|path| path.display().to_string())
+        .collect();
+    Err(format!(
+        "Python engine not found (tried: {})",
+        tried.join(", ")
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    #[test]
+    fn prefers_the_resource_then_the_environment() {
+        let dir = std::env::temp_dir().join(format!("ledgerflow_engine_{}", std::process::id()));
+        let bundled = dir.join("resources").join(RESOURCE_PATH);
+        let checkout = dir.join("checkout").join("engine.py");
+        for script in [&bundled, &checkout] {
+            fs::create_dir_all(script.parent().unwrap()).unwrap();
+            fs::write(script, "").unwrap();
+        }
+        let env = Some(OsString::from(&checkout));
+
+        assert_eq!(
+            resolve(Some(bundled.clone()), env.clone()),
+            Ok(bundled.clone())
+        );
+        let missing = dir.join("missing").join(RESOURCE_PATH);
+        assert_eq!(resolve(Some(missing.clone()), env), Ok(checkout.clone()));
+        // An empty variable is skipped; whatever's found is still absolute
+        let fallback = resolve(Some(missing.clone()), Some(OsString::new()));
+        assert!(fallback.as_ref().map_or(true, |path| path.is_absolute()));
+        if let Err(error) = fallback {
+            assert_eq!(
+                error,
+                format!(
+                    "Python engine not found (tried: {}, {})",
+                    missing.display(),
+                    RELATIVE_PATH
+                )
+            );
+        }
+        let _ = fs::remove_dir_all(&dir);
+    }
+}
@@ -0,0 +1,87 @@
+use serde::ser::{Serialize, SerializeStruct, Serializer};
+use std::fmt;
+
+/// Why a simulation run failed, serialized as `{"kind": ..., "message": ...}` so the
+/// frontend can branch on `kind` and still show `message` as-is.
+#[derive(Debug)]
+pub enum SimulationError {
+    /// The config was rejected before the engine was started.
+    ValidationFailed(String),
+    /// No engine script or Python interpreter could be found or started.
+    EngineNotFound(String),
+    /// The engine ran but failed, either exiting non-zero or reporting an error.
+    EngineFailed {
+        code: Option<i32>,
+        stderr: String,
+    },
+    Timeout {
+        seconds: u64,
+    },
+    Cancelled,
+    /// Another simulation was still running, see `RunLock`.
+    Busy,
+    /// The engine wrote more than `max_output_bytes` and was stopped.
+    OutputTooLarge {
+        limit: u64,
+    },
+    /// The engine finished but its output couldn't be read or didn't match the format.
+    ParseError(String),
+    /// Anything on our side, e.g. temp files or a poisoned lock.
+    Internal(String),
+}
+
+impl SimulationError {
+    pub fn kind(&self) -> &'static str {
+        match self {
+            SimulationError::ValidationFailed(_) => "validation_failed",
+            SimulationError::EngineNotFound(_) => "engine_not_found",
+            SimulationError::EngineFailed { .. } => "engine_failed",
+            SimulationError::Timeout { .. } => "timeout",
+            SimulationError::Cancelled => "cancelled",
+            SimulationError::Busy => "busy",
+            SimulationError::OutputTooLarge { .. } => "output_too_large",
+            SimulationError::ParseError(_) => "parse_error",
+            SimulationError::Internal(_) => "internal",
+        }
+    }
+}
+
+impl fmt::Display for SimulationError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SimulationError::ValidationFailed(message)
+            | SimulationError::EngineNotFound(message)
+            | SimulationError::ParseError(message)
+            | SimulationError::Internal(message) => f.write_str(message),
+            SimulationError::EngineFailed { stderr, .. } => {
+                write!(f, "Python engine error: {}", stderr)
+            }
+            SimulationError::Timeout { seconds } => {
+                write!(f, "Python engine timed out after {} seconds", seconds)
+            }
+            SimulationError::Cancelled => f.write_str("Simulation cancelled by user"),
+            SimulationError::Busy => f.write_str("A simulation is already running"),
+            SimulationError::OutputTooLarge { limit } => write!(
+                f,
+                "Python engine output exceeded the max_output_bytes limit of {} bytes",
+                limit
+            ),
+        }
+    }
+}
+
+impl std::error::Error for SimulationError {}
+
+impl Serialize for SimulationError {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut state = serializer.serialize_struct("SimulationError", 3)?;
+        state.serialize_field("kind", self.kind())?;
+        state.serialize_field("message", &self.to_string())?;
+        if let SimulationError::EngineFailed { code, .. } = self {
+            state.serialize_field("code", code)?;
+        } else {
+            state.skip_field("code")?;
+        }
+        state.end()
+    }
+}
@@ -0,0 +1,61 @@
+use crate::models::SimulationResult;
+use crate::rounding::RoundingMode;
+use std::path::Path;
+
+const HEADERS: &[&str] = &[
+    "invoice_number",
+    "date",
+    "customer",
+    "sku",
+    "name",
+    "qty",
+    "rate",
+    "amount",
+    "tax",
+    "total",
+];
+
+// Rows written between explicit flushes, so a failing disk is noticed early
+const FLUSH_EVERY: usize = 1000;
+
+/// Writes one row per line item, repeating the invoice's number, date and customer on
+/// each. Fields containing commas or quotes are quoted; missing SKUs and taxes are blank.
+/// Rows go straight to the file as they're produced, so memory use doesn't grow with the
+/// size of the result. Amounts are rounded with the result's `rounding_mode`.
+pub fn write_line_items(result: &SimulationResult, path: &Path) -> Result<(), String> {
+    let rounding = RoundingMode::or_default(result.rounding_mode.as_deref());
+    let amount = |value: f64| rounding.format(value, 2);
+    let mut writer =
+        ::csv::Writer::from_path(path).map_err(|e| format!("Failed to create CSV: {}", e))?;
+    let write_error = |e: ::csv::Error| format!("Failed to write CSV: {}", e);
+    writer.write_record(HEADERS).map_err(write_error)?;
+
+    let lines = result
+        .invoices
+        .iter()
+        .flat_map(|invoice| invoice.items.iter().map(move |item| (invoice, item)));
+    for (row, (invoice, item)) in (1..).zip(lines) {
+        writer
+            .write_record([
+                invoice.invoice_number.as_str(),
+                &invoice.date,
+                invoice.customer_name(),
+                item.sku.as_deref().unwrap_or(""),
+                item.normalized_name(),
+                &item.normalized_qty().to_string(),
+                &amount(item.rate),
+                &amount(item.line_amount()),
+                &item.tax.map(amount).unwrap_or_default(),
+                &amount(item.line_total()),
+            ])
+            .map_err(write_error)?;
+        if row % FLUSH_EVERY == 0 {
+            writer
+                .flush()
+                .map_err(|e| format!("Failed to write CSV: {}", e))?;
+        }
+    }
+    writer
+        .flush()
+        .map_err(|e| format!("Failed to write CSV: {}", e))
+}
@@ -0,0 +1,254 @@
+use crate::models::{Invoice, InvoiceItem, SellerInfo};
+use crate::rounding::RoundingMode;
+use serde_json::{json, Map, Value};
+use std::fs::File;
+use std::io::BufWriter;
+use std::path::Path;
+
+// The version of the NIC e-invoice schema (INV-01) the document follows
+const SCHEMA_VERSION: &str = "1.1";
+// Every simulated line is counted in units; the catalog has no other measure
+const UNIT: &str = "NOS";
+const GSTIN_LEN: usize = 15;
+
+// Keys the buyer's details may appear under in the invoice's customer object
+const ADDRESS_KEYS: &[&str] = &["address", "address1", "addr1"];
+const LOCATION_KEYS: &[&str] = &["location", "city", "loc"];
+const PINCODE_KEYS: &[&str] = &["pincode", "pin", "pin_code"];
+const STATE_KEYS: &[&str] = &["state_code", "stcd"];
+
+/// Writes `invoice` as a GST e-invoice JSON document. Nothing is written when a mandatory
+/// field is missing; the error lists every one.
+pub fn write(
+    invoice: &Invoice,
+    seller: &SellerInfo,
+    rounding: RoundingMode,
+    path: &Path,
+) -> Result<(), String> {
+    let document = build(invoice, seller, rounding).map_err(|missing| {
+        format!(
+            "Invoice {} can't be exported as an e-invoice, missing or invalid: {}",
+            invoice.invoice_number,
+            missing.join(", ")
+        )
+    })?;
+    let file = File::create(path).map_err(|e| format!("Failed to create e-invoice: {}", e))?;
+    serde_json::to_writer_pretty(BufWriter::new(file), &document)
+        .map_err(|e| format!("Failed to write e-invoice: {}", e))
+}
+
+/// Maps an invoice to the e-invoice schema's document, seller, buyer, item and value
+/// details. Line taxes come from each item's `tax`, or its GST rate, falling back to the
+/// rate in the invoice's `tax_breakdown`; they're charged as IGST when the breakdown says
+/// so or the buyer is in another state, and as CGST plus SGST otherwise.
+///
+/// Returns the schema paths of every mandatory field that's missing or malformed, such as
+/// `BuyerDtls.Pin` or `ItemList[2].HsnCd`, instead of an incomplete document.
+pub fn build(
+    invoice: &Invoice,
+    seller: &SellerInfo,
+    rounding: RoundingMode,
+) -> Result<Value, Vec<String>> {
+    let cents = |value: f64| rounding.round_cents(value);
+    let mut missing = Vec::new();
+    let mut require = |path: &str, present: bool| {
+        if !present {
+            missing.push(path.to_string());
+        }
+    };
+
+    let date = chrono::NaiveDate::parse_from_str(&invoice.date, "%Y-%m-%d");
+    require("DocDtls.No", !invoice.invoice_number.trim().is_empty());
+    require("DocDtls.Dt", date.is_ok());
+
+    require("SellerDtls.Gstin", is_gstin(&seller.gstin));
+    require("SellerDtls.LglNm", !seller.legal_name.trim().is_empty());
+    require("SellerDtls.Addr1", !seller.address.trim().is_empty());
+    require("SellerDtls.Loc", !seller.location.trim().is_empty());
+    require("SellerDtls.Pin", is_pincode(seller.pincode));
+    require("SellerDtls.Stcd", is_state_code(&seller.state_code));
+
+    let customer = invoice.customer.as_object();
+    let field = |keys: &[&str]| -> Option<String> {
+        let fields = customer?;
+        keys.iter().find_map(|key| match fields.get(*key)? {
+            Value::String(value) if !value.trim().is_empty() => Some(value.trim().to_string()),
+            Value::Number(value) => Some(value.to_string()),
+            _ => None,
+        })
+    };
+    let buyer_gstin = field(&["gstin"]).unwrap_or_default();
+    let buyer_address = field(ADDRESS_KEYS);
+    let buyer_location = field(LOCATION_KEYS);
+    let buyer_pin = field(PINCODE_KEYS).and_then(|pin| pin.parse::<u32>().ok());
+    // The first two digits of a GSTIN are the holder's state
+    let buyer_state = field(STATE_KEYS).or_else(|| buyer_gstin.get(..2).map(str::to_string));
+    require("BuyerDtls.Gstin", is_gstin(&buyer_gstin));
+    require(
+        "BuyerDtls.LglNm",
+        !invoice.customer_name().trim().is_empty(),
+    );
+    require("BuyerDtls.Addr1", buyer_address.is_some());
+    require("BuyerDtls.Loc", buyer_location.is_some());
+    require("BuyerDtls.Pin", buyer_pin.is_some_and(is_pincode));
+    require(
+        "BuyerDtls.Stcd",
+        buyer_state.as_deref().is_some_and(is_state_code),
+    );
+
+    let invoice_rate = breakdown_rate(invoice.tax_breakdown.as_ref());
+    let interstate = match breakdown_has_igst(invoice.tax_breakdown.as_ref()) {
+        Some(igst) => igst,
+        None => buyer_state.as_deref() != Some(seller.state_code.trim()),
+    };
+    require("ItemList", !invoice.items.is_empty());
+    let mut items = Vec::new();
+    let (mut assessable, mut cgst, mut sgst, mut igst) = (0.0, 0.0, 0.0, 0.0);
+    for (index, item) in invoice.items.iter().enumerate() {
+        let path = |field: &str| format!("ItemList[{}].{}", index + 1, field);
+        let hsn = item.hsn.as_deref().map(str::trim).unwrap_or("");
+        let rate = item.gst_percent.or(invoice_rate);
+        require(&path("PrdDesc"), !item.normalized_name().trim().is_empty());
+        require(&path("HsnCd"), is_hsn(hsn));
+        require(&path("GstRt"), rate.is_some());
+
+        let amount = cents(item.line_amount());
+        let tax = cents(line_tax(item, amount, rate.unwrap_or(0.0)));
+        let (line_igst, line_cgst, line_sgst) = if interstate {
+            (tax, 0.0, 0.0)
+        } else {
+            let half = cents(tax / 2.0);
+            (0.0, half, cents(tax - half))
+        };
+        assessable += amount;
+        igst += line_igst;
+        cgst += line_cgst;
+        sgst += line_sgst;
+        items.push(json!({
+            "SlNo": (index + 1).to_string(),
+            "PrdDesc": item.normalized_name(),
+            // SAC codes, for services, start with 99
+            "IsServc": if hsn.starts_with("99") { "Y" } else { "N" },
+            "HsnCd": hsn,
+            "Qty": item.normalized_qty(),
+            "Unit": UNIT,
+            "UnitPrice": cents(item.rate),
+            "TotAmt": amount,
+            "AssAmt": amount,
+            "GstRt": rate.unwrap_or(0.0),
+            "IgstAmt": line_igst,
+            "CgstAmt": line_cgst,
+            "SgstAmt": line_sgst,
+            "TotItemVal": cents(amount + tax),
+        }));
+    }
+    if !missing.is_empty() {
+        return Err(missing);
+    }
+
+    let mut seller_details = Map::new();
+    seller_details.insert("Gstin".into(), json!(seller.gstin.trim()));
+    seller_details.insert("LglNm".into(), json!(seller.legal_name.trim()));
+    if let Some(trade_name) = &seller.trade_name {
+        seller_details.insert("TrdNm".into(), json!(trade_name.trim()));
+    }
+    seller_details.insert("Addr1".into(), json!(seller.address.trim()));
+    seller_details.insert("Loc".into(), json!(seller.location.trim()));
+    seller_details.insert("Pin".into(), json!(seller.pincode));
+    seller_details.insert("Stcd".into(), json!(seller.state_code.trim()));
+    let buyer_state = buyer_state.unwrap_or_default();
+
+    let date = date.map(|date| date.format("%d/%m/%Y").to_string());
+    Ok(json!({
+        "Version": SCHEMA_VERSION,
+        "TranDtls": { "TaxSch": "GST", "SupTyp": "B2B" },
+        "DocDtls": {
+            "Typ": "INV",
+            "No": invoice.invoice_number.trim(),
+            "Dt": date.unwrap_or_default(),
+        },
+        "SellerDtls": seller_details,
+        "BuyerDtls": {
+            "Gstin": buyer_gstin,
+            "LglNm": invoice.customer_name().trim(),
+            "Pos": buyer_state,
+            "Addr1": buyer_address,
+            "Loc": buyer_location,
+            "Pin": buyer_pin,
+            "Stcd": buyer_state,
+        },
+        "ItemList": items,
+        "ValDtls": {
+            "AssVal": cents(assessable),
+            "CgstVal": cents(cgst),
+            "SgstVal": cents(sgst),
+            "IgstVal": cents(igst),
+            "TotInvVal": cents(assessable + cgst + sgst + igst),
+        },
+    }))
+}
+
+// The line's own tax when the engine reported it, otherwise its GST at `rate`
+fn line_tax(item: &InvoiceItem, amount: f64, rate: f64) -> f64 {
+    item.tax.unwrap_or(amount * rate / 100.0)
+}
+
+// The one GST rate an invoice's breakdown charges: a grouped breakdown's only rate, or a
+// flat breakdown's `CGST_rate`, `SGST_rate` and `IGST_rate` added up. None when it has
+// several rates, so each line would need its own.
+fn breakdown_rate(breakdown: Option<&Value>) -> Option<f64> {
+    let fields = breakdown?.as_object()?;
+    let grouped: Vec<f64> = fields
+        .iter()
+        .filter(|(_, value)| value.is_object())
+        .filter_map(|(key, _)| key.trim_end_matches('%').trim().parse().ok())
+        .collect();
+    if !grouped.is_empty() {
+        return (grouped.len() == 1).then(|| grouped[0]);
+    }
+    let rates: Vec<f64> = fields
+        .iter()
+        .filter(|(key, _)| key.to_uppercase().ends_with("_RATE"))
+        .filter_map(|(_, value)| value.as_f64())
+        .collect();
+    (!rates.is_empty()).then(|| rates.iter().sum())
+}
+
+// Whether the breakdown charges IGST (interstate) or CGST/SGST, if it names either
+fn breakdown_has_igst(breakdown: Option<&Value>) -> Option<bool> {
+    let groups = crate::verify::tax_groups(breakdown?)?;
+    let heads: Vec<&String> = groups
+        .values()
+        .flat_map(|group| group.taxes.keys())
+        .collect();
+    if heads.iter().any(|head| *head == "IGST") {
+        Some(true)
+    } else if heads.iter().any(|head| *head == "CGST" || *head == "SGST") {
+        Some(false)
+    } else {
+        None
+    }
+}
+
+// Two state digits, ten PAN characters, an entity number, 'Z' and a check character
+fn is_gstin(gstin: &str) -> bool {
+    let gstin = gstin.trim();
+    gstin.len() == GSTIN_LEN
+        && gstin.is_ascii()
+        && gstin[..2].bytes().all(|b| b.is_ascii_digit())
+        && gstin.bytes().all(|b| b.is_ascii_alphanumeric())
+}
+
+fn is_state_code(code: &str) -> bool {
+    let code = code.trim();
+    code.len() == 2 && code.bytes().all(|b| b.is_ascii_digit())
+}
+
+fn is_pincode(pin: u32) -> bool {
+    (100_000..=999_999).contains(&pin)
+}
+
+// HSN codes are 4, 6 or 8 digits; SAC codes for services are 6
+fn is_hsn(hsn: &str) -> bool {
+    matches!(hsn.len(), 4 | 6 | 8) && hsn.bytes().all(|b| b.is_ascii_digit())
+}
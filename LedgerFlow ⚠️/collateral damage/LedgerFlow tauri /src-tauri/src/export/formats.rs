@@ -0,0 +1,69 @@
+use crate::models::SimulationResult;
+use crate::rounding::RoundingMode;
+use std::fs::{self, File};
+use std::io::{BufWriter, Write};
+use std::path::{Path, PathBuf};
+
+/// What `output_formats` may ask for.
+pub const OUTPUT_FORMATS: &[&str] = &["json", "csv", "xlsx", "pdf"];
+
+/// Writes `result` into `dir` once per format, each file named `stem` plus the format's
+/// extension, and returns the paths written. 'pdf' writes one file per invoice into a
+/// `<stem>_pdf` folder, and that folder is the path returned. Every format is attempted;
+/// the ones that fail are returned as messages alongside the paths that succeeded.
+pub fn write_formats(
+    result: &SimulationResult,
+    formats: &[String],
+    dir: &Path,
+    stem: &str,
+) -> (Vec<PathBuf>, Vec<String>) {
+    let mut written = Vec::new();
+    let mut failures = Vec::new();
+    if let Err(e) = fs::create_dir_all(dir) {
+        failures.push(format!("Failed to create output directory: {}", e));
+        return (written, failures);
+    }
+    for format in formats {
+        let path = match format.as_str() {
+            "pdf" => dir.join(format!("{}_pdf", stem)),
+            "csv" => dir.join(format!("{}_line_items.csv", stem)),
+            other => dir.join(format!("{}.{}", stem, other)),
+        };
+        match write_format(result, format, &path) {
+            Ok(()) => written.push(path),
+            Err(e) => failures.push(format!("{} output not written: {}", format, e)),
+        }
+    }
+    (written, failures)
+}
+
+fn write_format(result: &SimulationResult, format: &str, path: &Path) -> Result<(), String> {
+    match format {
+        "json" => write_json(result, path),
+        "csv" => super::csv::write_line_items(result, path),
+        "xlsx" => super::xlsx::write_invoices(result, path),
+        "pdf" => {
+            let rounding = RoundingMode::or_default(result.rounding_mode.as_deref());
+            let summary = super::pdf::write_all(&result.invoices, rounding, path)?;
+            match summary.failures.first() {
+                Some(first) => Err(format!(
+                    "{} of {} invoices failed, first: {}",
+                    summary.failures.len(),
+                    result.invoices.len(),
+                    first
+                )),
+                None => Ok(()),
+            }
+        }
+        other => Err(format!("Unknown output format '{}'", other)),
+    }
+}
+
+fn write_json(result: &SimulationResult, path: &Path) -> Result<(), String> {
+    let file = File::create(path).map_err(|e| format!("Failed to create JSON: {}", e))?;
+    let mut out = BufWriter::new(file);
+    serde_json::to_writer_pretty(&mut out, result)
+        .map_err(|e| e.to_string())
+        .and_then(|()| out.flush().map_err(|e| e.to_string()))
+        .map_err(|e| format!("Failed to write JSON: {}", e))
+}
@@ -0,0 +1,235 @@
+use crate::models::{Invoice, SimulationResult};
+use crate::rounding::RoundingMode;
+use chrono::NaiveDate;
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::path::Path;
+
+// QuickBooks Desktop's default accounts for invoices and the tax charged on them
+const RECEIVABLES_ACCOUNT: &str = "Accounts Receivable";
+const SALES_ACCOUNT: &str = "Sales";
+const TAX_ACCOUNT: &str = "Sales Tax Payable";
+
+const TRNS_HEADER: &[&str] = &[
+    "!TRNS", "TRNSID", "TRNSTYPE", "DATE", "ACCNT", "NAME", "AMOUNT", "DOCNUM", "MEMO",
+];
+const SPL_HEADER: &[&str] = &[
+    "!SPL", "SPLID", "TRNSTYPE", "DATE", "ACCNT", "NAME", "AMOUNT", "DOCNUM", "MEMO", "QNTY",
+    "PRICE", "INVITEM",
+];
+
+/// Writes a QuickBooks Desktop IIF file with one INVOICE transaction per invoice: the
+/// customer's receivable is debited the total, and each line item and the invoice's tax
+/// are credited as splits. Credits and invoiced quantities are negative, as IIF expects.
+///
+/// Amounts are rounded with the result's `rounding_mode`, and the tax split takes up
+/// whatever rounding leaves between the lines and the total so every transaction
+/// balances, which QuickBooks insists on. Tabs and line breaks in names would end a field
+/// or a row, so they're written as spaces.
+pub fn write_invoices(result: &SimulationResult, path: &Path) -> Result<(), String> {
+    // Checked before the file is created so a bad date doesn't leave half an import behind
+    let dates = result
+        .invoices
+        .iter()
+        .map(|invoice| {
+            NaiveDate::parse_from_str(&invoice.date, "%Y-%m-%d")
+                // IIF dates are MM/DD/YYYY
+                .map(|date| date.format("%m/%d/%Y").to_string())
+                .map_err(|_| {
+                    format!(
+                        "Invoice {}: date '{}' isn't YYYY-MM-DD",
+                        invoice.invoice_number, invoice.date
+                    )
+                })
+        })
+        .collect::<Result<Vec<String>, String>>()?;
+    let file = File::create(path).map_err(|e| format!("Failed to create IIF file: {}", e))?;
+    let mut out = BufWriter::new(file);
+    let rounding = RoundingMode::or_default(result.rounding_mode.as_deref());
+    write_transactions(&mut out, &result.invoices, &dates, rounding)
+        .and_then(|()| out.flush())
+        .map_err(|e| format!("Failed to write IIF file: {}", e))
+}
+
+fn write_transactions<W: Write>(
+    out: &mut W,
+    invoices: &[Invoice],
+    dates: &[String],
+    rounding: RoundingMode,
+) -> io::Result<()> {
+    row(out, TRNS_HEADER)?;
+    row(out, SPL_HEADER)?;
+    row(out, &["!ENDTRNS"])?;
+    for (invoice, date) in invoices.iter().zip(dates) {
+        transaction(out, invoice, date, rounding)?;
+    }
+    Ok(())
+}
+
+fn transaction<W: Write>(
+    out: &mut W,
+    invoice: &Invoice,
+    date: &str,
+    rounding: RoundingMode,
+) -> io::Result<()> {
+    let cents = |value: f64| (rounding.round_cents(value) * 100.0).round() as i64;
+    let name = field(invoice.customer_name());
+    let number = field(&invoice.invoice_number);
+    let total = cents(invoice.total);
+    row(
+        out,
+        &[
+            "TRNS",
+            "",
+            "INVOICE",
+            date,
+            RECEIVABLES_ACCOUNT,
+            &name,
+            &amount(total),
+            &number,
+            "",
+        ],
+    )?;
+
+    let mut credited = 0;
+    for item in &invoice.items {
+        let line = cents(item.line_amount());
+        credited += line;
+        let item_name = field(item.normalized_name());
+        row(
+            out,
+            &[
+                "SPL",
+                "",
+                "INVOICE",
+                date,
+                SALES_ACCOUNT,
+                &name,
+                &amount(-line),
+                &number,
+                &item_name,
+                &(-item.normalized_qty()).to_string(),
+                &rounding.format(item.rate, 2),
+                &item_name,
+            ],
+        )?;
+    }
+    let tax = total - credited;
+    if tax != 0 {
+        row(
+            out,
+            &[
+                "SPL",
+                "",
+                "INVOICE",
+                date,
+                TAX_ACCOUNT,
+                &name,
+                &amount(-tax),
+                &number,
+                "Tax",
+                "",
+                "",
+                "",
+            ],
+        )?;
+    }
+    row(out, &["ENDTRNS"])
+}
+
+fn row<W: Write>(out: &mut W, fields: &[&str]) -> io::Result<()> {
+    writeln!(out, "{}", fields.join("\t"))
+}
+
+// A tab would start a new field and a line break a new row
+fn field(value: &str) -> String {
+    value.replace(['\t', '\r', '\n'], " ").trim().to_string()
+}
+
+fn amount(cents: i64) -> String {
+    let sign = if cents < 0 { "-" } else { "" };
+    format!("{}{}.{:02}", sign, cents.abs() / 100, cents.abs() % 100)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::temp_file::TempFile;
+
+    fn result() -> SimulationResult {
+        let mut result: SimulationResult =
+            serde_json::from_str(include_str!("../../../backend/test/test_output.json")).unwrap();
+        result.invoices.truncate(2);
+        result
+    }
+
+    fn write(result: &SimulationResult) -> String {
+        let file = TempFile::unique("ledgerflow_export", "iif");
+        write_invoices(result, file.path()).unwrap();
+        std::fs::read_to_string(file.path()).unwrap()
+    }
+
+    #[test]
+    fn two_invoices_parse_back_with_their_totals() {
+        let text = write(&result());
+        let rows: Vec<Vec<&str>> = text.lines().map(|l| l.split('\t').collect()).collect();
+        assert_eq!(rows[0][0], "!TRNS");
+        assert_eq!(rows[1][0], "!SPL");
+        assert_eq!(rows[2], ["!ENDTRNS"]);
+
+        let mut totals = Vec::new();
+        let mut balance = 0;
+        for row in &rows[3..] {
+            match row[0] {
+                "TRNS" => {
+                    assert_eq!(row.len(), TRNS_HEADER.len());
+                    totals.push((row[3], row[6], row[7]));
+                    balance += row[6].replace('.', "").parse::<i64>().unwrap();
+                }
+                "SPL" => {
+                    assert_eq!(row.len(), SPL_HEADER.len());
+                    balance += row[6].replace('.', "").parse::<i64>().unwrap();
+                }
+                "ENDTRNS" => {
+                    // Debits and credits cancel out to the cent
+                    assert_eq!(balance, 0);
+                    balance = 0;
+                }
+                other => panic!("unexpected row {}", other),
+            }
+        }
+        assert_eq!(
+            totals,
+            [
+                ("07/11/2024", "66563.86", "GST/FY24-25/03572"),
+                ("07/12/2024", "34641.19", "GST/FY24-25/03573"),
+            ]
+        );
+    }
+
+    #[test]
+    fn names_cannot_break_a_row() {
+        let mut result = result();
+        result.invoices[0].customer = serde_json::json!({ "name": "Acme\tTraders\n" });
+        result.invoices[0].items[0].name = "Desk\tchair\nset".to_string();
+        let text = write(&result);
+        assert!(text.contains("\tAcme Traders\t"), "{}", text);
+        assert!(text.contains("\tDesk chair set\t"), "{}", text);
+        // No row was split in two
+        assert_eq!(text.lines().count(), write(&self::result()).lines().count());
+        assert_eq!(amount(-1234), "-12.34");
+        assert_eq!(amount(5), "0.05");
+    }
+
+    #[test]
+    fn rejects_a_date_it_cannot_convert() {
+        let mut result = result();
+        result.invoices[1].date = "12/07/2024".to_string();
+        let file = TempFile::unique("ledgerflow_export", "iif");
+        assert_eq!(
+            write_invoices(&result, file.path()).unwrap_err(),
+            "Invoice GST/FY24-25/03573: date '12/07/2024' isn't YYYY-MM-DD"
+        );
+        assert!(!file.path().exists());
+    }
+}
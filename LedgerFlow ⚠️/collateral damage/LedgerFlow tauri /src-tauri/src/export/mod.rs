@@ -0,0 +1,62 @@
+// Writers that turn a SimulationResult into files for accountants and other tools
+pub mod csv;
+pub mod einvoice;
+pub mod formats;
+pub mod iif;
+pub mod open;
+pub mod pdf;
+pub mod qr;
+pub mod tally;
+pub mod xlsx;
+
+#[cfg(test)]
+mod tests {
+    use crate::models::SimulationResult;
+    use crate::temp_file::TempFile;
+    use serde_json::{json, Value};
+    use std::fs::File;
+    use std::io::{BufRead, BufReader};
+
+    // 10,000 invoices of five lines each
+    fn large_result() -> SimulationResult {
+        let invoices: Vec<Value> = (0..10_000)
+            .map(|n| {
+                let items: Vec<Value> = (0..5)
+                    .map(|i| {
+                        json!({
+                            "name": format!("Item {}", i),
+                            "quantity": 2,
+                            "rate": 50.0,
+                            "amount": 100.0,
+                        })
+                    })
+                    .collect();
+                json!({
+                    "invoice_number": format!("INV-{:05}", n),
+                    "date": "2024-07-11",
+                    "customer": "Acme, Inc.",
+                    "items": items,
+                    "subtotal": 500.0,
+                    "total": 500.0,
+                })
+            })
+            .collect();
+        serde_json::from_value(json!({ "invoices": invoices, "status": "success" })).unwrap()
+    }
+
+    #[test]
+    fn exports_fifty_thousand_lines() {
+        let result = large_result();
+        let csv = TempFile::unique("ledgerflow_export", "csv");
+        super::csv::write_line_items(&result, csv.path()).unwrap();
+        let rows = BufReader::new(File::open(csv.path()).unwrap())
+            .lines()
+            .count();
+        // Plus the header
+        assert_eq!(rows, 50_001);
+
+        let xlsx = TempFile::unique("ledgerflow_export", "xlsx");
+        super::xlsx::write_invoices(&result, xlsx.path()).unwrap();
+        assert!(xlsx.path().metadata().unwrap().len() > 0);
+    }
+}
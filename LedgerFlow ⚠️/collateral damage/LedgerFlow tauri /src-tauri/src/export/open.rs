@@ -0,0 +1,103 @@
+use std::path::{Path, PathBuf};
+#[cfg(target_os = "linux")]
+use std::process::{Command, Stdio};
+#[cfg(target_os = "linux")]
+use std::time::{Duration, Instant};
+
+// How long to watch xdg-open for a failure before taking its silence as success. Some
+// desktops keep it running until the opened application exits.
+#[cfg(target_os = "linux")]
+const LAUNCH_GRACE: Duration = Duration::from_secs(2);
+
+/// Shows an exported file in Explorer, Finder or the desktop's file manager, with the
+/// file itself selected rather than just its folder opened.
+pub fn reveal(path: &str) -> Result<(), String> {
+    let path = existing(path)?;
+    #[cfg(target_os = "linux")]
+    graphical_session().map_err(|e| manual(&path, &e))?;
+    tauri_plugin_opener::reveal_item_in_dir(&path).map_err(|e| manual(&path, &e.to_string()))
+}
+
+/// Opens an exported file in the application the system associates with its type.
+///
+/// On Linux the opener only reports whether it could start xdg-open, not whether
+/// xdg-open then found an application, so xdg-open is run here and watched briefly
+/// instead. Either way a failure names the file so it can be opened by hand.
+pub fn open(path: &str) -> Result<(), String> {
+    let path = existing(path)?;
+    #[cfg(target_os = "linux")]
+    {
+        graphical_session().map_err(|e| manual(&path, &e))?;
+        let mut command = Command::new("xdg-open");
+        command.arg(&path);
+        launch(command).map_err(|e| manual(&path, &e))
+    }
+    #[cfg(not(target_os = "linux"))]
+    tauri_plugin_opener::open_path(&path, None::<&str>).map_err(|e| manual(&path, &e.to_string()))
+}
+
+/// Runs a launcher such as xdg-open and fails if it exits unsuccessfully within
+/// `LAUNCH_GRACE`, with its stderr as the reason. One still running by then is left to
+/// it and counted as a success.
+#[cfg(target_os = "linux")]
+pub fn launch(mut command: Command) -> Result<(), String> {
+    let program = command.get_program().to_string_lossy().into_owned();
+    let mut child = command
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|e| format!("couldn't start {}: {}", program, e))?;
+    let started = Instant::now();
+    while started.elapsed() < LAUNCH_GRACE {
+        match child.try_wait() {
+            Ok(Some(status)) if status.success() => return Ok(()),
+            Ok(Some(status)) => {
+                let mut stderr = String::new();
+                if let Some(mut pipe) = child.stderr.take() {
+                    let _ = std::io::Read::read_to_string(&mut pipe, &mut stderr);
+                }
+                let stderr = stderr.trim();
+                return Err(if stderr.is_empty() {
+                    format!("{} failed ({})", program, status)
+                } else {
+                    format!("{} failed ({}): {}", program, status, stderr)
+                });
+            }
+            Ok(None) => std::thread::sleep(Duration::from_millis(50)),
+            Err(e) => return Err(format!("couldn't wait for {}: {}", program, e)),
+        }
+    }
+    Ok(())
+}
+
+// Without a display there's nothing to open the file on, yet xdg-open would still start
+#[cfg(target_os = "linux")]
+fn graphical_session() -> Result<(), String> {
+    let set = |name: &str| std::env::var_os(name).is_some_and(|value| !value.is_empty());
+    if set("DISPLAY") || set("WAYLAND_DISPLAY") {
+        Ok(())
+    } else {
+        Err("no graphical session (DISPLAY and WAYLAND_DISPLAY are unset)".to_string())
+    }
+}
+
+// The UI shows this as is, so it says where the file is for opening it by hand
+fn manual(path: &Path, reason: &str) -> String {
+    format!(
+        "Couldn't open {}: {}. Open it from your file manager instead.",
+        path.display(),
+        reason
+    )
+}
+
+// The opener's own errors for a missing file differ by platform and rarely say so
+fn existing(path: &str) -> Result<PathBuf, String> {
+    let path = Path::new(path);
+    if !path.exists() {
+        return Err(format!("File not found: {}", path.display()));
+    }
+    // Explorer's selection and `open` both misbehave on relative paths
+    path.canonicalize()
+        .map_err(|e| format!("Failed to resolve {}: {}", path.display(), e))
+}
@@ -0,0 +1,467 @@
+use crate::models::{ExportSummary, Invoice, SimulationResult};
+use crate::rounding::RoundingMode;
+use crate::stats;
+use printpdf::{
+    BuiltinFont, Color, Line, LinePoint, Mm, Op, PdfDocument, PdfFontHandle, PdfPage,
+    PdfSaveOptions, Point, Pt, Rgb, TextItem,
+};
+use std::collections::HashSet;
+use std::path::Path;
+use std::time::Duration;
+
+// A4 portrait, laid out in millimetres from the bottom-left corner
+const PAGE_WIDTH: f32 = 210.0;
+const PAGE_HEIGHT: f32 = 297.0;
+const MARGIN: f32 = 15.0;
+const TOP: f32 = PAGE_HEIGHT - 20.0;
+// Rows stop here so the footer never overlaps the table
+const TABLE_BOTTOM: f32 = 32.0;
+const ROW_HEIGHT: f32 = 6.0;
+const LINE_HEIGHT: f32 = 5.0;
+const TOTALS_HEIGHT: f32 = 16.0;
+
+// Right edges of the numeric columns; the item name runs from the margin up to QTY_RIGHT
+const QTY_RIGHT: f32 = 125.0;
+const RATE_RIGHT: f32 = 160.0;
+const AMOUNT_RIGHT: f32 = PAGE_WIDTH - MARGIN;
+const TOTALS_LEFT: f32 = RATE_RIGHT - 30.0;
+// Keeps long names clear of the quantity column at the row font size
+const MAX_NAME_CHARS: usize = 60;
+// Customers listed in a summary report
+const TOP_CUSTOMERS: usize = 10;
+
+#[derive(Clone, Copy)]
+struct Style {
+    font: BuiltinFont,
+    size: f32,
+}
+
+const TITLE: Style = Style {
+    font: BuiltinFont::HelveticaBold,
+    size: 18.0,
+};
+const DETAIL: Style = Style {
+    font: BuiltinFont::Helvetica,
+    size: 10.0,
+};
+const DETAIL_BOLD: Style = Style {
+    font: BuiltinFont::HelveticaBold,
+    size: 10.0,
+};
+const ROW: Style = Style {
+    font: BuiltinFont::Helvetica,
+    size: 9.0,
+};
+const ROW_BOLD: Style = Style {
+    font: BuiltinFont::HelveticaBold,
+    size: 9.0,
+};
+const FOOTER: Style = Style {
+    font: BuiltinFont::Helvetica,
+    size: 8.0,
+};
+
+/// Renders one invoice as a printable A4 PDF: a header and customer block, the line items
+/// (continued over as many pages as needed, with the column headings repeated), subtotal
+/// and total, and a footer with the payment terms and template when the invoice has them.
+/// Amounts are rounded to cents with `rounding`.
+pub fn write_invoice(invoice: &Invoice, rounding: RoundingMode, path: &Path) -> Result<(), String> {
+    let pages = layout(invoice, rounding);
+    let count = pages.len();
+    let pages: Vec<PdfPage> = pages
+        .into_iter()
+        .enumerate()
+        .map(|(index, mut page)| {
+            footer(&mut page, invoice, index + 1, count);
+            PdfPage::new(Mm(PAGE_WIDTH), Mm(PAGE_HEIGHT), page.ops)
+        })
+        .collect();
+
+    let title = format!("Invoice {}", invoice.invoice_number);
+    let bytes = PdfDocument::new(&title)
+        .with_pages(pages)
+        .save(&PdfSaveOptions::default(), &mut Vec::new());
+    std::fs::write(path, bytes).map_err(|e| format!("Failed to write PDF: {}", e))
+}
+
+/// Renders a run as an A4 report: the period, seed and totals, revenue per month and the
+/// top customers by revenue. The figures come from the same helpers as the UI's, so the
+/// two agree. A result without invoices gets a report that says so.
+pub fn write_summary(result: &SimulationResult, path: &Path) -> Result<(), String> {
+    let pages = summary_layout(result);
+    let count = pages.len();
+    let pages: Vec<PdfPage> = pages
+        .into_iter()
+        .enumerate()
+        .map(|(index, mut page)| {
+            page.right(
+                AMOUNT_RIGHT,
+                12.0,
+                FOOTER,
+                &format!("Page {} of {}", index + 1, count),
+            );
+            PdfPage::new(Mm(PAGE_WIDTH), Mm(PAGE_HEIGHT), page.ops)
+        })
+        .collect();
+
+    let bytes = PdfDocument::new("Simulation summary")
+        .with_pages(pages)
+        .save(&PdfSaveOptions::default(), &mut Vec::new());
+    std::fs::write(path, bytes).map_err(|e| format!("Failed to write PDF: {}", e))
+}
+
+/// Writes one PDF per invoice into `dir`, creating it if needed, with each file named
+/// after its invoice number. A failed invoice is recorded and the rest still written.
+pub fn write_all(
+    invoices: &[Invoice],
+    rounding: RoundingMode,
+    dir: &Path,
+) -> Result<ExportSummary, String> {
+    std::fs::create_dir_all(dir)
+        .map_err(|e| format!("Failed to create export directory: {}", e))?;
+    let mut summary = ExportSummary::default();
+    let mut used = HashSet::new();
+    for invoice in invoices {
+        let name = unique_name(file_stem(&invoice.invoice_number), &mut used);
+        match write_invoice(invoice, rounding, &dir.join(format!("{}.pdf", name))) {
+            Ok(()) => summary.written += 1,
+            Err(e) => summary
+                .failures
+                .push(format!("Invoice {}: {}", invoice.invoice_number, e)),
+        }
+    }
+    Ok(summary)
+}
+
+// Invoice numbers like `GST/FY24-25/00042` contain separators, so anything that isn't
+// safe in a file name on every platform becomes `_`
+fn file_stem(invoice_number: &str) -> String {
+    let stem: String = invoice_number
+        .chars()
+        .map(|c| match c {
+            '/' | '\\' | ':' | '*' | '?' | '"' | '<' | '>' | '|' => '_',
+            c if c.is_control() => '_',
+            c => c,
+        })
+        .collect();
+    // Windows drops trailing dots and spaces, and reserves a few device names
+    let stem = stem.trim_end_matches(['.', ' ']).trim_start();
+    let reserved = ["CON", "PRN", "AUX", "NUL", "COM1", "LPT1"]
+        .iter()
+        .any(|r| r.eq_ignore_ascii_case(stem));
+    match stem {
+        "" => "invoice".to_string(),
+        stem if reserved => format!("{}_", stem),
+        stem => stem.to_string(),
+    }
+}
+
+// Two numbers can sanitize to the same stem, e.g. `A/1` and `A_1`
+fn unique_name(stem: String, used: &mut HashSet<String>) -> String {
+    let mut name = stem.clone();
+    let mut n = 2;
+    while !used.insert(name.to_lowercase()) {
+        name = format!("{}-{}", stem, n);
+        n += 1;
+    }
+    name
+}
+
+// The pages' content, without footers
+fn layout(invoice: &Invoice, rounding: RoundingMode) -> Vec<Canvas> {
+    let amount = |value: f64| rounding.format(value, 2);
+    let mut pages = Vec::new();
+    let mut page = Canvas::default();
+    let mut y = header(&mut page, invoice);
+    y = table_header(&mut page, y);
+
+    for item in &invoice.items {
+        if y - ROW_HEIGHT < TABLE_BOTTOM {
+            pages.push(std::mem::take(&mut page));
+            y = table_header(&mut page, TOP);
+        }
+        y -= ROW_HEIGHT;
+        let name: String = item
+            .normalized_name()
+            .chars()
+            .take(MAX_NAME_CHARS)
+            .collect();
+        page.text(MARGIN, y, ROW, &name);
+        page.right(QTY_RIGHT, y, ROW, &item.normalized_qty().to_string());
+        page.right(RATE_RIGHT, y, ROW, &amount(item.rate));
+        page.right(AMOUNT_RIGHT, y, ROW, &amount(item.line_amount()));
+    }
+
+    if y - TOTALS_HEIGHT < TABLE_BOTTOM {
+        pages.push(std::mem::take(&mut page));
+        y = TOP;
+    }
+    page.rule(y - 2.0, TOTALS_LEFT, AMOUNT_RIGHT);
+    y -= 2.0 + ROW_HEIGHT;
+    page.text(TOTALS_LEFT, y, ROW, "Subtotal");
+    page.right(AMOUNT_RIGHT, y, ROW, &amount(subtotal(invoice)));
+    y -= ROW_HEIGHT;
+    page.text(TOTALS_LEFT, y, ROW_BOLD, "Total");
+    page.right(AMOUNT_RIGHT, y, ROW_BOLD, &amount(invoice.total));
+
+    pages.push(page);
+    pages
+}
+
+// The summary's pages, without page numbers
+fn summary_layout(result: &SimulationResult) -> Vec<Canvas> {
+    let rounding = RoundingMode::or_default(result.rounding_mode.as_deref());
+    let amount = |value: f64| rounding.format(value, 2);
+    let mut page = Canvas::default();
+    let mut y = TOP;
+    page.text(MARGIN, y, TITLE, "SIMULATION SUMMARY");
+    y -= 9.0;
+
+    // YYYY-MM-DD sorts as text
+    let first = result.invoices.iter().map(|invoice| &invoice.date).min();
+    let last = result.invoices.iter().map(|invoice| &invoice.date).max();
+    let mut details = Vec::new();
+    if let (Some(first), Some(last)) = (first, last) {
+        details.push(format!("Period: {} to {}", first, last));
+    }
+    details.push(format!("Seed: {}", result.seed_used));
+    if let Some(currency) = &result.currency {
+        details.push(format!("Currency: {}", currency));
+    }
+    for line in details {
+        page.text(MARGIN, y, DETAIL, &line);
+        y -= LINE_HEIGHT;
+    }
+    y -= LINE_HEIGHT;
+    if result.invoices.is_empty() {
+        page.text(MARGIN, y, DETAIL_BOLD, "No invoices were generated.");
+        return vec![page];
+    }
+
+    let target = result
+        .stats
+        .as_ref()
+        .map_or(0.0, |stats| stats.revenue_target);
+    let stats = stats::compute(&result.invoices, target, Duration::ZERO);
+    let mut totals = vec![
+        ("Invoices", stats.invoice_count.to_string()),
+        ("Total revenue", amount(stats.total_revenue)),
+    ];
+    if stats.revenue_target > 0.0 {
+        totals.push(("Revenue target", amount(stats.revenue_target)));
+        totals.push((
+            "Target achieved",
+            format!("{:.1}%", stats.revenue_achieved_percent),
+        ));
+    }
+    totals.push(("Average invoice", amount(stats.mean_invoice_amount)));
+    totals.push(("Distinct customers", stats.distinct_customers.to_string()));
+    page.text(MARGIN, y, DETAIL_BOLD, "Totals");
+    y -= 1.0;
+    for (label, value) in totals {
+        y -= ROW_HEIGHT;
+        page.text(MARGIN, y, ROW, label);
+        page.right(AMOUNT_RIGHT, y, ROW, &value);
+    }
+
+    let months: Vec<[String; 3]> = stats::by_month(&result.invoices)
+        .into_iter()
+        .map(|month| {
+            [
+                month.month,
+                month.invoice_count.to_string(),
+                amount(month.total_revenue),
+            ]
+        })
+        .collect();
+    let customers: Vec<[String; 3]> = stats::by_customer(&result.invoices)
+        .into_iter()
+        .take(TOP_CUSTOMERS)
+        .map(|customer| {
+            [
+                customer.customer.chars().take(MAX_NAME_CHARS).collect(),
+                customer.invoice_count.to_string(),
+                amount(customer.total_revenue),
+            ]
+        })
+        .collect();
+
+    let mut pages = Vec::new();
+    for (title, heading, rows) in [
+        ("Revenue by month", "Month", months),
+        ("Top customers", "Customer", customers),
+    ] {
+        y -= 10.0;
+        // Keep a section's title with at least its first row
+        if y - 2.0 * ROW_HEIGHT < TABLE_BOTTOM {
+            pages.push(std::mem::take(&mut page));
+            y = TOP;
+        }
+        page.text(MARGIN, y, DETAIL_BOLD, title);
+        y = summary_header(&mut page, y - 7.0, heading);
+        for [label, invoices, revenue] in rows {
+            if y - ROW_HEIGHT < TABLE_BOTTOM {
+                pages.push(std::mem::take(&mut page));
+                y = summary_header(&mut page, TOP, heading);
+            }
+            y -= ROW_HEIGHT;
+            page.text(MARGIN, y, ROW, &label);
+            page.right(RATE_RIGHT, y, ROW, &invoices);
+            page.right(AMOUNT_RIGHT, y, ROW, &revenue);
+        }
+    }
+    pages.push(page);
+    pages
+}
+
+// Column headings for a summary table; returns the baseline of the heading row
+fn summary_header(page: &mut Canvas, y: f32, heading: &str) -> f32 {
+    page.text(MARGIN, y, ROW_BOLD, heading);
+    page.right(RATE_RIGHT, y, ROW_BOLD, "Invoices");
+    page.right(AMOUNT_RIGHT, y, ROW_BOLD, "Revenue");
+    page.rule(y - 2.0, MARGIN, AMOUNT_RIGHT);
+    y - 1.0
+}
+
+// Title, invoice details and the customer block; returns where the table starts
+fn header(page: &mut Canvas, invoice: &Invoice) -> f32 {
+    let mut y = TOP;
+    page.text(MARGIN, y, TITLE, "INVOICE");
+    y -= 9.0;
+    let mut details = vec![
+        format!("Invoice No: {}", invoice.invoice_number),
+        format!("Date: {}", invoice.date),
+    ];
+    if !invoice.invoice_type.is_empty() {
+        details.push(format!("Type: {}", invoice.invoice_type));
+    }
+    for line in details {
+        page.text(MARGIN, y, DETAIL, &line);
+        y -= LINE_HEIGHT;
+    }
+
+    y -= LINE_HEIGHT;
+    page.text(MARGIN, y, DETAIL_BOLD, "Bill To");
+    y -= LINE_HEIGHT;
+    page.text(MARGIN, y, DETAIL, invoice.customer_name());
+    // Any other customer details the engine supplied, e.g. a GSTIN
+    if let Some(fields) = invoice.customer.as_object() {
+        for (key, value) in fields.iter().filter(|(key, _)| *key != "name") {
+            if let Some(value) = value.as_str() {
+                y -= LINE_HEIGHT;
+                page.text(
+                    MARGIN,
+                    y,
+                    DETAIL,
+                    &format!("{}: {}", key.to_uppercase(), value),
+                );
+            }
+        }
+    }
+    y - 10.0
+}
+
+// Column headings with a rule underneath; returns the baseline of the heading row
+fn table_header(page: &mut Canvas, y: f32) -> f32 {
+    page.text(MARGIN, y, ROW_BOLD, "Item");
+    page.right(QTY_RIGHT, y, ROW_BOLD, "Qty");
+    page.right(RATE_RIGHT, y, ROW_BOLD, "Rate");
+    page.right(AMOUNT_RIGHT, y, ROW_BOLD, "Amount");
+    page.rule(y - 2.0, MARGIN, AMOUNT_RIGHT);
+    y - 1.0
+}
+
+fn footer(page: &mut Canvas, invoice: &Invoice, number: usize, count: usize) {
+    let mut y = 22.0;
+    if let Some(terms) = &invoice.payment_terms {
+        page.text(MARGIN, y, FOOTER, &format!("Payment terms: {}", terms));
+        y -= 4.5;
+    }
+    if let Some(template) = &invoice.template_applied {
+        page.text(MARGIN, y, FOOTER, &format!("Template: {}", template));
+    }
+    page.right(
+        AMOUNT_RIGHT,
+        12.0,
+        FOOTER,
+        &format!("Page {} of {}", number, count),
+    );
+}
+
+// The older invoice shape has no subtotal, so fall back to the sum of the lines
+fn subtotal(invoice: &Invoice) -> f64 {
+    if invoice.subtotal != 0.0 {
+        invoice.subtotal
+    } else {
+        invoice.items.iter().map(|item| item.line_amount()).sum()
+    }
+}
+
+// Drawing ops for one page
+#[derive(Default)]
+struct Canvas {
+    ops: Vec<Op>,
+}
+
+impl Canvas {
+    fn text(&mut self, x: f32, y: f32, style: Style, value: &str) {
+        self.ops.extend([
+            Op::StartTextSection,
+            Op::SetTextCursor {
+                pos: Point::new(Mm(x), Mm(y)),
+            },
+            Op::SetFont {
+                font: PdfFontHandle::Builtin(style.font),
+                size: Pt(style.size),
+            },
+            Op::ShowText {
+                items: vec![TextItem::Text(value.to_string())],
+            },
+            Op::EndTextSection,
+        ]);
+    }
+
+    fn right(&mut self, right_edge: f32, y: f32, style: Style, value: &str) {
+        self.text(right_edge - text_width(value, style.size), y, style, value);
+    }
+
+    fn rule(&mut self, y: f32, from: f32, to: f32) {
+        let point = |x: f32| LinePoint {
+            p: Point::new(Mm(x), Mm(y)),
+            bezier: false,
+        };
+        self.ops.extend([
+            Op::SetOutlineColor {
+                col: Color::Rgb(Rgb {
+                    r: 0.6,
+                    g: 0.6,
+                    b: 0.6,
+                    icc_profile: None,
+                }),
+            },
+            Op::SetOutlineThickness { pt: Pt(0.5) },
+            Op::DrawLine {
+                line: Line {
+                    points: vec![point(from), point(to)],
+                    is_closed: false,
+                },
+            },
+        ]);
+    }
+}
+
+// Width in mm from Helvetica's metrics. Exact for digits and separators, which is what
+// gets right-aligned; other characters use an average width.
+fn text_width(value: &str, size: f32) -> f32 {
+    let units: u32 = value
+        .chars()
+        .map(|c| match c {
+            '0'..='9' => 556,
+            '.' | ',' | ' ' => 278,
+            '-' => 333,
+            'a'..='z' => 500,
+            _ => 667,
+        })
+        .sum();
+    units as f32 / 1000.0 * size * 25.4 / 72.0
+}
@@ -0,0 +1,155 @@
+use crate::models::{Invoice, PayeeInfo};
+use percent_encoding::{utf8_percent_encode, AsciiSet, NON_ALPHANUMERIC};
+use qrcode::{Color, QrCode};
+use std::fs::File;
+use std::io::BufWriter;
+use std::path::Path;
+
+// Pixels per QR module, and the blank modules scanners need around the code
+const MODULE_PIXELS: usize = 8;
+const QUIET_ZONE: usize = 4;
+
+// Left as they are in parameter values; UPI apps differ on the rest, so it's all escaped
+const UPI_VALUE: &AsciiSet = &NON_ALPHANUMERIC
+    .remove(b'-')
+    .remove(b'.')
+    .remove(b'_')
+    .remove(b'@');
+
+/// Builds the UPI payment URI for an invoice's total, e.g.
+/// `upi://pay?pa=shop@okbank&pn=Shop&am=1180.00&cu=INR&tr=INV-1&tn=Invoice%20INV-1`.
+/// The invoice number goes in as the transaction reference so the payment can be matched
+/// back to it.
+pub fn upi_uri(invoice: &Invoice, payee: &PayeeInfo) -> Result<String, String> {
+    check_vpa(&payee.vpa)?;
+    let name = payee.name.trim();
+    if name.is_empty() {
+        return Err("Payee name is required for a UPI payment QR".to_string());
+    }
+    if !(invoice.total.is_finite() && invoice.total > 0.0) {
+        return Err(format!(
+            "Invoice {} has a total of {:.2}; a payment QR needs a positive amount",
+            invoice.invoice_number, invoice.total
+        ));
+    }
+    let mut params = vec![
+        ("pa", payee.vpa.trim().to_string()),
+        ("pn", name.to_string()),
+    ];
+    if let Some(code) = payee.merchant_code.as_deref() {
+        if code.len() != 4 || !code.bytes().all(|b| b.is_ascii_digit()) {
+            return Err(format!(
+                "merchant_code '{}' is not a four-digit merchant category code",
+                code
+            ));
+        }
+        params.push(("mc", code.to_string()));
+    }
+    params.extend([
+        ("am", format!("{:.2}", invoice.total)),
+        ("cu", "INR".to_string()),
+        ("tr", invoice.invoice_number.clone()),
+        ("tn", format!("Invoice {}", invoice.invoice_number)),
+    ]);
+    let query: Vec<String> = params
+        .iter()
+        .map(|(key, value)| format!("{}={}", key, utf8_percent_encode(value, UPI_VALUE)))
+        .collect();
+    Ok(format!("upi://pay?{}", query.join("&")))
+}
+
+/// Renders `data` as a black-on-white QR code PNG at `path`.
+pub fn write_png(data: &str, path: &Path) -> Result<(), String> {
+    let code =
+        QrCode::new(data.as_bytes()).map_err(|e| format!("Failed to build QR code: {}", e))?;
+    let modules = code.width();
+    let colors = code.to_colors();
+    let side = (modules + 2 * QUIET_ZONE) * MODULE_PIXELS;
+    let dark = |x: usize, y: usize| {
+        let (x, y) = (x / MODULE_PIXELS, y / MODULE_PIXELS);
+        (QUIET_ZONE..QUIET_ZONE + modules).contains(&x)
+            && (QUIET_ZONE..QUIET_ZONE + modules).contains(&y)
+            && colors[(y - QUIET_ZONE) * modules + (x - QUIET_ZONE)] == Color::Dark
+    };
+    let pixels: Vec<u8> = (0..side)
+        .flat_map(|y| (0..side).map(move |x| (x, y)))
+        .map(|(x, y)| if dark(x, y) { 0 } else { 255 })
+        .collect();
+
+    let file = File::create(path).map_err(|e| format!("Failed to create QR code PNG: {}", e))?;
+    let mut encoder = png::Encoder::new(BufWriter::new(file), side as u32, side as u32);
+    encoder.set_color(png::ColorType::Grayscale);
+    encoder.set_depth(png::BitDepth::Eight);
+    encoder
+        .write_header()
+        .and_then(|mut writer| writer.write_image_data(&pixels))
+        .map_err(|e| format!("Failed to write QR code PNG: {}", e))
+}
+
+// A UPI ID is a handle and a payment provider, e.g. "shop.name@okbank"
+fn check_vpa(vpa: &str) -> Result<(), String> {
+    let vpa = vpa.trim();
+    let valid = vpa.split_once('@').is_some_and(|(handle, provider)| {
+        !handle.is_empty()
+            && handle
+                .bytes()
+                .all(|b| b.is_ascii_alphanumeric() || matches!(b, b'.' | b'-' | b'_'))
+            && !provider.is_empty()
+            && provider.bytes().all(|b| b.is_ascii_alphanumeric())
+    });
+    if valid {
+        Ok(())
+    } else {
+        Err(format!(
+            "Payee VPA '{}' is not a UPI ID like 'name@bank'",
+            vpa
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::SimulationResult;
+
+    fn invoice() -> Invoice {
+        let result: SimulationResult =
+            serde_json::from_str(include_str!("../../../backend/test/test_output.json")).unwrap();
+        result.invoices.into_iter().next().unwrap()
+    }
+
+    fn payee() -> PayeeInfo {
+        PayeeInfo {
+            vpa: "acme.traders@okbank".to_string(),
+            name: "Acme & Sons".to_string(),
+            merchant_code: None,
+        }
+    }
+
+    #[test]
+    fn the_uri_carries_the_invoice_total() {
+        assert_eq!(
+            upi_uri(&invoice(), &payee()).unwrap(),
+            "upi://pay?pa=acme.traders@okbank&pn=Acme%20%26%20Sons&am=66563.86&cu=INR\
+             &tr=GST%2FFY24-25%2F03572&tn=Invoice%20GST%2FFY24-25%2F03572"
+        );
+        let mut unpaid = invoice();
+        unpaid.total = 0.0;
+        assert!(upi_uri(&unpaid, &payee()).is_err());
+    }
+
+    #[test]
+    fn rejects_a_malformed_vpa_or_merchant_code() {
+        let mut payee = payee();
+        payee.merchant_code = Some("54".to_string());
+        assert_eq!(
+            upi_uri(&invoice(), &payee).unwrap_err(),
+            "merchant_code '54' is not a four-digit merchant category code"
+        );
+        payee.vpa = "acme traders".to_string();
+        assert_eq!(
+            upi_uri(&invoice(), &payee).unwrap_err(),
+            "Payee VPA 'acme traders' is not a UPI ID like 'name@bank'"
+        );
+    }
+}
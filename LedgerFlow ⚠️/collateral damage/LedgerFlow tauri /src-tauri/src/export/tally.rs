@@ -0,0 +1,211 @@
+use crate::models::{Invoice, SimulationResult};
+use crate::rounding::RoundingMode;
+use quick_xml::events::{BytesDecl, BytesText, Event};
+use quick_xml::Writer;
+use serde_json::Value;
+use std::collections::BTreeMap;
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::path::Path;
+
+// Ledger credited with the line items; the party ledger is the customer's name
+const SALES_LEDGER: &str = "Sales";
+
+/// Writes a Tally import file with one sales voucher per invoice: the customer is debited
+/// the total, and the sales ledger and each tax ledger are credited. Invoices without a
+/// tax breakdown get a single sales line for the whole total so the voucher still
+/// balances. Amounts are rounded with the result's `rounding_mode`.
+pub fn write_invoices(result: &SimulationResult, company: &str, path: &Path) -> Result<(), String> {
+    let file = File::create(path).map_err(|e| format!("Failed to create Tally XML: {}", e))?;
+    let mut out = BufWriter::new(file);
+    write_envelope(&mut out, result, company)
+        .and_then(|()| out.flush())
+        .map_err(|e| format!("Failed to write Tally XML: {}", e))
+}
+
+fn write_envelope<W: Write>(
+    out: W,
+    result: &SimulationResult,
+    company: &str,
+) -> io::Result<()> {
+    let mut writer = Writer::new_with_indent(out, b' ', 2);
+    writer.write_event(Event::Decl(BytesDecl::new("1.0", Some("UTF-8"), None)))?;
+    writer.create_element("ENVELOPE").write_inner_content(|w| {
+        w.create_element("HEADER")
+            .write_inner_content(|w| text(w, "TALLYREQUEST", "Import Data"))?;
+        w.create_element("BODY").write_inner_content(|w| {
+            w.create_element("IMPORTDATA").write_inner_content(|w| {
+                w.create_element("REQUESTDESC").write_inner_content(|w| {
+                    text(w, "REPORTNAME", "Vouchers")?;
+                    w.create_element("STATICVARIABLES")
+                        .write_inner_content(|w| text(w, "SVCURRENTCOMPANY", company))?;
+                    Ok(())
+                })?;
+                w.create_element("REQUESTDATA").write_inner_content(|w| {
+                    let rounding = RoundingMode::or_default(result.rounding_mode.as_deref());
+                    for invoice in &result.invoices {
+                        w.create_element("TALLYMESSAGE")
+                            .with_attribute(("xmlns:UDF", "TallyUDF"))
+                            .write_inner_content(|w| voucher(w, invoice, rounding))?;
+                    }
+                    Ok(())
+                })?;
+                Ok(())
+            })?;
+            Ok(())
+        })?;
+        Ok(())
+    })?;
+    Ok(())
+}
+
+fn voucher<W: Write>(
+    w: &mut Writer<W>,
+    invoice: &Invoice,
+    rounding: RoundingMode,
+) -> io::Result<()> {
+    let party = invoice.customer_name();
+    w.create_element("VOUCHER")
+        .with_attribute(("VCHTYPE", "Sales"))
+        .with_attribute(("ACTION", "Create"))
+        .write_inner_content(|w| {
+            // Tally wants YYYYMMDD
+            text(w, "DATE", &invoice.date.replace('-', ""))?;
+            text(w, "VOUCHERTYPENAME", "Sales")?;
+            text(w, "VOUCHERNUMBER", &invoice.invoice_number)?;
+            text(w, "PARTYLEDGERNAME", party)?;
+            // Debits are negative in Tally's import format
+            ledger_entry(w, party, -invoice.total, rounding)?;
+
+            let taxes = tax_ledgers(invoice.tax_breakdown.as_ref());
+            if taxes.is_empty() {
+                ledger_entry(w, SALES_LEDGER, invoice.total, rounding)?;
+            } else {
+                for item in &invoice.items {
+                    ledger_entry(w, SALES_LEDGER, item.line_amount(), rounding)?;
+                }
+                for (ledger, amount) in &taxes {
+                    ledger_entry(w, ledger, *amount, rounding)?;
+                }
+            }
+            Ok(())
+        })?;
+    Ok(())
+}
+
+fn ledger_entry<W: Write>(
+    w: &mut Writer<W>,
+    ledger: &str,
+    amount: f64,
+    rounding: RoundingMode,
+) -> io::Result<()> {
+    w.create_element("ALLLEDGERENTRIES.LIST")
+        .write_inner_content(|w| {
+            text(w, "LEDGERNAME", ledger)?;
+            text(
+                w,
+                "ISDEEMEDPOSITIVE",
+                if amount < 0.0 { "Yes" } else { "No" },
+            )?;
+            text(w, "AMOUNT", &rounding.format(amount, 2))
+        })?;
+    Ok(())
+}
+
+// One ledger per tax head, e.g. CGST and SGST. The breakdown is either flat
+// (`{"CGST": 9.0, "CGST_rate": 9}`) or grouped by rate
+// (`{"18%": {"taxable": 100.0, "cgst": 9.0, "sgst": 9.0}}`); rates and taxable
+// values aren't amounts and are skipped.
+fn tax_ledgers(breakdown: Option<&Value>) -> BTreeMap<String, f64> {
+    let mut ledgers = BTreeMap::new();
+    let Some(fields) = breakdown.and_then(Value::as_object) else {
+        return ledgers;
+    };
+    let mut add = |key: &str, value: &Value| {
+        let key = key.to_uppercase();
+        if key.ends_with("_RATE") || key == "TAXABLE" {
+            return;
+        }
+        if let Some(amount) = value.as_f64() {
+            *ledgers.entry(key).or_insert(0.0) += amount;
+        }
+    };
+    for (key, value) in fields {
+        match value.as_object() {
+            Some(group) => group.iter().for_each(|(key, value)| add(key, value)),
+            None => add(key, value),
+        }
+    }
+    ledgers.retain(|_, amount| *amount != 0.0);
+    ledgers
+}
+
+fn text<W: Write>(w: &mut Writer<W>, tag: &str, value: &str) -> io::Result<()> {
+    w.create_element(tag)
+        .write_text_content(BytesText::new(value))?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn result() -> SimulationResult {
+        serde_json::from_str(include_str!("../../../backend/test/test_output.json")).unwrap()
+    }
+
+    fn write(result: &SimulationResult) -> String {
+        let mut out = Vec::new();
+        write_envelope(&mut out, result, "Acme & Sons").unwrap();
+        String::from_utf8(out).unwrap()
+    }
+
+    // Reads every event through to the end, which fails on unbalanced or unescaped markup
+    fn assert_well_formed(xml: &str) {
+        let mut reader = quick_xml::Reader::from_str(xml);
+        loop {
+            match reader.read_event() {
+                Ok(Event::Eof) => break,
+                Ok(_) => {}
+                Err(e) => panic!("malformed XML at {}: {}", reader.buffer_position(), e),
+            }
+        }
+    }
+
+    #[test]
+    fn writes_one_voucher_per_invoice() {
+        let xml = write(&result());
+        assert_well_formed(&xml);
+        assert!(xml.starts_with("<?xml version=\"1.0\" encoding=\"UTF-8\"?>"));
+        assert!(xml.contains("<SVCURRENTCOMPANY>Acme &amp; Sons</SVCURRENTCOMPANY>"));
+        assert_eq!(xml.matches("<VOUCHER VCHTYPE=\"Sales\"").count(), 12);
+        assert!(xml.contains("<DATE>20240711</DATE>"));
+        assert!(xml.contains("<LEDGERNAME>CGST</LEDGERNAME>"));
+        assert!(!xml.contains("CGST_RATE"));
+    }
+
+    #[test]
+    fn an_invoice_without_taxes_gets_one_sales_line() {
+        let mut result = result();
+        result.invoices.truncate(1);
+        result.invoices[0].tax_breakdown = None;
+        let xml = write(&result);
+        assert_well_formed(&xml);
+        assert_eq!(xml.matches("<ALLLEDGERENTRIES.LIST>").count(), 2);
+        assert!(xml.contains("<AMOUNT>-66563.86</AMOUNT>"), "{}", xml);
+        assert!(xml.contains("<AMOUNT>66563.86</AMOUNT>"), "{}", xml);
+    }
+
+    #[test]
+    fn grouped_breakdowns_sum_per_tax_head() {
+        let breakdown = serde_json::json!({
+            "5%": {"taxable": 100.0, "cgst": 2.5, "sgst": 2.5},
+            "18%": {"taxable": 200.0, "cgst": 18.0, "sgst": 18.0},
+            "IGST": 0.0,
+        });
+        let ledgers = tax_ledgers(Some(&breakdown));
+        let expected = BTreeMap::from([("CGST".to_string(), 20.5), ("SGST".to_string(), 20.5)]);
+        assert_eq!(ledgers, expected);
+        assert!(tax_ledgers(None).is_empty());
+    }
+}
@@ -0,0 +1,84 @@
+use crate::models::SimulationResult;
+use crate::rounding::RoundingMode;
+use rust_xlsxwriter::{Format, Workbook, Worksheet, XlsxError};
+use std::path::Path;
+
+// Header and column width for each sheet. Widths are fixed because a constant-memory
+// sheet has already flushed its rows by the time `autofit` could measure them.
+const INVOICE_COLUMNS: &[(&str, f64)] = &[
+    ("Invoice Number", 20.0),
+    ("Date", 12.0),
+    ("Customer", 32.0),
+    ("Subtotal", 14.0),
+    ("Total", 14.0),
+];
+const ITEM_COLUMNS: &[(&str, f64)] = &[
+    ("Invoice Number", 20.0),
+    ("SKU", 12.0),
+    ("Item", 32.0),
+    ("Quantity", 10.0),
+    ("Rate", 12.0),
+    ("Amount", 14.0),
+];
+const CURRENCY_FORMAT: &str = "#,##0.00";
+
+/// Writes an "Invoices" sheet with one header row per invoice and a "Line Items" sheet
+/// with one row per item. An empty result still produces both sheets with headers.
+/// Amounts are stored rounded with the result's `rounding_mode`, so the cells add up to
+/// what the other exports print.
+///
+/// Both sheets are written in constant-memory mode, which streams each finished row to
+/// a temp file, so large results don't hold every cell in memory until the save.
+pub fn write_invoices(result: &SimulationResult, path: &Path) -> Result<(), String> {
+    build_workbook(result, path).map_err(|e| format!("Failed to write workbook: {}", e))
+}
+
+fn build_workbook(result: &SimulationResult, path: &Path) -> Result<(), XlsxError> {
+    let bold = Format::new().set_bold();
+    let currency = Format::new().set_num_format(CURRENCY_FORMAT);
+    let rounding = RoundingMode::or_default(result.rounding_mode.as_deref());
+    let cents = |value: f64| rounding.round_cents(value);
+    let mut workbook = Workbook::new();
+
+    // Rows have to be written in order, so each sheet gets its own pass
+    let invoices = workbook.add_worksheet_with_constant_memory();
+    invoices.set_name("Invoices")?;
+    write_headers(invoices, INVOICE_COLUMNS, &bold)?;
+    for (row, invoice) in (1..).zip(&result.invoices) {
+        invoices.write_string(row, 0, &invoice.invoice_number)?;
+        invoices.write_string(row, 1, &invoice.date)?;
+        invoices.write_string(row, 2, invoice.customer_name())?;
+        invoices.write_number_with_format(row, 3, cents(invoice.subtotal), &currency)?;
+        invoices.write_number_with_format(row, 4, cents(invoice.total), &currency)?;
+    }
+
+    let items = workbook.add_worksheet_with_constant_memory();
+    items.set_name("Line Items")?;
+    write_headers(items, ITEM_COLUMNS, &bold)?;
+    let lines = result
+        .invoices
+        .iter()
+        .flat_map(|invoice| invoice.items.iter().map(move |item| (invoice, item)));
+    for (row, (invoice, item)) in (1..).zip(lines) {
+        items.write_string(row, 0, &invoice.invoice_number)?;
+        items.write_string(row, 1, item.sku.as_deref().unwrap_or(""))?;
+        items.write_string(row, 2, item.normalized_name())?;
+        items.write_number(row, 3, item.normalized_qty())?;
+        items.write_number_with_format(row, 4, cents(item.rate), &currency)?;
+        items.write_number_with_format(row, 5, cents(item.line_amount()), &currency)?;
+    }
+
+    workbook.save(path)
+}
+
+fn write_headers(
+    sheet: &mut Worksheet,
+    columns: &[(&str, f64)],
+    format: &Format,
+) -> Result<(), XlsxError> {
+    for (col, (header, width)) in (0..).zip(columns) {
+        sheet.set_column_width(col, *width)?;
+        sheet.write_string_with_format(0, col, *header, format)?;
+    }
+    Ok(())
+}
@@ -0,0 +1,170 @@
+use crate::models::{Invoice, SimulationConfig, SimulationResult};
+use crate::stats;
+use std::time::Duration;
+
+// Renumbered invoices are padded to at least this many digits
+const MIN_SEQUENCE_DIGITS: usize = 4;
+
+/// Points `config` at the revenue still to generate and tells the engine where `previous`
+/// left off: numbering continues after its highest invoice number and its customers are
+/// offered for repeats.
+pub fn prepare(
+    config: &mut SimulationConfig,
+    previous: &SimulationResult,
+    additional_target: f64,
+) -> Result<(), String> {
+    if previous.error.is_some() {
+        return Err("Cannot extend a simulation that failed".to_string());
+    }
+    if additional_target <= 0.0 || additional_target.is_nan() {
+        return Err("Additional revenue target must be greater than 0".to_string());
+    }
+
+    config.revenue_target = additional_target;
+    // A manual count would generate that many invoices however small the top-up is
+    config.invoice_count_mode = Some("auto".to_string());
+    config.manual_invoice_count = None;
+    config.invoice_number_start = Some(highest_number(&previous.invoices).map_or(1, |n| n + 1));
+    config.existing_customers = customers(&previous.invoices);
+    Ok(())
+}
+
+/// Appends the extension's invoices to the earlier result and recomputes the stats over
+/// both. The seed stays the earlier run's, since the extension alone can't be reproduced
+/// from it anyway.
+pub fn append(mut previous: SimulationResult, extension: SimulationResult) -> SimulationResult {
+    // Targets and engine time add up across the two runs
+    let (target, seconds) = [&previous, &extension]
+        .iter()
+        .filter_map(|result| result.stats.as_ref())
+        .fold((0.0, 0.0), |(target, seconds), stats| {
+            (target + stats.revenue_target, seconds + stats.duration_secs)
+        });
+    previous.invoices.extend(extension.invoices);
+    previous.warnings.extend(extension.warnings);
+    if previous.currency.is_none() {
+        previous.currency = extension.currency;
+    }
+    // The earlier run's files don't hold the extension's invoices
+    previous.output_paths.clear();
+    // Judged by the tolerance the earlier run was, since the config isn't at hand here
+    let tolerance = previous
+        .stats
+        .as_ref()
+        .and_then(|stats| stats.convergence.as_ref())
+        .map(|convergence| convergence.tolerance);
+    let mut stats = stats::compute(&previous.invoices, target, Duration::from_secs_f64(seconds));
+    stats.convergence = tolerance.and_then(|tolerance| stats::convergence(&stats, tolerance));
+    previous.stats = Some(stats);
+    previous
+}
+
+/// The largest running number across `invoices`, i.e. the digits ending each invoice
+/// number (`00042` in `GST/FY24-25/00042`).
+pub fn highest_number(invoices: &[Invoice]) -> Option<u64> {
+    invoices
+        .iter()
+        .filter_map(|invoice| sequence_number(&invoice.invoice_number))
+        .max()
+}
+
+pub fn sequence_number(invoice_number: &str) -> Option<u64> {
+    let digits = invoice_number
+        .rsplit(|c: char| !c.is_ascii_digit())
+        .next()?;
+    digits.parse().ok()
+}
+
+// Unique names in first-seen order
+fn customers(invoices: &[Invoice]) -> Vec<String> {
+    let mut names: Vec<String> = Vec::new();
+    for invoice in invoices {
+        let name = invoice.customer_name();
+        if !name.is_empty() && !names.iter().any(|n| n == name) {
+            names.push(name.to_string());
+        }
+    }
+    names
+}
+
+/// Sorts `invoices` by date, keeping the engine's order within a day, and numbers them
+/// `prefix` + a zero-padded sequence from `start`, e.g. `INV-0001` for prefix `INV-`.
+/// Each invoice keeps the engine's number in `original_invoice_number`.
+pub fn renumber(invoices: &mut [Invoice], prefix: &str, start: u64) {
+    // Stable, and ISO dates sort as strings
+    invoices.sort_by(|a, b| a.date.cmp(&b.date));
+    let last = start + invoices.len().saturating_sub(1) as u64;
+    let width = last.to_string().len().max(MIN_SEQUENCE_DIGITS);
+    for (number, invoice) in (start..).zip(invoices.iter_mut()) {
+        let renumbered = format!("{}{:0width$}", prefix, number, width = width);
+        let original = std::mem::replace(&mut invoice.invoice_number, renumbered);
+        // A second pass keeps the first original
+        invoice.original_invoice_number.get_or_insert(original);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn result() -> SimulationResult {
+        serde_json::from_str(include_str!("../../backend/test/test_output.json")).unwrap()
+    }
+
+    #[test]
+    fn reads_the_running_number_at_the_end() {
+        assert_eq!(sequence_number("GST/FY24-25/00042"), Some(42));
+        assert_eq!(sequence_number("INV-7a"), None);
+        assert_eq!(highest_number(&result().invoices), Some(3583));
+    }
+
+    #[test]
+    fn extension_numbering_has_no_gaps_or_collisions() {
+        let previous = result();
+        let mut config: SimulationConfig =
+            serde_json::from_str(include_str!("../../backend/test/test_config.json")).unwrap();
+        prepare(&mut config, &previous, 5000.0).unwrap();
+        assert_eq!(config.revenue_target, 5000.0);
+        assert_eq!(config.invoice_number_start, Some(3584));
+        // One of the twelve invoices is for a returning customer
+        assert_eq!(config.existing_customers.len(), 11);
+
+        // What the engine would make of that start
+        let mut extension = result();
+        extension.invoices.truncate(3);
+        for (number, invoice) in (3584..).zip(&mut extension.invoices) {
+            invoice.invoice_number = format!("GST/FY24-25/{:05}", number);
+        }
+        let combined = append(previous, extension);
+        let numbers: Vec<u64> = combined
+            .invoices
+            .iter()
+            .filter_map(|invoice| sequence_number(&invoice.invoice_number))
+            .collect();
+        assert_eq!(numbers, (3572..=3586).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn renumber_sorts_by_date_and_keeps_the_first_original() {
+        let mut invoices = result().invoices;
+        invoices.swap(0, 11);
+        renumber(&mut invoices, "INV-", 9);
+        let numbers: Vec<&str> = invoices.iter().map(|i| i.invoice_number.as_str()).collect();
+        assert_eq!(numbers[..3], ["INV-0009", "INV-0010", "INV-0011"]);
+        assert_eq!(numbers[11], "INV-0020");
+        assert_eq!(invoices[0].date, "2024-07-11");
+        assert_eq!(
+            invoices[0].original_invoice_number.as_deref(),
+            Some("GST/FY24-25/03572")
+        );
+
+        // Past 9999 the numbers widen rather than wrap
+        renumber(&mut invoices, "X", 9995);
+        assert_eq!(invoices[0].invoice_number, "X09995");
+        assert_eq!(invoices[11].invoice_number, "X10006");
+        assert_eq!(
+            invoices[0].original_invoice_number.as_deref(),
+            Some("GST/FY24-25/03572")
+        );
+    }
+}
@@ -0,0 +1,245 @@
+use crate::models::{Invoice, SimulationResult};
+use crate::stats;
+use crate::validation;
+use chrono::NaiveDate;
+use std::collections::{BTreeMap, HashSet};
+use std::fs::{self, File};
+use std::io::BufWriter;
+use std::path::Path;
+use std::time::Duration;
+
+// Where `split_by_month` puts invoices whose date doesn't parse
+const UNKNOWN_MONTH: &str = "unknown";
+
+/// Keeps the invoices dated from `from` to `to`, both inclusive, and recomputes the stats
+/// for them against the run's original target. An invoice whose date doesn't parse is
+/// left out with a warning. The run's output files are dropped from the result, since
+/// they hold every invoice.
+pub fn by_date(
+    mut result: SimulationResult,
+    from: &str,
+    to: &str,
+) -> Result<SimulationResult, String> {
+    let (from, to) = match (
+        validation::parse_date("from", from)?,
+        validation::parse_date("to", to)?,
+    ) {
+        (start, end) if start > end => {
+            return Err(format!("from '{}' is after to '{}'", from, to));
+        }
+        dates => dates,
+    };
+
+    let mut unparseable = Vec::new();
+    result.invoices.retain(|invoice| {
+        match NaiveDate::parse_from_str(invoice.date.trim(), "%Y-%m-%d") {
+            Ok(date) => (from..=to).contains(&date),
+            Err(_) => {
+                unparseable.push(format!(
+                    "Invoice {}: date '{}' is not a valid YYYY-MM-DD date, so it was left out of the date filter",
+                    invoice.invoice_number, invoice.date
+                ));
+                false
+            }
+        }
+    });
+    result.warnings.extend(unparseable);
+
+    if let Some(previous) = result.stats.take() {
+        let mut subset = stats::compute(
+            &result.invoices,
+            previous.revenue_target,
+            Duration::from_secs_f64(previous.duration_secs),
+        );
+        subset.convergence = previous
+            .convergence
+            .and_then(|convergence| stats::convergence(&subset, convergence.tolerance));
+        result.stats = Some(subset);
+    }
+    result.output_paths.clear();
+    Ok(result)
+}
+
+/// Splits a result into one per calendar month, keyed YYYY-MM in date order, with
+/// invoices whose date doesn't parse under "unknown" at the end. Each keeps the run's
+/// metadata and the warnings about its own invoices, and gets stats of its own with no
+/// revenue target, since the run's target covered every month.
+pub fn split_by_month(result: SimulationResult) -> Vec<(String, SimulationResult)> {
+    let mut months: BTreeMap<String, Vec<Invoice>> = BTreeMap::new();
+    let mut unknown = Vec::new();
+    for invoice in result.invoices {
+        match NaiveDate::parse_from_str(invoice.date.trim(), "%Y-%m-%d") {
+            Ok(date) => months
+                .entry(date.format("%Y-%m").to_string())
+                .or_default()
+                .push(invoice),
+            Err(_) => unknown.push(invoice),
+        }
+    }
+    let mut splits: Vec<(String, Vec<Invoice>)> = months.into_iter().collect();
+    if !unknown.is_empty() {
+        splits.push((UNKNOWN_MONTH.to_string(), unknown));
+    }
+    let numbers: HashSet<String> = splits
+        .iter()
+        .flat_map(|(_, invoices)| invoices)
+        .map(|invoice| invoice.invoice_number.clone())
+        .collect();
+
+    splits
+        .into_iter()
+        .map(|(month, invoices)| {
+            let own: HashSet<&str> = invoices.iter().map(|i| i.invoice_number.as_str()).collect();
+            // Warnings about another month's invoices are left to that month's split
+            let warnings = result
+                .warnings
+                .iter()
+                .filter(|warning| {
+                    invoice_named(warning).is_none_or(|n| own.contains(n) || !numbers.contains(n))
+                })
+                .cloned()
+                .collect();
+            let stats = stats::compute(&invoices, 0.0, Duration::ZERO);
+            let split = SimulationResult {
+                invoices,
+                status: result.status.clone(),
+                error: result.error.clone(),
+                seed_used: result.seed_used,
+                warnings,
+                currency: result.currency.clone(),
+                rounding_mode: result.rounding_mode.clone(),
+                stats: Some(stats),
+                audit_path: None,
+                output_paths: Vec::new(),
+                engine_version: result.engine_version.clone(),
+                from_cache: result.from_cache,
+            };
+            (month, split)
+        })
+        .collect()
+}
+
+/// Writes `split_by_month`'s results into `dir` as `YYYY-MM.json`, plus `unknown.json`
+/// when some dates didn't parse, and returns the paths written.
+pub fn write_by_month(result: SimulationResult, dir: &Path) -> Result<Vec<String>, String> {
+    fs::create_dir_all(dir).map_err(|e| format!("Failed to create {}: {}", dir.display(), e))?;
+    split_by_month(result)
+        .into_iter()
+        .map(|(month, split)| {
+            let path = dir.join(format!("{}.json", month));
+            let file = File::create(&path)
+                .map_err(|e| format!("Failed to create {}: {}", path.display(), e))?;
+            serde_json::to_writer_pretty(BufWriter::new(file), &split)
+                .map_err(|e| format!("Failed to write {}: {}", path.display(), e))?;
+            Ok(path.to_string_lossy().into_owned())
+        })
+        .collect()
+}
+
+/// The number in a warning about one invoice, which all start "Invoice <number>: ".
+pub fn invoice_named(warning: &str) -> Option<&str> {
+    warning
+        .strip_prefix("Invoice ")
+        .and_then(|rest| rest.split_once(": "))
+        .map(|(number, _)| number)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn result() -> SimulationResult {
+        let mut result: SimulationResult =
+            serde_json::from_str(include_str!("../../backend/test/test_output.json")).unwrap();
+        result.stats = Some(stats::compute(
+            &result.invoices,
+            100000.0,
+            Duration::from_secs(2),
+        ));
+        result
+    }
+
+    #[test]
+    fn keeps_invoices_in_the_range_and_recomputes_their_stats() {
+        let mut result = result();
+        result.invoices[2].date = "soon".to_string();
+        result.output_paths.push("invoices.json".to_string());
+        let filtered = by_date(result, "2024-07-12", "2024-07-17").unwrap();
+        let dates: Vec<&str> = filtered.invoices.iter().map(|i| i.date.as_str()).collect();
+        assert_eq!(dates, ["2024-07-12", "2024-07-15", "2024-07-17"]);
+        assert_eq!(
+            filtered.warnings,
+            ["Invoice GST/FY24-25/03574: date 'soon' is not a valid YYYY-MM-DD date, so it was left out of the date filter"]
+        );
+        assert!(filtered.output_paths.is_empty());
+
+        let stats = filtered.stats.unwrap();
+        assert_eq!(stats.invoice_count, 3);
+        let kept: f64 = filtered.invoices.iter().map(|i| i.total).sum();
+        assert_eq!(stats.total_revenue, kept);
+        // Still measured against the whole run's target
+        assert_eq!(stats.revenue_target, 100000.0);
+        assert_eq!(stats.duration_secs, 2.0);
+
+        let error = by_date(self::result(), "2024-08-01", "2024-07-01").unwrap_err();
+        assert_eq!(error, "from '2024-08-01' is after to '2024-07-01'");
+    }
+
+    #[test]
+    fn splits_by_month_with_each_months_warnings() {
+        let mut result = result();
+        result.invoices[10].date = "2024-08-01".to_string();
+        result.invoices[11].date = "someday".to_string();
+        let (august, undated) = (
+            result.invoices[10].invoice_number.clone(),
+            result.invoices[11].invoice_number.clone(),
+        );
+        result.warnings = vec![
+            format!("Invoice {}: late", august),
+            "Run took a while".to_string(),
+        ];
+        let splits = split_by_month(result);
+        let months: Vec<&str> = splits.iter().map(|(month, _)| month.as_str()).collect();
+        assert_eq!(months, ["2024-07", "2024-08", "unknown"]);
+        assert_eq!(splits[0].1.invoices.len(), 10);
+        assert_eq!(splits[0].1.warnings, ["Run took a while"]);
+        assert_eq!(splits[1].1.warnings.len(), 2);
+        assert_eq!(splits[2].1.invoices[0].invoice_number, undated);
+    }
+
+    #[test]
+    fn writes_one_file_per_month() {
+        let mut result = result();
+        for (i, invoice) in result.invoices.iter_mut().enumerate() {
+            let month = match i {
+                0..=5 => "05",
+                6..=8 => "06",
+                _ => "07",
+            };
+            invoice.date.replace_range(5..7, month);
+        }
+        let dir = std::env::temp_dir().join(format!("ledgerflow_months_{}", std::process::id()));
+        let paths = write_by_month(result, &dir).unwrap();
+        let names: Vec<String> = paths
+            .iter()
+            .map(|path| {
+                Path::new(path)
+                    .file_name()
+                    .unwrap()
+                    .to_string_lossy()
+                    .into_owned()
+            })
+            .collect();
+        assert_eq!(names, ["2024-05.json", "2024-06.json", "2024-07.json"]);
+        let counts: Vec<usize> = paths
+            .iter()
+            .map(|path| {
+                let split: SimulationResult =
+                    serde_json::from_reader(File::open(path).unwrap()).unwrap();
+                split.invoices.len()
+            })
+            .collect();
+        assert_eq!(counts, [6, 3, 3]);
+        let _ = fs::remove_dir_all(&dir);
+    }
+}
@@ -0,0 +1,89 @@
+use crate::engine::{self, ActiveChildren, RunOptions};
+use crate::python::PythonConfig;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+use std::time::Duration;
+
+// `--selfcheck` only imports a few modules, so anything slower than this is a problem
+const SELFCHECK_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// Whether a simulation could run right now, with enough detail to say why not.
+#[derive(Debug, Default, Serialize)]
+pub struct EngineHealth {
+    pub healthy: bool,
+    pub interpreter: Option<String>,
+    pub engine_path: Option<String>,
+    pub python_version: Option<String>,
+    pub engine_version: Option<String>,
+    // Module name -> importable
+    pub modules: BTreeMap<String, bool>,
+    pub error: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct SelfCheck {
+    engine_version: String,
+    python_version: String,
+    modules: BTreeMap<String, bool>,
+}
+
+/// Resolves the interpreter and runs `engine.py --selfcheck`. Failures at any step are
+/// reported in the returned `EngineHealth` rather than as an error.
+pub async fn check(python: &PythonConfig, script: Result<PathBuf, String>) -> EngineHealth {
+    let mut health = EngineHealth::default();
+    if let Err(e) = run_selfcheck(python, script, &mut health).await {
+        health.error = Some(e);
+    }
+    health
+}
+
+async fn run_selfcheck(
+    python: &PythonConfig,
+    script: Result<PathBuf, String>,
+    health: &mut EngineHealth,
+) -> Result<(), String> {
+    let interpreter = python.resolve().await?;
+    health.interpreter = Some(interpreter.to_string());
+    let script = script?;
+    health.engine_path = Some(script.display().to_string());
+
+    let mut command = interpreter.command();
+    command
+        .current_dir(python.working_dir(&script))
+        .arg(&script)
+        .arg("--selfcheck");
+    let options = RunOptions {
+        timeout: Some(SELFCHECK_TIMEOUT),
+        ..RunOptions::default()
+    };
+    // Its own map, so cancelling a simulation doesn't cancel the check
+    let output = engine::run_engine(command, options, &ActiveChildren::default(), |_| {})
+        .await
+        .map_err(|e| e.to_string())?;
+    if !output.status.success() {
+        // Older engines without --selfcheck land here too
+        return Err(format!(
+            "Engine self-check failed: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        ));
+    }
+    let stdout = engine::decode_output(&output.stdout)?;
+    let report: SelfCheck = serde_json::from_str(engine::last_json_object(stdout))
+        .map_err(|e| format!("Failed to parse engine self-check: {}", e))?;
+
+    health.python_version = Some(report.python_version);
+    health.engine_version = Some(report.engine_version);
+    let missing: Vec<&str> = report
+        .modules
+        .iter()
+        .filter(|(_, available)| !**available)
+        .map(|(name, _)| name.as_str())
+        .collect();
+    if !missing.is_empty() {
+        health.error = Some(format!("Missing Python modules: {}", missing.join(", ")));
+    }
+    health.healthy = missing.is_empty();
+    health.modules = report.modules;
+    Ok(())
+}
@@ -0,0 +1,104 @@
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use tracing_subscriber::filter::LevelFilter;
+use tracing_subscriber::fmt::MakeWriter;
+use tracing_subscriber::layer::SubscriberExt;
+use tracing_subscriber::util::SubscriberInitExt;
+use tracing_subscriber::{reload, Registry};
+
+const LOG_FILE: &str = "ledgerflow.log";
+const DEFAULT_LEVEL: LevelFilter = LevelFilter::INFO;
+
+/// Handles for changing the app's logging once it's running: the level, and whether logs
+/// also go to a file.
+pub struct Logging {
+    level: reload::Handle<LevelFilter, Registry>,
+    file: Arc<Mutex<Option<File>>>,
+}
+
+/// Installs the global subscriber, writing to stdout at info level. Call once, before
+/// anything logs.
+pub fn init() -> Logging {
+    let (level, handle) = reload::Layer::new(DEFAULT_LEVEL);
+    let sink = Sink::default();
+    let file = Arc::clone(&sink.file);
+    tracing_subscriber::registry()
+        .with(level)
+        .with(
+            tracing_subscriber::fmt::layer()
+                .with_ansi(false)
+                .with_writer(sink),
+        )
+        .init();
+    Logging {
+        level: handle,
+        file,
+    }
+}
+
+impl Logging {
+    /// Sets the most verbose level logged: "error", "warn", "info", "debug", "trace" or
+    /// "off".
+    pub fn set_level(&self, level: &str) -> Result<(), String> {
+        let filter: LevelFilter = level.trim().parse().map_err(|_| {
+            format!(
+                "Unknown log level '{}' (expected error, warn, info, debug, trace or off)",
+                level
+            )
+        })?;
+        self.level
+            .modify(|current| *current = filter)
+            .map_err(|e| format!("Failed to change log level: {}", e))
+    }
+
+    /// Appends all further logs to `ledgerflow.log` in `dir` as well as stdout, returning
+    /// the file's path. Calling it again switches to the new directory.
+    pub fn log_to_file(&self, dir: &Path) -> Result<PathBuf, String> {
+        fs::create_dir_all(dir).map_err(|e| format!("Failed to create log directory: {}", e))?;
+        let path = dir.join(LOG_FILE);
+        let file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&path)
+            .map_err(|e| format!("Failed to open log file: {}", e))?;
+        *self
+            .file
+            .lock()
+            .map_err(|_| "Log file lock poisoned".to_string())? = Some(file);
+        Ok(path)
+    }
+}
+
+// stdout plus the log file once one is set. Each event arrives as one `write_all`, so
+// lines from different threads don't interleave in the file.
+#[derive(Clone, Default)]
+struct Sink {
+    file: Arc<Mutex<Option<File>>>,
+}
+
+impl Write for Sink {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        io::stdout().write_all(buf)?;
+        if let Ok(mut file) = self.file.lock() {
+            if let Some(file) = file.as_mut() {
+                // A full disk shouldn't take console logging down with it
+                let _ = file.write_all(buf);
+            }
+        }
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        io::stdout().flush()
+    }
+}
+
+impl<'a> MakeWriter<'a> for Sink {
+    type Writer = Sink;
+
+    fn make_writer(&'a self) -> Self::Writer {
+        self.clone()
+    }
+}
@@ -1,184 +1,1319 @@
 // Prevents additional console window on Windows in release, DO NOT REMOVE!!
 #![cfg_attr(not(debug_assertions), windows_subsystem = "windows")]
 
-use serde::{Deserialize, Serialize};
-use std::process::Command;
-use std::path::Path;
-use std::fs;
-
-#[derive(Debug, Serialize, Deserialize)]
-struct SimulationConfig {
-    revenue_target: f64,
-    start_date: String,
-    end_date: String,
-    invoice_type: String,
-    min_items: i32,
-    max_items: i32,
-    min_invoice_amount: f64,
-    max_invoice_amount: f64,
-    item_filter_mode: String,
-    selected_items: Vec<String>,
-    name_type: String,
-    realism_mode: String,
-    seed: Option<i32>,
-    invoice_count_mode: Option<String>,
-    manual_invoice_count: Option<i32>,
-    reality_buffer: Option<f64>,         // Added for reality buffer
-    distribution_mode: Option<String>,   // Added for invoice distribution
-    customer_repeat_rate: Option<f64>,   // Added for customer repeat rate
+mod anonymize;
+mod audit;
+mod batch;
+mod capabilities;
+mod catalog;
+mod config_store;
+mod currency;
+mod deps;
+mod diff;
+mod distribution;
+mod engine;
+mod engine_log;
+mod engine_output;
+mod engine_path;
+mod error;
+mod export;
+mod extend;
+mod filter;
+mod health;
+mod logging;
+mod manifest;
+mod models;
+mod output_schema;
+mod python;
+mod regenerate;
+mod result_cache;
+mod result_file;
+mod rounding;
+mod sample;
+mod smoke;
+mod stats;
+mod temp_file;
+mod validation;
+mod verify;
+
+use capabilities::{CapabilitiesCache, EngineCapabilities};
+use deps::{DependencyCache, DependencyStatus};
+use engine::{ActiveChildren, RunGuard, RunLock, RunOptions};
+use error::SimulationError;
+use logging::Logging;
+use models::{
+    BatchCheckpoint, CatalogItem, CatalogReport, CustomerSummary, DedupeReport, ExportSummary,
+    Invoice, InvoiceCountEstimate, Manifest, ManifestCheck, MonthlyRevenue, NameType, PayeeInfo,
+    ReconcileReport, ResultDiff, RevenueConvergence, SellerInfo, SimulationConfig,
+    SimulationResult, SmokeTestReport, TaxSummary,
+};
+use python::PythonConfig;
+use rounding::RoundingMode;
+use temp_file::TempFile;
+use serde::Serialize;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use tauri::path::BaseDirectory;
+use tauri::{Emitter, Manager};
+use tracing::Instrument;
+
+#[derive(Debug, Clone, Serialize)]
+struct SimulationProgress {
+    percent: f64,
+}
+
+// Sent as each config of a batch finishes, which isn't in index order
+#[derive(Debug, Clone, Serialize)]
+struct BatchProgress {
+    index: usize,
+    total: usize,
+    succeeded: bool,
+}
+
+#[tauri::command]
+fn validate_config(
+    mut config: SimulationConfig,
+    catalog: Option<Vec<CatalogItem>>,
+) -> Result<Vec<String>, String> {
+    validation::validate(&config)?;
+    let mut warnings: Vec<String> = validation::check_run_size(&config)?.into_iter().collect();
+    if let Some(catalog) = &catalog {
+        validation::validate_selection(&config, catalog)?;
+        validation::validate_catalog_taxes(&config.invoice_type, catalog)?;
+        warnings.extend(validation::fit_max_items(&mut config, catalog)?);
+        let converted = currency::base_catalog(&config, catalog)?;
+        validation::check_amount_range(&config, converted.as_deref().unwrap_or(catalog))?;
+    }
+    warnings.extend(validation::warnings(&config));
+    Ok(warnings)
+}
+
+#[tauri::command]
+fn preview_distribution(config: SimulationConfig) -> Result<Vec<(String, u32)>, String> {
+    distribution::preview(&config)
+}
+
+#[tauri::command]
+fn estimate_invoice_count(config: SimulationConfig) -> Result<InvoiceCountEstimate, String> {
+    distribution::estimate(&config)
+}
+
+#[tauri::command]
+fn aggregate_by_customer(result: SimulationResult) -> Vec<CustomerSummary> {
+    stats::by_customer(&result.invoices)
+}
+
+#[tauri::command]
+fn revenue_convergence(
+    result: SimulationResult,
+    reality_buffer: Option<f64>,
+) -> Result<RevenueConvergence, String> {
+    let target = result
+        .stats
+        .as_ref()
+        .map_or(0.0, |stats| stats.revenue_target);
+    let stats = stats::compute(&result.invoices, target, Duration::ZERO);
+    let tolerance = reality_buffer.unwrap_or(validation::DEFAULT_REALITY_BUFFER);
+    stats::convergence(&stats, tolerance)
+        .ok_or_else(|| "Result has no revenue target to compare against".to_string())
+}
+
+#[tauri::command]
+fn item_count_distribution(result: SimulationResult) -> Vec<(u32, u32)> {
+    stats::item_count_distribution(&result.invoices)
+}
+
+#[tauri::command]
+fn get_name_types() -> Vec<NameType> {
+    validation::NAME_TYPES
+        .iter()
+        .map(|(name, description)| NameType {
+            name: name.to_string(),
+            description: description.to_string(),
+        })
+        .collect()
+}
+
+#[tauri::command]
+fn revenue_by_month(result: SimulationResult) -> Vec<MonthlyRevenue> {
+    stats::by_month(&result.invoices)
+}
+
+#[tauri::command]
+fn tax_summary(result: SimulationResult) -> TaxSummary {
+    let summary = stats::tax_summary(&result.invoices);
+    for warning in &summary.warnings {
+        tracing::warn!(%warning, "Tax summary warning");
+    }
+    summary
+}
+
+#[tauri::command]
+fn diff_results(a: SimulationResult, b: SimulationResult) -> ResultDiff {
+    diff::diff(&a, &b)
+}
+
+#[tauri::command]
+fn build_manifest(
+    config: SimulationConfig,
+    catalog: Vec<CatalogItem>,
+    result: SimulationResult,
+) -> Result<Manifest, String> {
+    manifest::build(&config, &catalog, &result)
+}
+
+#[tauri::command]
+fn verify_manifest(
+    manifest: Manifest,
+    config: SimulationConfig,
+    catalog: Vec<CatalogItem>,
+) -> Result<ManifestCheck, String> {
+    let check = manifest::verify(&manifest, &config, &catalog)?;
+    tracing::info!(matches = check.matches, mismatches = ?check.mismatches, "Verified manifest");
+    Ok(check)
+}
+
+#[tauri::command]
+fn filter_invoices_by_date(
+    result: SimulationResult,
+    from: String,
+    to: String,
+) -> Result<SimulationResult, String> {
+    let before = result.invoices.len();
+    let filtered = filter::by_date(result, &from, &to)?;
+    tracing::info!(
+        before,
+        after = filtered.invoices.len(),
+        %from,
+        %to,
+        "Filtered invoices by date"
+    );
+    Ok(filtered)
+}
+
+#[tauri::command]
+fn split_result_by_month(result: SimulationResult, dir: String) -> Result<Vec<String>, String> {
+    let paths = filter::write_by_month(result, Path::new(&dir))?;
+    tracing::info!(files = paths.len(), dir, "Split result by month");
+    Ok(paths)
+}
+
+#[tauri::command]
+fn sample_invoices(result: SimulationResult, n: usize, seed: Option<i32>) -> Vec<Invoice> {
+    let seed = seed.unwrap_or_else(random_seed);
+    sample::sample(result.invoices, n, seed as u64)
+}
+
+#[tauri::command]
+fn anonymize_result(result: SimulationResult, seed: Option<i32>) -> SimulationResult {
+    let seed = seed.unwrap_or_else(random_seed);
+    anonymize::anonymize(result, seed as u64)
+}
+
+#[tauri::command]
+fn format_amount(
+    value: f64,
+    currency: String,
+    rounding_mode: Option<String>,
+) -> Result<String, String> {
+    let rounding = RoundingMode::parse(rounding_mode.as_deref())?;
+    Ok(currency::format_amount(value, &currency, rounding))
+}
+
+#[tauri::command]
+fn set_python_path(
+    path: String,
+    python: tauri::State<'_, Mutex<PythonConfig>>,
+    capabilities: tauri::State<'_, CapabilitiesCache>,
+    deps: tauri::State<'_, DependencyCache>,
+) -> Result<(), String> {
+    let mut python = python
+        .lock()
+        .map_err(|_| "Python config lock poisoned".to_string())?;
+    let path = path.trim();
+    // An empty path switches back to auto-detection
+    python.path = if path.is_empty() { None } else { Some(path.to_string()) };
+    capabilities.clear();
+    deps.clear();
+    tracing::info!(path = ?python.path, "Python interpreter set");
+    Ok(())
 }
 
-#[derive(Debug, Serialize, Deserialize)]
-struct SimulationResult {
-    invoices: Vec<Invoice>,
-    status: String,
-    error: Option<String>,
+#[tauri::command]
+fn set_engine_env(
+    env: HashMap<String, String>,
+    python: tauri::State<'_, Mutex<PythonConfig>>,
+    capabilities: tauri::State<'_, CapabilitiesCache>,
+    deps: tauri::State<'_, DependencyCache>,
+) -> Result<(), String> {
+    let mut python = python
+        .lock()
+        .map_err(|_| "Python config lock poisoned".to_string())?;
+    tracing::info!(keys = ?env.keys().collect::<Vec<_>>(), "Engine environment overrides set");
+    python.env = env;
+    capabilities.clear();
+    deps.clear();
+    Ok(())
+}
+
+#[tauri::command]
+fn set_engine_working_dir(
+    path: String,
+    python: tauri::State<'_, Mutex<PythonConfig>>,
+) -> Result<(), String> {
+    let path = path.trim();
+    // An empty path goes back to running in the engine script's directory
+    let dir = if path.is_empty() {
+        None
+    } else {
+        let dir = std::path::absolute(path)
+            .map_err(|e| format!("Invalid engine working directory '{}': {}", path, e))?;
+        if !dir.is_dir() {
+            return Err(format!("Engine working directory not found: {}", dir.display()));
+        }
+        Some(dir)
+    };
+    let mut python = python
+        .lock()
+        .map_err(|_| "Python config lock poisoned".to_string())?;
+    tracing::info!(dir = ?dir, "Engine working directory set");
+    python.working_dir = dir;
+    Ok(())
+}
+
+#[tauri::command]
+async fn check_engine_health(
+    app: tauri::AppHandle,
+    python: tauri::State<'_, Mutex<PythonConfig>>,
+) -> Result<health::EngineHealth, String> {
+    let python = python
+        .lock()
+        .map_err(|_| "Python config lock poisoned".to_string())?
+        .clone();
+    let health = health::check(&python, engine_script(&app)).await;
+    tracing::info!(?health, "Engine health checked");
+    Ok(health)
+}
+
+#[tauri::command]
+async fn get_engine_capabilities(
+    app: tauri::AppHandle,
+    python: tauri::State<'_, Mutex<PythonConfig>>,
+    capabilities: tauri::State<'_, CapabilitiesCache>,
+) -> Result<EngineCapabilities, String> {
+    let script_path = engine_script(&app)?;
+    // Cloned so the lock isn't held while the interpreter is probed
+    let python_config = python
+        .lock()
+        .map_err(|_| "Python config lock poisoned".to_string())?
+        .clone();
+    let interpreter = python_config.resolve().await?;
+    capabilities
+        .get(&python_config, &script_path, &interpreter)
+        .await
+}
+
+#[tauri::command]
+async fn check_engine_dependencies(
+    app: tauri::AppHandle,
+    python: tauri::State<'_, Mutex<PythonConfig>>,
+    deps: tauri::State<'_, DependencyCache>,
+) -> Result<Vec<DependencyStatus>, String> {
+    let script_path = engine_script(&app)?;
+    // Cloned so the lock isn't held while the interpreter is probed
+    let python_config = python
+        .lock()
+        .map_err(|_| "Python config lock poisoned".to_string())?
+        .clone();
+    let interpreter = python_config.resolve().await?;
+    let dependencies = deps
+        .get(&python_config, &script_path, &interpreter)
+        .await?;
+    if let Some(message) = deps::install_message(&dependencies) {
+        tracing::warn!(%interpreter, "{}", message);
+    }
+    Ok(dependencies)
+}
+
+#[tauri::command]
+fn cancel_simulation(active: tauri::State<'_, ActiveChildren>) -> Result<bool, String> {
+    let cancelled = engine::cancel(&active);
+    tracing::info!(killed = cancelled, "Cancel requested");
+    Ok(cancelled)
+}
+
+#[tauri::command]
+fn load_catalog_csv(path: String) -> Result<Vec<CatalogItem>, String> {
+    let items = catalog::load_csv(Path::new(&path))?;
+    tracing::info!(items = items.len(), path, "Loaded catalog CSV");
+    Ok(items)
+}
+
+#[tauri::command]
+fn load_result(path: String) -> Result<SimulationResult, String> {
+    let result = result_file::load(Path::new(&path))?;
+    tracing::info!(
+        path,
+        invoices = result.invoices.len(),
+        warnings = result.warnings.len(),
+        "Loaded saved result"
+    );
+    Ok(result)
+}
+
+#[tauri::command]
+fn check_future_dates(
+    result: SimulationResult,
+    end_date: Option<String>,
+) -> Result<Option<String>, String> {
+    let end_date = end_date
+        .map(|end| validation::parse_date("end_date", &end))
+        .transpose()?;
+    let today = chrono::Local::now().date_naive();
+    Ok(validation::future_dates(&result.invoices, end_date, today))
+}
+
+#[tauri::command]
+fn verify_tax_breakdown(result: SimulationResult) -> Vec<String> {
+    verify::verify_tax_breakdown(&result.invoices)
+}
+
+#[tauri::command]
+fn reconcile_config_with_catalog(
+    config: SimulationConfig,
+    catalog: Vec<CatalogItem>,
+) -> ReconcileReport {
+    catalog::reconcile(&config, &catalog)
+}
+
+#[tauri::command]
+fn validate_catalog(catalog: Vec<CatalogItem>) -> CatalogReport {
+    let report = catalog::validate(&catalog);
+    tracing::info!(
+        items = catalog.len(),
+        errors = report.errors,
+        warnings = report.warnings,
+        "Validated catalog"
+    );
+    report
+}
+
+#[tauri::command]
+fn dedupe_catalog(catalog: Vec<CatalogItem>, strategy: String) -> Result<DedupeReport, String> {
+    let count = catalog.len();
+    let report = catalog::dedupe(catalog, &strategy)?;
+    tracing::info!(
+        items = count,
+        kept = report.catalog.len(),
+        %strategy,
+        "Deduplicated catalog"
+    );
+    Ok(report)
+}
+
+#[tauri::command]
+fn merge_catalogs(
+    catalogs: Vec<Vec<CatalogItem>>,
+    on_conflict: String,
+) -> Result<Vec<CatalogItem>, String> {
+    let count = catalogs.len();
+    let merged = catalog::merge(catalogs, &on_conflict)?;
+    tracing::info!(catalogs = count, items = merged.len(), "Merged catalogs");
+    Ok(merged)
+}
+
+// Sync commands run on the main thread, so exports that scale with the result run here
+// instead and the UI stays responsive while thousands of invoices are written
+async fn off_main_thread<T, F>(export: F) -> Result<T, String>
+where
+    T: Send + 'static,
+    F: FnOnce() -> Result<T, String> + Send + 'static,
+{
+    tauri::async_runtime::spawn_blocking(export)
+        .await
+        .map_err(|e| format!("Export failed: {}", e))?
+}
+
+#[tauri::command]
+async fn export_invoices_xlsx(result: SimulationResult, path: String) -> Result<(), String> {
+    off_main_thread(move || {
+        export::xlsx::write_invoices(&result, Path::new(&path))?;
+        tracing::info!(invoices = result.invoices.len(), path, "Exported XLSX");
+        Ok(())
+    })
+    .await
+}
+
+#[tauri::command]
+async fn export_line_items_csv(result: SimulationResult, path: String) -> Result<(), String> {
+    off_main_thread(move || {
+        export::csv::write_line_items(&result, Path::new(&path))?;
+        tracing::info!(invoices = result.invoices.len(), path, "Exported line items CSV");
+        Ok(())
+    })
+    .await
+}
+
+#[tauri::command]
+async fn export_invoices_tally_xml(
+    result: SimulationResult,
+    company: String,
+    path: String,
+) -> Result<(), String> {
+    off_main_thread(move || {
+        export::tally::write_invoices(&result, &company, Path::new(&path))?;
+        tracing::info!(vouchers = result.invoices.len(), path, "Exported Tally XML");
+        Ok(())
+    })
+    .await
 }
 
-#[derive(Debug, Serialize, Deserialize)]
-struct Invoice {
-    #[serde(rename = "invoice_type")]
-    invoice_type: String,
-    #[serde(rename = "invoice_number")]
+#[tauri::command]
+async fn export_invoices_iif(result: SimulationResult, path: String) -> Result<(), String> {
+    off_main_thread(move || {
+        export::iif::write_invoices(&result, Path::new(&path))?;
+        tracing::info!(transactions = result.invoices.len(), path, "Exported QuickBooks IIF");
+        Ok(())
+    })
+    .await
+}
+
+#[tauri::command]
+fn save_config(config: SimulationConfig, app: tauri::AppHandle) -> Result<(), String> {
+    config_store::save(&config_dir(&app)?, &config)?;
+    tracing::info!("Saved config");
+    Ok(())
+}
+
+#[tauri::command]
+fn load_config_toml(path: String) -> Result<SimulationConfig, String> {
+    let config = config_store::load_toml(Path::new(&path))?;
+    tracing::info!(path, "Loaded TOML config");
+    Ok(config)
+}
+
+#[tauri::command]
+fn load_last_config(app: tauri::AppHandle) -> Result<Option<SimulationConfig>, String> {
+    config_store::load(&config_dir(&app)?)
+}
+
+fn config_dir(app: &tauri::AppHandle) -> Result<PathBuf, String> {
+    app.path()
+        .app_config_dir()
+        .map_err(|e| format!("Failed to resolve config directory: {}", e))
+}
+
+#[tauri::command]
+fn clear_simulation_cache(app: tauri::AppHandle) -> Result<usize, String> {
+    let removed = result_cache::clear(&cache_dir(&app)?)?;
+    tracing::info!(removed, "Cleared simulation cache");
+    Ok(removed)
+}
+
+fn cache_dir(app: &tauri::AppHandle) -> Result<PathBuf, String> {
+    app.path()
+        .app_cache_dir()
+        .map(|dir| dir.join("simulations"))
+        .map_err(|e| format!("Failed to resolve cache directory: {}", e))
+}
+
+#[tauri::command]
+fn export_invoice_pdf(
+    invoice: Invoice,
+    path: String,
+    rounding_mode: Option<String>,
+) -> Result<(), String> {
+    let rounding = RoundingMode::parse(rounding_mode.as_deref())?;
+    export::pdf::write_invoice(&invoice, rounding, Path::new(&path))?;
+    tracing::info!(invoice = %invoice.invoice_number, path, "Exported invoice PDF");
+    Ok(())
+}
+
+#[tauri::command]
+fn build_invoice_qr(
+    invoice: Invoice,
+    payee: PayeeInfo,
+    png_path: Option<String>,
+) -> Result<String, String> {
+    let uri = export::qr::upi_uri(&invoice, &payee)?;
+    if let Some(path) = png_path {
+        export::qr::write_png(&uri, Path::new(&path))?;
+        tracing::info!(invoice = %invoice.invoice_number, path, "Saved payment QR code");
+    }
+    Ok(uri)
+}
+
+#[tauri::command]
+fn export_einvoice_json(
+    invoice: Invoice,
+    seller: SellerInfo,
+    path: String,
+    rounding_mode: Option<String>,
+) -> Result<(), String> {
+    let rounding = RoundingMode::parse(rounding_mode.as_deref())?;
+    export::einvoice::write(&invoice, &seller, rounding, Path::new(&path))?;
+    tracing::info!(invoice = %invoice.invoice_number, path, "Exported e-invoice JSON");
+    Ok(())
+}
+
+#[tauri::command]
+async fn export_summary_report_pdf(result: SimulationResult, path: String) -> Result<(), String> {
+    off_main_thread(move || {
+        export::pdf::write_summary(&result, Path::new(&path))?;
+        tracing::info!(invoices = result.invoices.len(), path, "Exported summary report PDF");
+        Ok(())
+    })
+    .await
+}
+
+#[tauri::command]
+async fn export_all_invoices_pdf(
+    result: SimulationResult,
+    dir: String,
+) -> Result<ExportSummary, String> {
+    off_main_thread(move || {
+        let rounding = RoundingMode::or_default(result.rounding_mode.as_deref());
+        let summary = export::pdf::write_all(&result.invoices, rounding, Path::new(&dir))?;
+        tracing::info!(
+            written = summary.written,
+            failed = summary.failures.len(),
+            dir,
+            "Exported invoice PDFs"
+        );
+        Ok(summary)
+    })
+    .await
+}
+
+#[tauri::command]
+fn reveal_in_folder(path: String) -> Result<(), String> {
+    export::open::reveal(&path)
+}
+
+#[tauri::command]
+async fn open_file(path: String) -> Result<(), String> {
+    // Off the main thread, since on Linux this waits a moment to see if xdg-open fails
+    tauri::async_runtime::spawn_blocking(move || export::open::open(&path))
+        .await
+        .map_err(|e| format!("Failed to open file: {}", e))?
+}
+
+#[tauri::command]
+fn set_log_level(level: String, logging: tauri::State<'_, Logging>) -> Result<(), String> {
+    logging.set_level(&level)?;
+    tracing::info!(level, "Log level changed");
+    Ok(())
+}
+
+#[tauri::command]
+fn enable_file_logging(
+    app: tauri::AppHandle,
+    logging: tauri::State<'_, Logging>,
+) -> Result<String, String> {
+    let dir = app
+        .path()
+        .app_log_dir()
+        .map_err(|e| format!("Failed to resolve log directory: {}", e))?;
+    let path = logging.log_to_file(&dir)?;
+    tracing::info!(path = ?path, "Logging to file");
+    Ok(path.to_string_lossy().into_owned())
+}
+
+#[tauri::command]
+fn get_last_engine_log(app: tauri::AppHandle) -> Result<String, String> {
+    let log_dir = app
+        .path()
+        .app_log_dir()
+        .map_err(|e| format!("Failed to resolve log directory: {}", e))?;
+    engine_log::last_entry(&log_dir)
+}
+
+#[tauri::command]
+async fn run_simulation(
+    config: SimulationConfig,
+    catalog: Vec<CatalogItem>,
+    window: tauri::Window,
+    python: tauri::State<'_, Mutex<PythonConfig>>,
+    active: tauri::State<'_, ActiveChildren>,
+    run_lock: tauri::State<'_, RunLock>,
+) -> Result<SimulationResult, SimulationError> {
+    let _guard = acquire_run(&run_lock)?;
+    let catalog_json = serialize_catalog(&catalog)?;
+    simulate(config, &catalog, &catalog_json, &window, &python, &active).await
+}
+
+#[tauri::command]
+async fn rerun_with_seed(
+    mut config: SimulationConfig,
+    catalog: Vec<CatalogItem>,
+    seed: i32,
+    window: tauri::Window,
+    python: tauri::State<'_, Mutex<PythonConfig>>,
+    active: tauri::State<'_, ActiveChildren>,
+    run_lock: tauri::State<'_, RunLock>,
+) -> Result<SimulationResult, SimulationError> {
+    let _guard = acquire_run(&run_lock)?;
+    config.seed = Some(seed);
+    let catalog_json = serialize_catalog(&catalog)?;
+    simulate(config, &catalog, &catalog_json, &window, &python, &active).await
+}
+
+#[tauri::command]
+#[allow(clippy::too_many_arguments)]
+async fn extend_simulation(
+    previous: SimulationResult,
+    additional_target: f64,
+    mut config: SimulationConfig,
+    catalog: Vec<CatalogItem>,
+    window: tauri::Window,
+    python: tauri::State<'_, Mutex<PythonConfig>>,
+    active: tauri::State<'_, ActiveChildren>,
+    run_lock: tauri::State<'_, RunLock>,
+) -> Result<SimulationResult, SimulationError> {
+    let _guard = acquire_run(&run_lock)?;
+    extend::prepare(&mut config, &previous, additional_target)
+        .and_then(|()| validation::validate_output_formats(&config.output_formats))
+        .map_err(SimulationError::ValidationFailed)?;
+    // Written for the combined result below rather than for the extension alone
+    let formats = std::mem::take(&mut config.output_formats);
+    let output_dir = config.output_dir.clone();
+    tracing::info!(
+        invoices = previous.invoices.len(),
+        additional_target,
+        numbering_from = ?config.invoice_number_start,
+        "Extending simulation"
+    );
+    let catalog_json = serialize_catalog(&catalog)?;
+    let extension = simulate(config, &catalog, &catalog_json, &window, &python, &active).await?;
+    let mut result = extend::append(previous, extension);
+    // A manifest would only describe the extension, which can't be rerun on its own
+    write_outputs(&mut result, &formats, output_dir.as_deref(), None, window.app_handle());
+    Ok(result)
+}
+
+#[tauri::command]
+#[allow(clippy::too_many_arguments)]
+async fn regenerate_invoice(
+    result: SimulationResult,
     invoice_number: String,
-    date: String,
-    customer: serde_json::Value,
-    items: Vec<InvoiceItem>,
-    #[serde(rename = "subtotal")]
-    subtotal: f64,
-    #[serde(rename = "total")]
-    total: f64,
-    // Optional fields
-    #[serde(skip_serializing_if = "Option::is_none")]
-    payment_terms: Option<String>,
-    #[serde(skip_serializing_if = "Option::is_none")]
-    template_applied: Option<String>,
-}
-
-#[derive(Debug, Serialize, Deserialize)]
-struct InvoiceItem {
-    // Handle both "name" and "item" fields
-    #[serde(rename = "name")]
-    #[serde(default)]
-    name: String,
-    #[serde(rename = "item")]
-    #[serde(default)]
-    item: String,
-    // Handle both "quantity" and "qty" fields
-    #[serde(rename = "quantity")]
-    #[serde(default)]
-    quantity: i32,
-    #[serde(rename = "qty")]
-    #[serde(default)]
-    qty: i32,
-    #[serde(rename = "rate")]
-    rate: f64,
-    #[serde(rename = "amount")]
-    amount: f64,
-    // Optional fields for backward compatibility
-    #[serde(skip_serializing_if = "Option::is_none")]
-    sku: Option<String>,
-    #[serde(skip_serializing_if = "Option::is_none")]
-    tax: Option<f64>,
-    #[serde(skip_serializing_if = "Option::is_none")]
-    total: Option<f64>,
-}
-
-#[derive(Debug, Serialize, Deserialize)]
-#[serde(rename_all = "camelCase")]
-struct CatalogItem {
-    sku: String,
-    name: String,
-    price: f64,
-    gst_percent: Option<f64>,
-    vat_percent: Option<f64>,
-    category: Option<String>,
-}
-
-#[tauri::command]
-async fn run_simulation(config: SimulationConfig, catalog: Vec<CatalogItem>) -> Result<SimulationResult, String> {
-    println!("[Rust] Starting simulation with config: {:?}", config);
-    
-    // Validate required fields
-    if config.revenue_target <= 0.0 {
-        return Err("Revenue target must be positive".into());
+    mut config: SimulationConfig,
+    catalog: Vec<CatalogItem>,
+    seed: Option<i32>,
+    window: tauri::Window,
+    python: tauri::State<'_, Mutex<PythonConfig>>,
+    active: tauri::State<'_, ActiveChildren>,
+    run_lock: tauri::State<'_, RunLock>,
+) -> Result<SimulationResult, SimulationError> {
+    let _guard = acquire_run(&run_lock)?;
+    let seed = seed.unwrap_or_else(random_seed);
+    let index = regenerate::prepare(&mut config, &result, &invoice_number, seed)
+        .map_err(SimulationError::ValidationFailed)?;
+    tracing::info!(invoice = %invoice_number, seed, "Regenerating invoice");
+    let catalog_json = serialize_catalog(&catalog)?;
+    let fresh = simulate(config, &catalog, &catalog_json, &window, &python, &active).await?;
+    regenerate::replace(result, index, fresh).map_err(SimulationError::ParseError)
+}
+
+#[tauri::command]
+#[allow(clippy::too_many_arguments)]
+async fn run_batch(
+    mut configs: Vec<SimulationConfig>,
+    catalog: Vec<CatalogItem>,
+    max_parallel: Option<usize>,
+    master_seed: Option<u64>,
+    window: tauri::Window,
+    python: tauri::State<'_, Mutex<PythonConfig>>,
+    active: tauri::State<'_, ActiveChildren>,
+    run_lock: tauri::State<'_, RunLock>,
+) -> Result<tauri::ipc::Response, String> {
+    // Held for the whole batch, whose own runs share it
+    let _guard = acquire_run(&run_lock).map_err(|e| e.to_string())?;
+    // Seeded up front so a failed run still reports the seed it was given, and so a
+    // resumed batch runs each config with the seed it would have had
+    let master_seed = batch::derive_seeds(&mut configs, master_seed);
+    let checkpoint = BatchCheckpoint {
+        master_seed,
+        configs,
+        catalog,
+        completed: Vec::new(),
+    };
+    let checkpoint_path = window
+        .path()
+        .app_data_dir()
+        .map_err(|e| format!("Failed to resolve data directory: {}", e))
+        .and_then(|dir| batch::create_checkpoint(&dir, &checkpoint))?;
+    tracing::info!(
+        configs = checkpoint.configs.len(),
+        master_seed,
+        checkpoint = ?checkpoint_path,
+        "Running batch"
+    );
+    drive_batch(&checkpoint_path, checkpoint, max_parallel, &window, &python, &active)
+}
+
+#[tauri::command]
+async fn resume_batch(
+    checkpoint_path: String,
+    max_parallel: Option<usize>,
+    window: tauri::Window,
+    python: tauri::State<'_, Mutex<PythonConfig>>,
+    active: tauri::State<'_, ActiveChildren>,
+    run_lock: tauri::State<'_, RunLock>,
+) -> Result<tauri::ipc::Response, String> {
+    let _guard = acquire_run(&run_lock).map_err(|e| e.to_string())?;
+    let path = PathBuf::from(checkpoint_path);
+    let checkpoint = batch::load_checkpoint(&path)?;
+    tracing::info!(
+        configs = checkpoint.configs.len(),
+        completed = checkpoint.completed.len(),
+        checkpoint = ?path,
+        "Resuming batch"
+    );
+    drive_batch(&path, checkpoint, max_parallel, &window, &python, &active)
+}
+
+// Runs whatever the checkpoint hasn't finished, emitting `batch-progress` as runs complete
+fn drive_batch(
+    checkpoint_path: &Path,
+    checkpoint: BatchCheckpoint,
+    max_parallel: Option<usize>,
+    window: &tauri::Window,
+    python: &Mutex<PythonConfig>,
+    active: &ActiveChildren,
+) -> Result<tauri::ipc::Response, String> {
+    // Cloned since the checkpoint itself goes to the batch, which keeps it up to date
+    let catalog = checkpoint.catalog.clone();
+    let catalog_json = serialize_catalog(&catalog).map_err(|e| e.to_string())?;
+    let total = checkpoint.configs.len();
+    let progress = |index: usize, result: &SimulationResult| {
+        let event = BatchProgress {
+            index,
+            total,
+            succeeded: result.error.is_none(),
+        };
+        // Windows can be emitted to from any thread
+        if let Err(e) = window.emit("batch-progress", event) {
+            tracing::warn!(error = %e, "Failed to emit batch progress event");
+        }
+    };
+    // The workers are plain threads that each drive one run at a time on the runtime;
+    // this thread hands its other tasks off while it waits for them
+    let batch = tokio::task::block_in_place(|| {
+        batch::run_checkpointed(
+            checkpoint_path,
+            checkpoint,
+            max_parallel,
+            |config| {
+                let seed = config.seed.unwrap_or_default();
+                let run = simulate(config, &catalog, &catalog_json, window, python, active);
+                tauri::async_runtime::block_on(run)
+                    .unwrap_or_else(|e| SimulationResult::failed(seed, e.to_string()))
+            },
+            progress,
+        )
+    });
+    tracing::info!(
+        succeeded = batch.succeeded,
+        failed = batch.failed,
+        checkpoint = ?batch.checkpoint_path,
+        "Batch finished"
+    );
+    // Serialized here, one saved result at a time, while the checkpoint folder they're
+    // read from is still there
+    let json = serde_json::to_string(&batch)
+        .map_err(|e| format!("Failed to serialize batch result: {}", e))?;
+    if batch.failed == 0 {
+        batch::remove_checkpoint(checkpoint_path);
     }
-    if config.min_invoice_amount <= 0.0 || config.max_invoice_amount <= 0.0 {
-        return Err("Invoice amounts must be positive".into());
+    Ok(tauri::ipc::Response::new(json))
+}
+
+#[tauri::command]
+async fn run_smoke_test(
+    window: tauri::Window,
+    python: tauri::State<'_, Mutex<PythonConfig>>,
+    active: tauri::State<'_, ActiveChildren>,
+    run_lock: tauri::State<'_, RunLock>,
+) -> Result<SmokeTestReport, String> {
+    let _guard = acquire_run(&run_lock).map_err(|e| e.to_string())?;
+    let catalog = smoke::catalog();
+    let catalog_json = serialize_catalog(&catalog).map_err(|e| e.to_string())?;
+    tracing::info!("Running smoke test");
+    let started = Instant::now();
+    let run = simulate(smoke::config(), &catalog, &catalog_json, &window, &python, &active).await;
+    let report = smoke::check(run, started.elapsed());
+    match &report {
+        Ok(report) => tracing::info!(?report, "Smoke test passed"),
+        Err(e) => tracing::error!(error = %e, "Smoke test failed"),
     }
-    if config.min_items <= 0 || config.max_items <= 0 {
-        return Err("Item counts must be positive".into());
+    report
+}
+
+// Every command that runs the engine takes this first: two runs at once would each be
+// cancelled along with the other, since cancelling kills every engine process
+fn acquire_run(run_lock: &RunLock) -> Result<RunGuard<'_>, SimulationError> {
+    run_lock.try_acquire().ok_or_else(|| {
+        tracing::warn!("Rejected a simulation while another was running");
+        SimulationError::Busy
+    })
+}
+
+fn serialize_catalog(catalog: &[CatalogItem]) -> Result<String, SimulationError> {
+    tracing::debug!(items = catalog.len(), "Serializing catalog");
+    serde_json::to_string(catalog).map_err(|e| {
+        tracing::error!(error = %e, "Catalog serialization failed");
+        SimulationError::Internal(format!("Failed to serialize catalog: {}", e))
+    })
+}
+
+// Non-negative so it survives the round trip through the engine's `Option<i32>` seed
+fn random_seed() -> i32 {
+    (rand::random::<u32>() >> 1) as i32
+}
+
+// Bundled resource first, then $LEDGERFLOW_ENGINE, then the development checkout
+fn engine_script(app: &tauri::AppHandle) -> Result<PathBuf, String> {
+    let resource = app
+        .path()
+        .resolve(engine_path::RESOURCE_PATH, BaseDirectory::Resource)
+        .ok();
+    engine_path::resolve(resource, std::env::var_os(engine_path::ENGINE_ENV))
+}
+
+async fn simulate(
+    mut config: SimulationConfig,
+    catalog: &[CatalogItem],
+    catalog_json: &str,
+    window: &tauri::Window,
+    python: &Mutex<PythonConfig>,
+    active: &ActiveChildren,
+) -> Result<SimulationResult, SimulationError> {
+    // Always hand the engine an explicit seed so every run can be reproduced
+    let seed = *config.seed.get_or_insert_with(random_seed);
+    // The config as given, for the manifest written with the output files
+    let manifest_config = (!config.output_formats.is_empty()).then(|| config.clone());
+    config
+        .reality_buffer
+        .get_or_insert(validation::DEFAULT_REALITY_BUFFER);
+    // Everything logged below, including from batch workers, is tagged with the run
+    let span = tracing::info_span!("simulation", seed, revenue_target = config.revenue_target);
+    let (formats, output_dir) = (config.output_formats.clone(), config.output_dir.clone());
+    let mut result = simulate_seeded(config, seed, catalog, catalog_json, window, python, active)
+        .instrument(span.clone())
+        .await?;
+    // After the cache, so a cached run still writes its files
+    span.in_scope(|| {
+        let inputs = manifest_config.as_ref().map(|config| (config, catalog));
+        write_outputs(&mut result, &formats, output_dir.as_deref(), inputs, window.app_handle())
+    });
+    Ok(result)
+}
+
+// Writes the files a config's `output_formats` asked for and records their paths, plus
+// a manifest of the run when `inputs` has the config and catalog it came from. One that
+// can't be written is a warning, since the run itself succeeded.
+fn write_outputs(
+    result: &mut SimulationResult,
+    formats: &[String],
+    output_dir: Option<&str>,
+    inputs: Option<(&SimulationConfig, &[CatalogItem])>,
+    app: &tauri::AppHandle,
+) {
+    if formats.is_empty() {
+        return;
     }
-    if config.min_invoice_amount > config.max_invoice_amount {
-        return Err("Minimum invoice amount cannot exceed maximum".into());
+    let dir = match output_dir {
+        Some(dir) => PathBuf::from(dir),
+        None => match app.path().app_data_dir() {
+            Ok(dir) => dir.join("exports"),
+            Err(e) => {
+                result
+                    .warnings
+                    .push(format!("Failed to resolve exports directory: {}", e));
+                return;
+            }
+        },
+    };
+    // Timestamped so an extension or a rerun doesn't overwrite the files of the run before
+    let stem = format!(
+        "ledgerflow_{}_seed{}",
+        chrono::Local::now().format("%Y%m%d_%H%M%S"),
+        result.seed_used
+    );
+    let (mut written, mut failures) = export::formats::write_formats(result, formats, &dir, &stem);
+    if let Some((config, catalog)) = inputs.filter(|_| !written.is_empty()) {
+        let path = dir.join(format!("{}.manifest.json", stem));
+        match manifest::build(config, catalog, result).and_then(|m| manifest::write(&m, &path)) {
+            Ok(()) => written.push(path),
+            Err(e) => failures.push(e),
+        }
     }
-    if config.min_items > config.max_items {
-        return Err("Minimum items cannot exceed maximum".into());
+    for failure in &failures {
+        tracing::warn!(%failure, "Output format not written");
     }
+    tracing::info!(files = written.len(), dir = ?dir, "Wrote output formats");
+    result.warnings.extend(failures);
+    result.output_paths = written
+        .iter()
+        .map(|path| path.to_string_lossy().into_owned())
+        .collect();
+}
 
-    println!("[Rust] Serializing config...");
-    let config_json = serde_json::to_string(&config)
+async fn simulate_seeded(
+    mut config: SimulationConfig,
+    seed: i32,
+    catalog: &[CatalogItem],
+    catalog_json: &str,
+    window: &tauri::Window,
+    python: &Mutex<PythonConfig>,
+    active: &ActiveChildren,
+) -> Result<SimulationResult, SimulationError> {
+    tracing::info!(?config, "Starting simulation");
+
+    // Validate required fields
+    let validation_warnings = validation::validate(&config)
+        .and_then(|()| validation::validate_selection(&config, catalog))
+        .and_then(|()| validation::validate_catalog_taxes(&config.invoice_type, catalog))
+        .and_then(|()| validation::fit_max_items(&mut config, catalog))
+        .and_then(|clamped| {
+            let large = validation::check_run_size(&config)?;
+            Ok(clamped.into_iter().chain(large).collect::<Vec<_>>())
+        })
         .map_err(|e| {
-            println!("[Rust] Config serialization failed: {}", e);
-            format!("Failed to serialize config: {}", e)
+            tracing::warn!(error = %e, "Config failed validation");
+            SimulationError::ValidationFailed(e)
         })?;
-    
-    println!("[Rust] Serializing catalog...");
-    let catalog_json = serde_json::to_string(&catalog)
+    tracing::debug!("Config passed validation");
+
+    // The engine sums prices as they are, so any in another currency are converted first,
+    // and the amount range is checked against what it will actually be given
+    let converted = currency::base_catalog(&config, catalog)
+        .and_then(|converted| {
+            let prices = converted.as_deref().unwrap_or(catalog);
+            validation::check_amount_range(&config, prices)?;
+            Ok(converted)
+        })
         .map_err(|e| {
-            println!("[Rust] Catalog serialization failed: {}", e);
-            format!("Failed to serialize catalog: {}", e)
+            tracing::warn!(error = %e, "Catalog doesn't fit the config");
+            SimulationError::ValidationFailed(e)
         })?;
+    let converted_json;
+    let catalog_json = match converted {
+        Some(converted) => {
+            converted_json = serialize_catalog(&converted)?;
+            converted_json.as_str()
+        }
+        None => catalog_json,
+    };
 
-    let script_path = Path::new("../backend/src/engine.py");
-    println!("[Rust] Checking Python script at: {:?}", script_path);
-    
-    if !script_path.exists() {
-        println!("[Rust] Python script not found!");
-        return Err(format!("Python engine not found at: {:?}", script_path));
-    }
-
-    println!("[Rust] Executing Python engine...");
-    let output = Command::new("python3")
-        .arg(script_path)
-        .arg("--config_json")
-        .arg(&config_json)
-        .arg("--catalog_json")
-        .arg(&catalog_json)
-        .output()
+    let config_json = serde_json::to_string(&config)
         .map_err(|e| {
-            println!("[Rust] Python execution failed: {}", e);
-            format!("Failed to execute Python engine: {}", e)
+            tracing::error!(error = %e, "Config serialization failed");
+            SimulationError::Internal(format!("Failed to serialize config: {}", e))
         })?;
 
+    let script_path = engine_script(window.app_handle()).map_err(|e| {
+        tracing::error!(error = %e, "Engine script not found");
+        SimulationError::EngineNotFound(e)
+    })?;
+    tracing::debug!(script = ?script_path, "Using engine script");
+
+    // Identical runs are answered from disk without starting Python. Runs that keep their
+    // raw output, or write it to an output_path, always go to the engine, since the copy
+    // has to come from a real run.
+    let cache = if config.save_raw_output.unwrap_or(false)
+        || config.output_path.is_some()
+        || config.skip_cache
+    {
+        None
+    } else {
+        let cache = cache_dir(window.app_handle()).and_then(|dir| {
+            let engine = std::fs::read(&script_path)
+                .map_err(|e| format!("Failed to read engine script: {}", e))?;
+            Ok((dir, result_cache::key(&config_json, catalog_json, seed, &engine)))
+        });
+        cache
+            .map_err(|e| tracing::warn!(error = %e, "Result cache unavailable"))
+            .ok()
+    };
+    if let Some((dir, key)) = &cache {
+        if let Some(mut result) = result_cache::load(dir, key) {
+            tracing::info!(key = %key, "Returning cached result");
+            // The stored timing belongs to the run that filled the cache; this one spent
+            // no time in the engine
+            if let Some(stats) = result.stats.as_mut() {
+                stats.duration_secs = 0.0;
+            }
+            result.from_cache = true;
+            return Ok(result);
+        }
+    }
+
+    let python_config = python
+        .lock()
+        .map_err(|_| SimulationError::Internal("Python config lock poisoned".to_string()))?
+        .clone();
+    let interpreter = python_config
+        .resolve()
+        .await
+        .map_err(SimulationError::EngineNotFound)?;
+
+    // The engine writes its result to a file rather than to stdout, which can be hundreds
+    // of MB for large revenue targets: the output_path when there is one, which is left in
+    // place, and otherwise a temp file unique per run so batch workers don't collide.
+    // The temp file is named .gz so engines that support it compress the result.
+    let temp_output;
+    let output_file = match config.output_path.as_deref() {
+        Some(path) => {
+            let path = Path::new(path);
+            engine::check_output_path(path).map_err(|e| {
+                tracing::warn!(error = %e, "Output path not usable");
+                SimulationError::ValidationFailed(e)
+            })?;
+            path
+        }
+        None => {
+            temp_output = TempFile::unique("ledgerflow_output", "json.gz");
+            temp_output.path()
+        }
+    };
+
+    tracing::info!(%interpreter, "Spawning engine");
+    let mut command = interpreter.command();
+    command
+        .current_dir(python_config.working_dir(&script_path))
+        .arg(&script_path)
+        .arg("--stdin")
+        .arg("--output")
+        .arg(output_file)
+        .args(config.extra_engine_args.iter().flatten());
+    // Large catalogs overflow argv, so both documents go over stdin, one per line.
+    // Compact JSON never contains a raw newline.
+    let input = format!("{}\n{}\n", config_json, catalog_json).into_bytes();
+    let log_dir = window.path().app_log_dir();
+    let max_output_bytes = config
+        .max_output_bytes
+        .unwrap_or(engine::DEFAULT_MAX_OUTPUT_BYTES);
+    let options = RunOptions {
+        input: Some(input),
+        timeout: config.timeout_secs.map(Duration::from_secs),
+        max_retries: config.max_retries.unwrap_or(0),
+        max_output_bytes: Some(max_output_bytes),
+        output_file: Some(output_file.to_path_buf()),
+    };
+    let progress_window = window.clone();
+    let started = Instant::now();
+    let output = engine::run_engine(command, options, active, move |percent| {
+        if let Err(e) = progress_window.emit("simulation-progress", SimulationProgress { percent }) {
+            tracing::warn!(error = %e, "Failed to emit progress event");
+        }
+    })
+    .await
+    .map_err(|e| {
+        tracing::error!(kind = e.kind(), error = %e, "Engine run failed");
+        e
+    })?;
+    let duration = started.elapsed();
+
+    // Logging is best effort and never fails the run
+    match log_dir {
+        Ok(dir) => {
+            if let Err(e) = engine_log::append(&dir, config.seed, &output.stdout, &output.stderr) {
+                tracing::warn!(error = %e, "Failed to write engine log");
+            }
+        }
+        Err(e) => tracing::warn!(error = %e, "Failed to resolve log directory"),
+    }
+
     if !output.status.success() {
         let stderr = String::from_utf8_lossy(&output.stderr);
-        println!("[Rust] Python engine error: {}", stderr);
-        return Err(format!("Python engine error: {}", stderr));
+        tracing::error!(code = ?output.status.code(), %stderr, "Engine exited with an error");
+        return Err(SimulationError::EngineFailed {
+            code: output.status.code(),
+            stderr: stderr.into_owned(),
+        });
     }
+    let engine_warnings = engine::stderr_warnings(&output.stderr);
 
-    let stdout = String::from_utf8_lossy(&output.stdout);
-    println!("[Rust] Raw Python output: {}", stdout);
-    
-    let result: SimulationResult = serde_json::from_str(&stdout)
-        .map_err(|e| {
-            println!("[Rust] Failed to parse output: {}", e);
-            format!("Failed to parse engine output: {}. Raw: {}", e, stdout)
-        })?;
+    // Copied before anything here reads or changes it, so it's exactly what the engine wrote.
+    // A failed copy is reported but doesn't fail the run.
+    let audit = if config.save_raw_output.unwrap_or(false) {
+        let saved = window
+            .path()
+            .app_data_dir()
+            .map_err(|e| format!("Failed to resolve data directory: {}", e))
+            .and_then(|dir| audit::save(&dir, output_file, seed));
+        Some(saved)
+    } else {
+        None
+    };
+
+    let mut result = engine_output::read(output_file, max_output_bytes).map_err(|e| {
+        tracing::error!(error = %e, "Failed to read engine output");
+        SimulationError::ParseError(e)
+    })?;
+
+    if let Some(err) = verify::engine_error(&result.status, result.error.as_deref()) {
+        tracing::error!(error = %err, status = %result.status, "Engine reported an error");
+        // Reported by the engine itself, which still exited cleanly
+        return Err(SimulationError::EngineFailed {
+            code: Some(0),
+            stderr: err,
+        });
+    }
 
-    if let Some(err) = result.error {
-        println!("[Rust] Simulation returned error: {}", err);
-        return Err(err);
+    result.seed_used = seed;
+    result.currency = config.currency.clone().or(config.base_currency.clone());
+    result.rounding_mode = config.rounding_mode.clone();
+    // Scanned on the engine's own numbers, which renumbering would make unique
+    let duplicates = verify::duplicate_invoice_numbers(&result.invoices);
+    // Before the checks below so their warnings use the final numbers
+    if let Some(prefix) = &config.invoice_number_prefix {
+        let start = config.invoice_number_start.unwrap_or(1);
+        extend::renumber(&mut result.invoices, prefix, start);
+    }
+    result.warnings = engine_warnings;
+    result.warnings.extend(validation_warnings);
+    match audit {
+        Some(Ok(path)) => {
+            tracing::info!(path = ?path, "Saved raw engine output");
+            result.audit_path = Some(path.to_string_lossy().into_owned());
+        }
+        Some(Err(e)) => result.warnings.push(format!("Raw output not saved: {}", e)),
+        None => {}
+    }
+    // Everything from here to the stats is a problem with the output, which strict fails on
+    let checked_from = result.warnings.len();
+    if let Some(duplicates) = duplicates {
+        if !config.allow_duplicate_invoice_numbers {
+            tracing::error!(error = %duplicates, "Engine output has duplicate invoice numbers");
+            return Err(SimulationError::ParseError(duplicates));
+        }
+        result.warnings.push(duplicates);
+    }
+    let end_date = validation::parse_date("end_date", &config.end_date).ok();
+    let today = chrono::Local::now().date_naive();
+    if let Some(future) = validation::future_dates(&result.invoices, end_date, today) {
+        if config.forbid_future_dates {
+            tracing::error!(error = %future, "Engine output has future-dated invoices");
+            return Err(SimulationError::ValidationFailed(future));
+        }
+        result.warnings.push(future);
+    }
+    result.warnings.extend(verify::field_conflicts(&result.invoices));
+    // Before verify_totals so it checks the corrected amounts
+    let rounding = RoundingMode::or_default(config.rounding_mode.as_deref());
+    let drift = verify::rounding_drift(&mut result.invoices, config.fix_rounding, rounding);
+    result.warnings.extend(drift);
+    result.warnings.extend(verify::verify_totals(&result.invoices));
+    result.warnings.extend(verify::verify_tax_breakdown(&result.invoices));
+    result.warnings.extend(verify::catalog_tax(&result.invoices, catalog));
+    result.warnings.extend(verify::item_count_bounds(
+        &result.invoices,
+        config.min_items,
+        config.max_items,
+    ));
+    if config.strict {
+        verify::strict(&result.warnings[checked_from..]).map_err(|e| {
+            tracing::error!(error = %e, "Strict mode rejected the engine output");
+            SimulationError::ParseError(e)
+        })?;
+    }
+    let mut stats = stats::compute(&result.invoices, config.revenue_target, duration);
+    let tolerance = config
+        .reality_buffer
+        .unwrap_or(validation::DEFAULT_REALITY_BUFFER);
+    result.warnings.extend(stats::revenue_warning(&stats, tolerance));
+    stats.convergence = stats::convergence(&stats, tolerance);
+    result.stats = Some(stats);
+    for warning in &result.warnings {
+        tracing::warn!(%warning, "Simulation warning");
+    }
+    if let Some((dir, key)) = &cache {
+        if let Err(e) = result_cache::store(dir, key, &result) {
+            tracing::warn!(error = %e, "Failed to cache result");
+        }
     }
 
-    println!("[Rust] Simulation completed successfully");
+    tracing::info!(
+        invoices = result.invoices.len(),
+        duration_secs = duration.as_secs_f64(),
+        "Simulation completed successfully"
+    );
     Ok(result)
 }
 
 fn main() {
+    let logging = logging::init();
     tauri::Builder::default()
         .plugin(tauri_plugin_opener::init())
-        .invoke_handler(tauri::generate_handler![run_simulation])
+        .manage(logging)
+        .manage(Mutex::new(PythonConfig::default()))
+        .manage(ActiveChildren::default())
+        .manage(RunLock::default())
+        .manage(CapabilitiesCache::default())
+        .manage(DependencyCache::default())
+        .invoke_handler(tauri::generate_handler![
+            run_simulation,
+            rerun_with_seed,
+            extend_simulation,
+            regenerate_invoice,
+            run_batch,
+            resume_batch,
+            run_smoke_test,
+            validate_config,
+            preview_distribution,
+            estimate_invoice_count,
+            diff_results,
+            aggregate_by_customer,
+            revenue_by_month,
+            tax_summary,
+            revenue_convergence,
+            get_name_types,
+            item_count_distribution,
+            verify_tax_breakdown,
+            check_future_dates,
+            load_result,
+            sample_invoices,
+            filter_invoices_by_date,
+            split_result_by_month,
+            build_manifest,
+            verify_manifest,
+            anonymize_result,
+            format_amount,
+            set_python_path,
+            set_engine_env,
+            set_engine_working_dir,
+            get_engine_capabilities,
+            check_engine_dependencies,
+            check_engine_health,
+            cancel_simulation,
+            get_last_engine_log,
+            set_log_level,
+            enable_file_logging,
+            save_config,
+            load_last_config,
+            load_config_toml,
+            clear_simulation_cache,
+            load_catalog_csv,
+            validate_catalog,
+            reconcile_config_with_catalog,
+            merge_catalogs,
+            dedupe_catalog,
+            export_invoices_xlsx,
+            export_invoices_tally_xml,
+            export_invoices_iif,
+            export_line_items_csv,
+            export_invoice_pdf,
+            export_summary_report_pdf,
+            export_einvoice_json,
+            build_invoice_qr,
+            export_all_invoices_pdf,
+            reveal_in_folder,
+            open_file
+        ])
         .run(tauri::generate_context!())
         .expect("error while running tauri application");
 } 
\ No newline at end of file
@@ -0,0 +1,132 @@
+use crate::models::{CatalogItem, Manifest, ManifestCheck, SimulationConfig, SimulationResult};
+use serde::Serialize;
+use std::fs::File;
+use std::io::BufWriter;
+use std::path::Path;
+
+/// Records what a run needs to be reproduced: the seed it ran with, blake3 hashes of its
+/// config and catalog, and the engine and app versions.
+pub fn build(
+    config: &SimulationConfig,
+    catalog: &[CatalogItem],
+    result: &SimulationResult,
+) -> Result<Manifest, String> {
+    Ok(Manifest {
+        seed: result.seed_used,
+        config_hash: config_hash(config, result.seed_used)?,
+        catalog_hash: hash(&catalog)?,
+        engine_version: result.engine_version.clone(),
+        app_version: env!("CARGO_PKG_VERSION").to_string(),
+        created_at: chrono::Utc::now().to_rfc3339(),
+    })
+}
+
+/// Re-hashes `config` and `catalog` and lists every way they differ from the run
+/// `manifest` describes. A config without a seed is hashed with the manifest's, since
+/// that's the seed it would be rerun with.
+pub fn verify(
+    manifest: &Manifest,
+    config: &SimulationConfig,
+    catalog: &[CatalogItem],
+) -> Result<ManifestCheck, String> {
+    let mut mismatches = Vec::new();
+    if let Some(seed) = config.seed.filter(|seed| *seed != manifest.seed) {
+        mismatches.push(format!(
+            "seed {} differs from the manifest's {}",
+            seed, manifest.seed
+        ));
+    } else if config_hash(config, manifest.seed)? != manifest.config_hash {
+        mismatches.push("config differs from the one the manifest was built from".to_string());
+    }
+    if hash(&catalog)? != manifest.catalog_hash {
+        mismatches.push("catalog differs from the one the manifest was built from".to_string());
+    }
+    Ok(ManifestCheck {
+        matches: mismatches.is_empty(),
+        mismatches,
+    })
+}
+
+/// Writes `manifest` as pretty-printed JSON.
+pub fn write(manifest: &Manifest, path: &Path) -> Result<(), String> {
+    let file = File::create(path).map_err(|e| format!("Failed to create manifest: {}", e))?;
+    serde_json::to_writer_pretty(BufWriter::new(file), manifest)
+        .map_err(|e| format!("Failed to write manifest: {}", e))
+}
+
+// Hashed with the seed filled in, so a config that left it to the app matches its rerun
+fn config_hash(config: &SimulationConfig, seed: i32) -> Result<String, String> {
+    let mut value =
+        serde_json::to_value(config).map_err(|e| format!("Failed to serialize config: {}", e))?;
+    value["seed"] = seed.into();
+    hash(&value)
+}
+
+// Of the compact JSON, the same bytes the engine is given
+fn hash<T: Serialize + ?Sized>(value: &T) -> Result<String, String> {
+    let json = serde_json::to_string(value).map_err(|e| format!("Failed to serialize: {}", e))?;
+    Ok(blake3::hash(json.as_bytes()).to_hex().to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config() -> SimulationConfig {
+        serde_json::from_str(include_str!("../../backend/test/test_config.json")).unwrap()
+    }
+
+    fn catalog() -> Vec<CatalogItem> {
+        serde_json::from_str(include_str!("../../backend/test/test_catalog.json")).unwrap()
+    }
+
+    // As if run with the config's seed
+    fn result() -> SimulationResult {
+        let mut result: SimulationResult =
+            serde_json::from_str(include_str!("../../backend/test/test_output.json")).unwrap();
+        result.seed_used = 12345;
+        result
+    }
+
+    #[test]
+    fn an_altered_catalog_fails_verification() {
+        let manifest = build(&config(), &catalog(), &result()).unwrap();
+        let check = verify(&manifest, &config(), &catalog()).unwrap();
+        assert!(check.matches, "{:?}", check.mismatches);
+
+        let mut altered = catalog();
+        altered[0].price += 1.0;
+        let check = verify(&manifest, &config(), &altered).unwrap();
+        assert!(!check.matches);
+        assert_eq!(
+            check.mismatches,
+            ["catalog differs from the one the manifest was built from"]
+        );
+    }
+
+    #[test]
+    fn an_unseeded_config_is_checked_with_the_manifests_seed() {
+        let mut unseeded = config();
+        unseeded.seed = None;
+        let manifest = build(&unseeded, &catalog(), &result()).unwrap();
+        assert!(verify(&manifest, &unseeded, &catalog()).unwrap().matches);
+
+        let mut other = config();
+        other.seed = Some(manifest.seed + 1);
+        other.revenue_target += 1.0;
+        // A different seed is reported on its own, since the hash can't match either
+        assert_eq!(
+            verify(&manifest, &other, &catalog()).unwrap().mismatches,
+            [format!(
+                "seed {} differs from the manifest's {}",
+                manifest.seed + 1,
+                manifest.seed
+            )]
+        );
+        other.seed = None;
+        assert_eq!(
+            verify(&manifest, &other, &catalog()).unwrap().mismatches,
+            ["config differs from the one the manifest was built from"]
+        );
+    }
+}
@@ -0,0 +1,752 @@
+// Types shared by every LedgerFlow build. The engine has emitted two invoice shapes over
+// time (`invoice_number`/`customer` and `invoice_id`/`customer_name`/`grand_total`), so
+// the structs below accept both through serde aliases.
+
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs::File;
+use std::io::BufReader;
+use std::path::{Path, PathBuf};
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SimulationConfig {
+    pub revenue_target: f64,
+    pub start_date: String,
+    pub end_date: String,
+    pub invoice_type: String,
+    pub min_items: i32,
+    pub max_items: i32,
+    pub min_invoice_amount: f64,
+    pub max_invoice_amount: f64,
+    pub item_filter_mode: String,
+    pub selected_items: Vec<String>,
+    pub name_type: String,
+    pub realism_mode: String,
+    pub seed: Option<i32>,
+    pub invoice_count_mode: Option<String>,
+    pub manual_invoice_count: Option<i32>,
+    pub reality_buffer: Option<f64>,       // Fraction 0.0-1.0, see DEFAULT_REALITY_BUFFER
+    pub distribution_mode: Option<String>, // Added for invoice distribution
+    pub customer_repeat_rate: Option<f64>, // Fraction 0.0-1.0 of invoices to returning customers
+    pub timeout_secs: Option<u64>,         // Kill the engine after this many seconds
+    pub max_retries: Option<u32>,          // Retries if the engine fails to start, default 0
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_output_bytes: Option<u64>, // Stop the engine past this, see DEFAULT_MAX_OUTPUT_BYTES
+    pub currency: Option<String>,          // ISO 4217 code the amounts are in, e.g. "INR"
+    // Catalog prices in other currencies are converted to this before the run; `currency`
+    // when unset. Rates are units of the base currency per unit of each other currency.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub base_currency: Option<String>,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub exchange_rates: BTreeMap<String, f64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub rounding_mode: Option<String>, // One of ROUNDING_MODES, "half_up" when unset
+    #[serde(default)]
+    pub fix_rounding: bool, // Absorb line rounding drift into the largest line
+    #[serde(default)]
+    pub clamp_max_items: bool, // Lower max_items to the eligible item count instead of failing
+    #[serde(default)]
+    pub confirm_large_run: bool, // Allow runs over LARGE_RUN_INVOICES invoices
+    #[serde(default)]
+    pub allow_duplicate_invoice_numbers: bool, // Warn about repeated numbers instead of failing
+    #[serde(default)]
+    pub strict: bool, // Fail with every problem found in the output instead of warning
+    #[serde(default)]
+    pub forbid_future_dates: bool, // Fail on invoices dated after today or end_date, not warn
+    #[serde(default)]
+    pub keep_temp_on_error: bool, // Temp-file build: leave a failed run's files for inspection
+    #[serde(default)]
+    pub skip_cache: bool, // Always run the engine, even when an identical run is cached
+    // The first invoice's running number. `extend_simulation` sets it to continue an
+    // earlier run; with a prefix it's where the Rust-side renumbering starts.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub invoice_number_start: Option<u64>,
+    // When set, invoices are renumbered in date order as prefix + zero-padded number
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub invoice_number_prefix: Option<String>,
+    // Set by `extend_simulation` so repeat customers carry over from the earlier run
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub existing_customers: Vec<String>,
+    pub save_raw_output: Option<bool>, // Keep a copy of the engine's output under audit/
+    // Formats from `OUTPUT_FORMATS` to write once the run succeeds, into `output_dir`
+    // (the app's exports folder when unset)
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub output_formats: Vec<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub output_dir: Option<String>,
+    // Where the engine writes its result JSON, kept after the run, instead of a temp file
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub output_path: Option<String>,
+    // Passed to the engine after the app's own arguments; see `engine::RESERVED_ARGS`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub extra_engine_args: Option<Vec<String>>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SimulationResult {
+    pub invoices: Vec<Invoice>,
+    pub status: String,
+    pub error: Option<String>,
+    // The seed the engine actually ran with, generated in Rust when the config had none
+    #[serde(default)]
+    pub seed_used: i32,
+    // Non-fatal problems found after parsing, e.g. totals that don't add up
+    #[serde(default)]
+    pub warnings: Vec<String>,
+    // Copied from the config so the UI knows how to display the amounts
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub currency: Option<String>,
+    // Likewise, so exports round the amounts the way the run did
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub rounding_mode: Option<String>,
+    // Summary of a successful run, computed in Rust after parsing
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub stats: Option<SimulationStats>,
+    // Where the untouched engine output was saved when `save_raw_output` was set
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub audit_path: Option<String>,
+    // The files written for the config's `output_formats`
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub output_paths: Vec<String>,
+    // Reported by engines from 1.1.0 on
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub engine_version: Option<String>,
+    // Set when the result came out of the result cache instead of a fresh engine run
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub from_cache: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SimulationStats {
+    pub invoice_count: usize,
+    pub total_revenue: f64,
+    pub revenue_target: f64,
+    // total_revenue as a percentage of revenue_target, 100.0 when it was hit exactly
+    #[serde(default)]
+    pub revenue_achieved_percent: f64,
+    pub min_invoice_amount: f64,
+    pub max_invoice_amount: f64,
+    pub mean_invoice_amount: f64,
+    pub distinct_customers: usize,
+    // Fraction of invoices whose customer also appears on an earlier invoice, to compare
+    // against the config's customer_repeat_rate
+    #[serde(default)]
+    pub repeat_customer_rate: f64,
+    // Wall-clock time spent in the engine
+    pub duration_secs: f64,
+    // None when there was no revenue target to converge on
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub convergence: Option<RevenueConvergence>,
+}
+
+// How close a run's revenue came to its target. `ratio` is achieved / target, `delta` the
+// revenue over (positive) or under (negative) the target, and `tolerance` the
+// reality_buffer fraction either side of the target that counts as on target.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RevenueConvergence {
+    pub status: ConvergenceStatus,
+    pub ratio: f64,
+    pub delta: f64,
+    pub delta_percent: f64,
+    pub tolerance: f64,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ConvergenceStatus {
+    Under,
+    OnTarget,
+    Over,
+}
+
+impl SimulationResult {
+    /// An error result for a run that never produced output, e.g. one config in a batch.
+    pub fn failed(seed_used: i32, error: String) -> Self {
+        SimulationResult {
+            invoices: Vec::new(),
+            status: "error".to_string(),
+            error: Some(error),
+            seed_used,
+            warnings: Vec::new(),
+            currency: None,
+            rounding_mode: None,
+            stats: None,
+            audit_path: None,
+            output_paths: Vec::new(),
+            engine_version: None,
+            from_cache: false,
+        }
+    }
+}
+
+// One result per config, in submission order; failed configs carry their error instead
+// of invoices
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct BatchResult {
+    pub results: Vec<BatchRun>,
+    pub succeeded: usize,
+    pub failed: usize,
+    // Passing this back as `master_seed` reproduces the batch
+    #[serde(default)]
+    pub master_seed: u64,
+    // Kept when a config failed, so `resume_batch` can retry just the failures
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub checkpoint_path: Option<String>,
+}
+
+impl BatchResult {
+    pub fn push(&mut self, run: BatchRun) {
+        match &run {
+            BatchRun::Finished(result) if result.error.is_some() => self.failed += 1,
+            _ => self.succeeded += 1,
+        }
+        self.results.push(run);
+    }
+}
+
+/// One config's result in a batch. A successful run stays in the file it was saved to
+/// until the batch is serialized, which reads the results back one at a time, so a batch
+/// never holds more than one of them in memory. A saved result that can no longer be read
+/// serializes as a failure with the config's seed.
+#[derive(Debug)]
+pub enum BatchRun {
+    Finished(Box<SimulationResult>),
+    Saved { path: PathBuf, seed: i32 },
+}
+
+impl Serialize for BatchRun {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        match self {
+            BatchRun::Finished(result) => result.serialize(serializer),
+            BatchRun::Saved { path, seed } => read_saved(path)
+                .unwrap_or_else(|e| {
+                    SimulationResult::failed(
+                        *seed,
+                        format!("Failed to read back batch result: {}", e),
+                    )
+                })
+                .serialize(serializer),
+        }
+    }
+}
+
+fn read_saved(path: &Path) -> Result<SimulationResult, String> {
+    File::open(path)
+        .map_err(|e| e.to_string())
+        .and_then(|f| serde_json::from_reader(BufReader::new(f)).map_err(|e| e.to_string()))
+}
+
+impl<'de> Deserialize<'de> for BatchRun {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        SimulationResult::deserialize(deserializer)
+            .map(|result| BatchRun::Finished(Box::new(result)))
+    }
+}
+
+// What `resume_batch` needs to finish a batch: its configs, already seeded, and catalog,
+// plus each config that has succeeded so far with the file its result was saved to
+#[derive(Debug, Serialize, Deserialize)]
+pub struct BatchCheckpoint {
+    pub master_seed: u64,
+    pub configs: Vec<SimulationConfig>,
+    pub catalog: Vec<CatalogItem>,
+    pub completed: Vec<CompletedRun>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CompletedRun {
+    pub index: usize,
+    pub result_path: String,
+}
+
+// Outcome of a bulk export: how many files were written, plus one message per invoice
+// that couldn't be
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct ExportSummary {
+    pub written: usize,
+    pub failures: Vec<String>,
+}
+
+// Enough to reproduce a run exactly: its seed, blake3 hashes of the compact JSON of its
+// config and catalog, and the versions that ran it. `created_at` is RFC 3339 UTC.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Manifest {
+    pub seed: i32,
+    pub config_hash: String,
+    pub catalog_hash: String,
+    pub engine_version: Option<String>,
+    pub app_version: String,
+    pub created_at: String,
+}
+
+// Whether a config and catalog are the ones a manifest was built from, and if not, how not
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct ManifestCheck {
+    pub matches: bool,
+    pub mismatches: Vec<String>,
+}
+
+// What `run_smoke_test` saw when its fixed config went through the whole pipeline
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SmokeTestReport {
+    pub invoice_count: usize,
+    pub total_revenue: f64,
+    pub duration_secs: f64,
+    pub warnings: Vec<String>,
+}
+
+// The supplier's details for a GST e-invoice, which the simulated invoices don't carry.
+// `state_code` is the two-digit GST state code, e.g. "27" for Maharashtra.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct SellerInfo {
+    pub gstin: String,
+    pub legal_name: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub trade_name: Option<String>,
+    pub address: String,
+    pub location: String,
+    pub pincode: u32,
+    pub state_code: String,
+}
+
+// Who an invoice's UPI payment QR pays: `vpa` is the UPI ID (name@bank) and
+// `merchant_code` the payee's four-digit merchant category code, if it has one
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct PayeeInfo {
+    pub vpa: String,
+    pub name: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub merchant_code: Option<String>,
+}
+
+// A rough range for how many invoices a config will produce; `expected` is what the engine
+// plans for, `low` and `high` what invoice amounts `reality_buffer` above or below the
+// midpoint would take
+#[derive(Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct InvoiceCountEstimate {
+    pub low: u32,
+    pub expected: u32,
+    pub high: u32,
+}
+
+// One customer's line in a ledger view of a run; dates are the invoices' YYYY-MM-DD
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CustomerSummary {
+    pub customer: String,
+    pub invoice_count: usize,
+    pub total_revenue: f64,
+    pub first_invoice_date: String,
+    pub last_invoice_date: String,
+    pub average_invoice_amount: f64,
+}
+
+// A customer name style the engine can generate, for the UI's name_type choices
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct NameType {
+    pub name: String,
+    pub description: String,
+}
+
+// One calendar month of a run, `month` as YYYY-MM; months without invoices are zero
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct MonthlyRevenue {
+    pub month: String,
+    pub invoice_count: usize,
+    pub total_revenue: f64,
+}
+
+// Tax charged at one rate across a run, with the value it was charged on
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TaxBucket {
+    pub rate: f64,
+    pub taxable_value: f64,
+    pub tax_amount: f64,
+}
+
+// A run's tax liability: a bucket per rate, lowest first, and the totals across them.
+// `warnings` names invoices whose tax_breakdown couldn't be read and was skipped for their
+// line items.
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct TaxSummary {
+    pub buckets: Vec<TaxBucket>,
+    pub total_taxable_value: f64,
+    pub total_tax_amount: f64,
+    pub warnings: Vec<String>,
+}
+
+// The selected_items a saved config names that the current catalog lacks, with the
+// closest catalog SKU for each that has a plausible match
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct ReconcileReport {
+    pub missing: Vec<String>,
+    pub suggestions: Vec<SkuSuggestion>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SkuSuggestion {
+    pub selected: String,
+    pub suggestion: String,
+    pub distance: usize, // Levenshtein edits from `selected`, ignoring case
+}
+
+// A catalog with one item per SKU, and a line for each duplicate describing what became
+// of it
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct DedupeReport {
+    pub catalog: Vec<CatalogItem>,
+    pub changes: Vec<String>,
+}
+
+// Every problem found in a catalog. Errors would break or mislead a simulation and should
+// block it; warnings are worth showing but the catalog is still usable.
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct CatalogReport {
+    pub errors: usize,
+    pub warnings: usize,
+    pub issues: Vec<CatalogIssue>,
+}
+
+impl CatalogReport {
+    pub fn push(&mut self, severity: Severity, row: usize, sku: &str, message: String) {
+        match severity {
+            Severity::Error => self.errors += 1,
+            Severity::Warning => self.warnings += 1,
+        }
+        self.issues.push(CatalogIssue {
+            severity,
+            row,
+            sku: sku.to_string(),
+            message,
+        });
+    }
+}
+
+// `row` is the item's 1-based position in the catalog
+#[derive(Debug, Serialize, Deserialize)]
+pub struct CatalogIssue {
+    pub severity: Severity,
+    pub row: usize,
+    pub sku: String,
+    pub message: String,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Severity {
+    Error,
+    Warning,
+}
+
+// What changed between two runs, with invoices matched by invoice number. `a` is the
+// earlier result, so positive deltas mean `b` is higher.
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct ResultDiff {
+    pub added: Vec<String>,   // Invoice numbers only in `b`
+    pub removed: Vec<String>, // Invoice numbers only in `a`
+    pub changed: Vec<AmountChange>,
+    pub revenue_delta: f64,
+    // Customers whose total revenue differs, by name
+    pub customers: Vec<AmountChange>,
+}
+
+// One row of a diff: an invoice number or customer name and its total in each result
+#[derive(Debug, Serialize, Deserialize)]
+pub struct AmountChange {
+    pub key: String,
+    pub before: f64,
+    pub after: f64,
+    pub delta: f64,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Invoice {
+    #[serde(default)]
+    pub invoice_type: String,
+    #[serde(alias = "invoice_id")]
+    pub invoice_number: String,
+    // The engine's number, kept when `invoice_number_prefix` renumbered the invoice
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub original_invoice_number: Option<String>,
+    pub date: String,
+    // Either a `{"name": ...}` object or, in the older shape, a bare name string
+    #[serde(alias = "customer_name")]
+    pub customer: serde_json::Value,
+    pub items: Vec<InvoiceItem>,
+    #[serde(default)]
+    pub subtotal: f64,
+    #[serde(alias = "grand_total")]
+    pub total: f64,
+    // Optional fields. The engine spells it `tax_breakup`
+    #[serde(default, alias = "tax_breakup", skip_serializing_if = "Option::is_none")]
+    pub tax_breakdown: Option<serde_json::Value>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub payment_terms: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub template_applied: Option<String>,
+}
+
+impl Invoice {
+    /// Customer display name, whether `customer` is a `{"name": ...}` object or a bare
+    /// string as in the older shape.
+    pub fn customer_name(&self) -> &str {
+        match &self.customer {
+            serde_json::Value::String(name) => name,
+            value => value.get("name").and_then(|n| n.as_str()).unwrap_or(""),
+        }
+    }
+}
+
+// Serialized with only the canonical `name`/`quantity`; see `LegacyInvoiceItem` for the
+// older field spellings accepted on input.
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(from = "LegacyInvoiceItem")]
+pub struct InvoiceItem {
+    pub name: String,
+    // Fractional for goods sold by weight or measure; integer quantities parse as before
+    pub quantity: f64,
+    pub rate: f64,
+    // Missing from the older shape, which only carried `total`
+    pub amount: f64,
+    // Optional fields for backward compatibility
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub sku: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tax: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub total: Option<f64>,
+    // GST invoices carry the line's rate; HSN/SAC codes only come from engines that add them
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub gst_percent: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub hsn: Option<String>,
+    // Set when the legacy name/item or quantity/qty pairs disagreed
+    #[serde(skip)]
+    pub conflict: Option<String>,
+}
+
+impl InvoiceItem {
+    /// The item's name, whether the engine sent it as `name` or `item`. When both were
+    /// sent and disagree this is `name`, and `conflict` says so.
+    pub fn normalized_name(&self) -> &str {
+        &self.name
+    }
+
+    /// The item's quantity, whether the engine sent it as `quantity` or `qty`. When both
+    /// were sent and disagree this is `quantity`, and `conflict` says so.
+    pub fn normalized_qty(&self) -> f64 {
+        self.quantity
+    }
+
+    /// The line's pre-tax amount. The older shape has no `amount`, only a tax-inclusive
+    /// `total`, so fall back to that (minus tax) and then to `rate * quantity`.
+    pub fn line_amount(&self) -> f64 {
+        if self.amount != 0.0 {
+            return self.amount;
+        }
+        match self.total {
+            Some(total) => total - self.tax.unwrap_or(0.0),
+            None => self.rate * self.quantity,
+        }
+    }
+
+    /// The line's tax-inclusive total, computed from the amount and tax when the engine
+    /// didn't report one.
+    pub fn line_total(&self) -> f64 {
+        self.total
+            .unwrap_or_else(|| self.line_amount() + self.tax.unwrap_or(0.0))
+    }
+}
+
+// Wire format of a line item. Engine templates use either "name" or "item", and either
+// "quantity" or "qty", so both spellings are accepted and collapsed on the way in.
+#[derive(Deserialize)]
+struct LegacyInvoiceItem {
+    #[serde(default)]
+    name: String,
+    #[serde(default)]
+    item: String,
+    #[serde(default)]
+    quantity: f64,
+    #[serde(default)]
+    qty: f64,
+    rate: f64,
+    #[serde(default)]
+    amount: f64,
+    #[serde(default)]
+    sku: Option<String>,
+    #[serde(default)]
+    tax: Option<f64>,
+    #[serde(default)]
+    total: Option<f64>,
+    #[serde(default, alias = "gst_rate")]
+    gst_percent: Option<f64>,
+    #[serde(default, alias = "hsn_code", alias = "hsn_sac")]
+    hsn: Option<String>,
+}
+
+impl LegacyInvoiceItem {
+    // Prefers `name`, falling back to `item` when `name` is empty
+    fn normalized_name(&self) -> String {
+        if self.name.is_empty() {
+            self.item.clone()
+        } else {
+            self.name.clone()
+        }
+    }
+
+    // Prefers `quantity`, falling back to `qty` when `quantity` is zero
+    fn normalized_qty(&self) -> f64 {
+        if self.quantity == 0.0 {
+            self.qty
+        } else {
+            self.quantity
+        }
+    }
+
+    fn conflict(&self) -> Option<String> {
+        let mut conflicts = Vec::new();
+        if !self.name.is_empty() && !self.item.is_empty() && self.name != self.item {
+            conflicts.push(format!("name '{}' vs item '{}'", self.name, self.item));
+        }
+        if self.quantity != 0.0 && self.qty != 0.0 && self.quantity != self.qty {
+            conflicts.push(format!("quantity {} vs qty {}", self.quantity, self.qty));
+        }
+        if conflicts.is_empty() {
+            None
+        } else {
+            Some(format!("{}; using name/quantity", conflicts.join(", ")))
+        }
+    }
+}
+
+impl From<LegacyInvoiceItem> for InvoiceItem {
+    fn from(raw: LegacyInvoiceItem) -> Self {
+        InvoiceItem {
+            name: raw.normalized_name(),
+            quantity: raw.normalized_qty(),
+            conflict: raw.conflict(),
+            rate: raw.rate,
+            amount: raw.amount,
+            sku: raw.sku,
+            tax: raw.tax,
+            total: raw.total,
+            gst_percent: raw.gst_percent,
+            hsn: raw.hsn,
+        }
+    }
+}
+
+// Serialized snake_case because that's what engine.py reads; camelCase is still accepted
+// from the frontend.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CatalogItem {
+    pub sku: String,
+    pub name: String,
+    pub price: f64,
+    #[serde(alias = "gstPercent")]
+    pub gst_percent: Option<f64>,
+    #[serde(alias = "vatPercent")]
+    pub vat_percent: Option<f64>,
+    pub category: Option<String>,
+    // ISO 4217 code the price is in, when it isn't the config's base currency
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub currency: Option<String>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const NEWER: &str = r#"{
+        "invoices": [{
+            "invoice_type": "Plain",
+            "invoice_number": "INV/1",
+            "date": "2024-01-01",
+            "customer": {"name": "Asha Traders"},
+            "items": [{"name": "Desk", "quantity": 2, "rate": 5.0, "amount": 10.0}],
+            "subtotal": 10.0,
+            "total": 10.0,
+            "payment_terms": "Net 30"
+        }],
+        "status": "success",
+        "error": null
+    }"#;
+
+    const OLDER: &str = r#"{
+        "invoices": [{
+            "invoice_id": "INV/2",
+            "date": "2024-01-02",
+            "customer_name": "Bala Stores",
+            "items": [{"sku": "S1", "name": "Chair", "qty": 1, "rate": 5.0, "tax": 0.9, "total": 5.9}],
+            "tax_breakdown": {"18%": {"taxable": 5.0, "tax": 0.9}},
+            "grand_total": 5.9
+        }],
+        "status": "success",
+        "error": null
+    }"#;
+
+    #[test]
+    fn both_invoice_shapes_round_trip() {
+        for (json, number, customer, total) in [
+            (NEWER, "INV/1", "Asha Traders", 10.0),
+            (OLDER, "INV/2", "Bala Stores", 5.9),
+        ] {
+            let parsed: SimulationResult = serde_json::from_str(json).unwrap();
+            let again: SimulationResult =
+                serde_json::from_str(&serde_json::to_string(&parsed).unwrap()).unwrap();
+            for result in [&parsed, &again] {
+                let invoice = &result.invoices[0];
+                assert_eq!(invoice.invoice_number, number);
+                assert_eq!(invoice.customer_name(), customer);
+                assert_eq!(invoice.total, total);
+                assert_eq!(invoice.items.len(), 1);
+            }
+        }
+        let older: SimulationResult = serde_json::from_str(OLDER).unwrap();
+        assert_eq!(older.invoices[0].items[0].line_amount(), 5.0);
+        let fixture: SimulationResult =
+            serde_json::from_str(include_str!("../../backend/test/test_output.json")).unwrap();
+        assert_eq!(fixture.invoices.len(), 12);
+        assert!(fixture.invoices[0].tax_breakdown.is_some());
+    }
+
+    fn item(json: &str) -> InvoiceItem {
+        serde_json::from_str(json).unwrap()
+    }
+
+    #[test]
+    fn legacy_item_spellings_collapse_into_one_field() {
+        let current = item(r#"{"name": "Desk", "quantity": 2, "rate": 5.0}"#);
+        let legacy = item(r#"{"item": "Desk", "qty": 2, "rate": 5.0}"#);
+        for item in [&current, &legacy] {
+            assert_eq!(item.normalized_name(), "Desk");
+            assert_eq!(item.normalized_qty(), 2.0);
+            assert_eq!(item.conflict, None);
+        }
+        let serialized = serde_json::to_value(&legacy).unwrap();
+        assert_eq!(serialized["name"], "Desk");
+        assert!(serialized.get("item").is_none() && serialized.get("qty").is_none());
+    }
+
+    #[test]
+    fn disagreeing_spellings_prefer_name_and_quantity() {
+        let item =
+            item(r#"{"name": "Desk", "item": "Table", "quantity": 2, "qty": 3, "rate": 5.0}"#);
+        assert_eq!(item.normalized_name(), "Desk");
+        assert_eq!(item.normalized_qty(), 2.0);
+        assert_eq!(
+            item.conflict.as_deref(),
+            Some("name 'Desk' vs item 'Table', quantity 2 vs qty 3; using name/quantity")
+        );
+    }
+
+    #[test]
+    fn fractional_quantities_price_by_weight() {
+        let rice = item(r#"{"name": "Rice", "quantity": 2.5, "rate": 64.4}"#);
+        assert_eq!(rice.quantity, 2.5);
+        assert!((rice.line_amount() - 161.0).abs() < 1e-9);
+        // Whole numbers still parse, from either spelling
+        let desks = item(r#"{"item": "Desk", "qty": 3, "rate": 1.0}"#);
+        assert_eq!(desks.quantity, 3.0);
+    }
+}
@@ -0,0 +1,232 @@
+use jsonschema::error::{TypeKind, ValidationErrorKind};
+use jsonschema::paths::LocationSegment;
+use jsonschema::{ValidationError, Validator};
+use serde_json::Value;
+use std::sync::OnceLock;
+
+// Stop listing problems after this many; one broken field usually repeats per invoice
+const MAX_REPORTED: usize = 10;
+
+// What `SimulationResult` accepts, including the older field spellings (`invoice_id`,
+// `customer_name`, `grand_total`, `item`, `qty`). Each `anyOf` lists the spellings of one
+// field, at least one of which must be present.
+const SCHEMA: &str = r#"{
+  "type": "object",
+  "required": ["status", "invoices"],
+  "properties": {
+    "status": { "type": "string" },
+    "error": { "type": ["string", "null"] },
+    "invoices": {
+      "type": "array",
+      "items": {
+        "type": "object",
+        "required": ["date", "items"],
+        "allOf": [
+          { "anyOf": [{ "required": ["invoice_number"] }, { "required": ["invoice_id"] }] },
+          { "anyOf": [{ "required": ["customer"] }, { "required": ["customer_name"] }] },
+          { "anyOf": [{ "required": ["total"] }, { "required": ["grand_total"] }] }
+        ],
+        "properties": {
+          "invoice_type": { "type": "string" },
+          "invoice_number": { "type": "string" },
+          "invoice_id": { "type": "string" },
+          "date": { "type": "string" },
+          "customer": { "type": ["object", "string"] },
+          "customer_name": { "type": "string" },
+          "subtotal": { "type": "number" },
+          "total": { "type": "number" },
+          "grand_total": { "type": "number" },
+          "tax_breakdown": { "type": ["object", "null"] },
+          "payment_terms": { "type": ["string", "null"] },
+          "template_applied": { "type": ["string", "null"] },
+          "items": {
+            "type": "array",
+            "items": {
+              "type": "object",
+              "required": ["rate"],
+              "anyOf": [{ "required": ["name"] }, { "required": ["item"] }],
+              "properties": {
+                "name": { "type": "string" },
+                "item": { "type": "string" },
+                "quantity": { "type": "number" },
+                "qty": { "type": "number" },
+                "rate": { "type": "number" },
+                "amount": { "type": "number" },
+                "sku": { "type": ["string", "null"] },
+                "tax": { "type": ["number", "null"] },
+                "total": { "type": ["number", "null"] }
+              }
+            }
+          }
+        }
+      }
+    }
+  }
+}"#;
+
+/// Checks engine output against the schema, listing each problem by field path, e.g.
+/// `invoices[3].total: expected number, got string`. Only output that failed to
+/// deserialize is checked, since building the `Value` costs as much as the result itself.
+pub fn validate(output: &Value) -> Result<(), String> {
+    let (schema, validator) = compiled();
+    let problems: Vec<String> = validator
+        .iter_errors(output)
+        .map(|error| describe(&error, schema))
+        .collect();
+    if problems.is_empty() {
+        return Ok(());
+    }
+
+    let mut message = problems
+        .iter()
+        .take(MAX_REPORTED)
+        .cloned()
+        .collect::<Vec<_>>()
+        .join("; ");
+    if problems.len() > MAX_REPORTED {
+        message.push_str(&format!(" (and {} more)", problems.len() - MAX_REPORTED));
+    }
+    Err(format!(
+        "Engine output does not match the expected format: {}",
+        message
+    ))
+}
+
+// The schema is a constant, so it's parsed and compiled once and failures are bugs
+fn compiled() -> &'static (Value, Validator) {
+    static COMPILED: OnceLock<(Value, Validator)> = OnceLock::new();
+    COMPILED.get_or_init(|| {
+        let schema: Value = serde_json::from_str(SCHEMA).expect("output schema is valid JSON");
+        let validator = jsonschema::validator_for(&schema).expect("output schema compiles");
+        (schema, validator)
+    })
+}
+
+fn describe(error: &ValidationError, schema: &Value) -> String {
+    let problem = match error.kind() {
+        ValidationErrorKind::Type {
+            kind: TypeKind::Single(expected),
+        } => format!("expected {}, got {}", expected, type_name(error.instance())),
+        ValidationErrorKind::Type {
+            kind: TypeKind::Multiple(expected),
+        } => format!(
+            "expected one of {}, got {}",
+            expected
+                .iter()
+                .map(|t| t.to_string())
+                .collect::<Vec<_>>()
+                .join("/"),
+            type_name(error.instance())
+        ),
+        ValidationErrorKind::Required { property } => {
+            format!("missing field {}", property)
+        }
+        ValidationErrorKind::AnyOf { .. } => {
+            let spellings = alternatives(schema, error.schema_path().as_str());
+            format!("missing field {}", spellings.join(" or "))
+        }
+        _ => error.to_string(),
+    };
+
+    let path = field_path(error);
+    if path.is_empty() {
+        problem
+    } else {
+        format!("{}: {}", path, problem)
+    }
+}
+
+// `/invoices/3/total` -> `invoices[3].total`
+fn field_path(error: &ValidationError) -> String {
+    let mut path = String::new();
+    for segment in error.instance_path().iter() {
+        match segment {
+            LocationSegment::Index(index) => path.push_str(&format!("[{}]", index)),
+            LocationSegment::Property(name) => {
+                if !path.is_empty() {
+                    path.push('.');
+                }
+                path.push_str(&name);
+            }
+        }
+    }
+    path
+}
+
+// The field names listed in the `anyOf` at `schema_path`
+fn alternatives(schema: &Value, schema_path: &str) -> Vec<String> {
+    schema
+        .pointer(schema_path)
+        .and_then(Value::as_array)
+        .map(|options| {
+            options
+                .iter()
+                .filter_map(|option| option.pointer("/required/0"))
+                .map(|name| name.to_string())
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+fn type_name(value: &Value) -> &'static str {
+    match value {
+        Value::Null => "null",
+        Value::Bool(_) => "boolean",
+        Value::Number(_) => "number",
+        Value::String(_) => "string",
+        Value::Array(_) => "array",
+        Value::Object(_) => "object",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const OUTPUT: &str = include_str!("../../backend/test/test_output.json");
+
+    #[test]
+    fn accepts_both_invoice_shapes() {
+        let output: Value = serde_json::from_str(OUTPUT).unwrap();
+        validate(&output).unwrap();
+        let plain: Value =
+            serde_json::from_str(include_str!("../../backend/test/test_output_plain.json"))
+                .unwrap();
+        validate(&plain).unwrap();
+    }
+
+    #[test]
+    fn lists_each_problem_by_field_path() {
+        let mut output: Value = serde_json::from_str(OUTPUT).unwrap();
+        output["invoices"][0]["total"] = Value::from("12");
+        output["invoices"][0]
+            .as_object_mut()
+            .unwrap()
+            .remove("invoice_number");
+        output["invoices"][0]["items"][0]["rate"] = Value::Null;
+        output["error"] = Value::from(3);
+        let error = validate(&output).unwrap_err();
+        for problem in [
+            "invoices[0].total: expected number, got string",
+            "invoices[0]: missing field \"invoice_number\" or \"invoice_id\"",
+            "invoices[0].items[0].rate: expected number, got null",
+            "error: expected one of null/string, got number",
+        ] {
+            assert!(error.contains(problem), "{}", error);
+        }
+    }
+
+    #[test]
+    fn stops_listing_after_max_reported() {
+        let mut output: Value = serde_json::from_str(OUTPUT).unwrap();
+        for invoice in output["invoices"].as_array_mut().unwrap() {
+            invoice["date"] = Value::from(20240711);
+        }
+        let error = validate(&output).unwrap_err();
+        assert_eq!(
+            error.matches("expected string, got number").count(),
+            MAX_REPORTED
+        );
+        assert!(error.ends_with("(and 2 more)"), "{}", error);
+    }
+}
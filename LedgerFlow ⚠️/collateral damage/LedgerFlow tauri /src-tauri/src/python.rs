@@ -0,0 +1,147 @@
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::time::Duration;
+
+// Interpreters tried in order when no explicit path has been set
+const CANDIDATES: &[&[&str]] = &[&["python3"], &["python"], &["py", "-3"]];
+
+// Set for every engine process so output encoding and number formatting don't depend on
+// the user's shell. Entries in `PythonConfig::env` take precedence.
+const DEFAULT_ENV: &[(&str, &str)] = &[("PYTHONIOENCODING", "utf-8"), ("LC_ALL", "C.UTF-8")];
+// Inherited values of these are dropped unless `PythonConfig::env` sets them again
+const CLEARED_ENV: &[&str] = &["PYTHONPATH"];
+
+// An interpreter still starting up after this long is as good as not working
+const PROBE_TIMEOUT: Duration = Duration::from_secs(5);
+
+#[derive(Debug, Clone, Default)]
+pub struct PythonConfig {
+    // Interpreter path set via `set_python_path`, None means auto-detect
+    pub path: Option<String>,
+    // Environment overrides set via `set_engine_env`, applied on top of DEFAULT_ENV
+    pub env: HashMap<String, String>,
+    // Set via `set_engine_working_dir`, None means the engine script's own directory
+    pub working_dir: Option<PathBuf>,
+}
+
+#[derive(Debug, Clone)]
+pub struct Interpreter {
+    pub program: String,
+    pub args: Vec<String>,
+    pub env: HashMap<String, String>,
+}
+
+impl Interpreter {
+    fn new(parts: &[&str]) -> Self {
+        Interpreter {
+            program: parts[0].to_string(),
+            args: parts[1..].iter().map(|a| a.to_string()).collect(),
+            env: HashMap::new(),
+        }
+    }
+
+    /// Builds a `Command` for this interpreter with any launcher args (e.g. `py -3`) and
+    /// the curated environment applied.
+    pub fn command(&self) -> Command {
+        let mut cmd = Command::new(&self.program);
+        cmd.args(&self.args);
+        for name in CLEARED_ENV {
+            cmd.env_remove(name);
+        }
+        cmd.envs(DEFAULT_ENV.iter().copied());
+        cmd.envs(&self.env);
+        cmd
+    }
+
+    /// Returns true if `<interpreter> --version` runs and exits cleanly within
+    /// `PROBE_TIMEOUT`. It runs as a tokio process so callers on the async runtime don't
+    /// block a worker thread while it starts.
+    pub async fn is_working(&self) -> bool {
+        let mut command = tokio::process::Command::from(self.command());
+        command
+            .arg("--version")
+            .stdin(Stdio::null())
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .kill_on_drop(true);
+        matches!(
+            tokio::time::timeout(PROBE_TIMEOUT, command.status()).await,
+            Ok(Ok(status)) if status.success()
+        )
+    }
+}
+
+impl std::fmt::Display for Interpreter {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.program)?;
+        for arg in &self.args {
+            write!(f, " {}", arg)?;
+        }
+        Ok(())
+    }
+}
+
+impl PythonConfig {
+    /// Resolves the interpreter to use: the configured path if set, otherwise the first
+    /// working candidate from auto-detection.
+    pub async fn resolve(&self) -> Result<Interpreter, String> {
+        let mut interpreter = match &self.path {
+            Some(path) => {
+                let interpreter = Interpreter::new(&[path]);
+                if !interpreter.is_working().await {
+                    return Err(format!(
+                        "Configured Python interpreter '{}' is not runnable",
+                        path
+                    ));
+                }
+                interpreter
+            }
+            None => detect().await?,
+        };
+        interpreter.env = self.env.clone();
+        Ok(interpreter)
+    }
+
+    /// The directory the engine runs in, so relative paths it opens don't depend on where
+    /// the app was launched from.
+    pub fn working_dir(&self, script: &Path) -> PathBuf {
+        self.working_dir
+            .clone()
+            .or_else(|| script.parent().map(Path::to_path_buf))
+            .unwrap_or_else(|| PathBuf::from("."))
+    }
+}
+
+/// Tries `python3`, `python` and `py -3` in order and returns the first that runs.
+pub async fn detect() -> Result<Interpreter, String> {
+    let mut tried = Vec::new();
+    for parts in CANDIDATES {
+        let interpreter = Interpreter::new(parts);
+        if interpreter.is_working().await {
+            return Ok(interpreter);
+        }
+        tried.push(interpreter.to_string());
+    }
+    Err(format!(
+        "No working Python interpreter found (tried: {})",
+        tried.join(", ")
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn rejects_a_configured_path_that_does_not_run() {
+        let config = PythonConfig {
+            path: Some("/nonexistent/python3".to_string()),
+            ..PythonConfig::default()
+        };
+        assert_eq!(
+            config.resolve().await.unwrap_err(),
+            "Configured Python interpreter '/nonexistent/python3' is not runnable"
+        );
+    }
+}
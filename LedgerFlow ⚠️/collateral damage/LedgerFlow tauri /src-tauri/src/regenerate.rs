@@ -0,0 +1,161 @@
+use crate::filter;
+use crate::models::{SimulationConfig, SimulationResult};
+use crate::stats;
+use crate::validation;
+use crate::verify;
+use std::time::Duration;
+
+/// Points `config` at a single invoice like the one numbered `invoice_number`: on its
+/// date, for its total, and no further than `reality_buffer` from it. Numbering, output
+/// files and the run's other extras are switched off, since the new invoice takes the
+/// old one's place. Returns the old invoice's position in `result`.
+pub fn prepare(
+    config: &mut SimulationConfig,
+    result: &SimulationResult,
+    invoice_number: &str,
+    seed: i32,
+) -> Result<usize, String> {
+    if result.error.is_some() {
+        return Err("Cannot regenerate an invoice of a simulation that failed".to_string());
+    }
+    let index = result
+        .invoices
+        .iter()
+        .position(|invoice| invoice.invoice_number == invoice_number)
+        .ok_or_else(|| format!("Invoice {} not found in the result", invoice_number))?;
+    let invoice = &result.invoices[index];
+    let buffer = config
+        .reality_buffer
+        .unwrap_or(validation::DEFAULT_REALITY_BUFFER);
+
+    config.revenue_target = invoice.total;
+    config.min_invoice_amount = invoice.total * (1.0 - buffer);
+    config.max_invoice_amount = invoice.total * (1.0 + buffer);
+    config.start_date = invoice.date.clone();
+    config.end_date = invoice.date.clone();
+    // A single day can't be spread or weighted
+    config.distribution_mode = None;
+    config.invoice_count_mode = Some("manual".to_string());
+    config.manual_invoice_count = Some(1);
+    config.seed = Some(seed);
+    config.invoice_number_start = None;
+    config.invoice_number_prefix = None;
+    config.output_formats.clear();
+    config.output_path = None;
+    config.save_raw_output = None;
+    Ok(index)
+}
+
+/// Puts the first invoice of `fresh` in place of the one at `index`, under the old
+/// invoice's number and date, and recomputes the stats against the run's original target.
+/// Warnings about the old invoice give way to the totals checks of the new one.
+pub fn replace(
+    mut result: SimulationResult,
+    index: usize,
+    fresh: SimulationResult,
+) -> Result<SimulationResult, String> {
+    let mut invoice = fresh
+        .invoices
+        .into_iter()
+        .next()
+        .ok_or_else(|| "The engine generated no replacement invoice".to_string())?;
+    let old = &result.invoices[index];
+    invoice.invoice_number = old.invoice_number.clone();
+    invoice.original_invoice_number = old.original_invoice_number.clone();
+    invoice.date = old.date.clone();
+
+    let number = invoice.invoice_number.clone();
+    result
+        .warnings
+        .retain(|warning| filter::invoice_named(warning) != Some(number.as_str()));
+    let replaced = std::slice::from_ref(&invoice);
+    result.warnings.extend(verify::verify_totals(replaced));
+    result
+        .warnings
+        .extend(verify::verify_tax_breakdown(replaced));
+    result.invoices[index] = invoice;
+
+    if let Some(previous) = result.stats.take() {
+        let mut recomputed = stats::compute(
+            &result.invoices,
+            previous.revenue_target,
+            Duration::from_secs_f64(previous.duration_secs),
+        );
+        recomputed.convergence = previous
+            .convergence
+            .and_then(|convergence| stats::convergence(&recomputed, convergence.tolerance));
+        result.stats = Some(recomputed);
+    }
+    // Written before the invoice changed
+    result.output_paths.clear();
+    Ok(result)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config() -> SimulationConfig {
+        serde_json::from_str(include_str!("../../backend/test/test_config.json")).unwrap()
+    }
+
+    fn result() -> SimulationResult {
+        let mut result: SimulationResult =
+            serde_json::from_str(include_str!("../../backend/test/test_output.json")).unwrap();
+        result.stats = Some(stats::compute(&result.invoices, 100000.0, Duration::ZERO));
+        result
+    }
+
+    #[test]
+    fn aims_the_config_at_the_one_invoice() {
+        let mut config = config();
+        assert_eq!(
+            prepare(&mut config, &result(), "GST/FY24-25/03574", 9),
+            Ok(2)
+        );
+        assert_eq!(config.revenue_target, 13701.64);
+        assert_eq!(
+            (config.start_date.as_str(), config.end_date.as_str()),
+            ("2024-07-14", "2024-07-14")
+        );
+        assert_eq!(config.manual_invoice_count, Some(1));
+        assert_eq!(config.seed, Some(9));
+
+        assert_eq!(
+            prepare(&mut self::config(), &result(), "INV-404", 9),
+            Err("Invoice INV-404 not found in the result".to_string())
+        );
+    }
+
+    #[test]
+    fn replaces_only_the_regenerated_invoice() {
+        let mut before = result();
+        before.warnings = vec![
+            "Invoice GST/FY24-25/03572: stale".to_string(),
+            "Invoice GST/FY24-25/03583: unrelated".to_string(),
+        ];
+        let mut fresh = result();
+        fresh.invoices.drain(..4);
+        let fresh_total = fresh.invoices[0].total;
+
+        let after = replace(before, 0, fresh).unwrap();
+        let before = result();
+        assert_eq!(after.invoices.len(), before.invoices.len());
+        let replaced = &after.invoices[0];
+        assert_eq!(replaced.invoice_number, "GST/FY24-25/03572");
+        assert_eq!(replaced.date, before.invoices[0].date);
+        assert_eq!(replaced.total, fresh_total);
+        for (old, new) in before.invoices.iter().zip(&after.invoices).skip(1) {
+            assert_eq!(
+                (old.invoice_number.as_str(), old.total),
+                (new.invoice_number.as_str(), new.total)
+            );
+        }
+        assert_eq!(after.warnings, ["Invoice GST/FY24-25/03583: unrelated"]);
+        let revenue: f64 = after.invoices.iter().map(|i| i.total).sum();
+        assert_eq!(after.stats.unwrap().total_revenue, revenue);
+
+        assert_eq!(filter::invoice_named("Invoice A-1: late"), Some("A-1"));
+        assert_eq!(filter::invoice_named("Run took a while"), None);
+    }
+}
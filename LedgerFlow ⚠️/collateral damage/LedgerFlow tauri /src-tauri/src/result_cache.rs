@@ -0,0 +1,168 @@
+use crate::models::SimulationResult;
+use std::fs::{self, File};
+use std::io::{BufReader, BufWriter, ErrorKind};
+use std::path::Path;
+use std::time::SystemTime;
+
+// Least recently used results are dropped once the cache grows past this
+const MAX_CACHE_BYTES: u64 = 256 * 1024 * 1024;
+
+/// Hex digest identifying a run by everything that determines its output: the config,
+/// the catalog, the seed and the engine script itself.
+pub fn key(config_json: &str, catalog_json: &str, seed: i32, engine: &[u8]) -> String {
+    let mut hasher = blake3::Hasher::new();
+    // Length-prefixed so no two different inputs concatenate to the same bytes
+    for part in [config_json.as_bytes(), catalog_json.as_bytes(), engine] {
+        hasher.update(&(part.len() as u64).to_le_bytes());
+        hasher.update(part);
+    }
+    hasher.update(&seed.to_le_bytes());
+    hasher.finalize().to_hex().to_string()
+}
+
+/// Returns the cached result for `key`, if any. A hit counts as a use for eviction.
+pub fn load(dir: &Path, key: &str) -> Option<SimulationResult> {
+    let path = dir.join(format!("{}.json", key));
+    let file = File::open(&path).ok()?;
+    // An unreadable entry, e.g. from an older version, is just a miss
+    let result = serde_json::from_reader(BufReader::new(&file)).ok()?;
+    let _ = File::options()
+        .write(true)
+        .open(&path)
+        .and_then(|file| file.set_modified(SystemTime::now()));
+    Some(result)
+}
+
+/// Stores `result` under `key`, then evicts the least recently used entries until the
+/// cache is back under its size limit. A failed eviction is only logged, since the entry
+/// itself was stored.
+pub fn store(dir: &Path, key: &str, result: &SimulationResult) -> Result<(), String> {
+    fs::create_dir_all(dir).map_err(|e| format!("Failed to create cache directory: {}", e))?;
+    let path = dir.join(format!("{}.json", key));
+    // Renamed into place so a concurrent load never sees half a file
+    let staging = path.with_extension("json.tmp");
+    let file = File::create(&staging).map_err(|e| format!("Failed to write cache entry: {}", e))?;
+    serde_json::to_writer(BufWriter::new(file), result)
+        .map_err(|e| format!("Failed to write cache entry: {}", e))?;
+    fs::rename(&staging, &path).map_err(|e| format!("Failed to write cache entry: {}", e))?;
+    if let Err(e) = evict(dir, MAX_CACHE_BYTES) {
+        tracing::warn!(error = %e, "Failed to evict cached results");
+    }
+    Ok(())
+}
+
+/// Removes every cached result, returning how many there were.
+pub fn clear(dir: &Path) -> Result<usize, String> {
+    let entries = match entries(dir) {
+        Ok(entries) => entries,
+        Err(e) if e.kind() == ErrorKind::NotFound => return Ok(0),
+        Err(e) => return Err(format!("Failed to read cache directory: {}", e)),
+    };
+    for (path, _, _) in &entries {
+        fs::remove_file(path).map_err(|e| format!("Failed to remove cache entry: {}", e))?;
+    }
+    Ok(entries.len())
+}
+
+fn evict(dir: &Path, max_bytes: u64) -> Result<(), String> {
+    let mut entries = entries(dir).map_err(|e| format!("Failed to read cache directory: {}", e))?;
+    let mut total: u64 = entries.iter().map(|(_, size, _)| size).sum();
+    // Oldest first
+    entries.sort_by_key(|(_, _, used)| *used);
+    for (path, size, _) in entries {
+        if total <= max_bytes {
+            break;
+        }
+        fs::remove_file(&path).map_err(|e| format!("Failed to remove cache entry: {}", e))?;
+        total -= size;
+    }
+    Ok(())
+}
+
+// Cached results with their size and last use
+fn entries(dir: &Path) -> std::io::Result<Vec<(std::path::PathBuf, u64, SystemTime)>> {
+    let mut entries = Vec::new();
+    for entry in fs::read_dir(dir)? {
+        let path = entry?.path();
+        if path.extension().is_some_and(|ext| ext == "json") {
+            let meta = fs::metadata(&path)?;
+            entries.push((path, meta.len(), meta.modified()?));
+        }
+    }
+    Ok(entries)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    const CONFIG: &str = include_str!("../../backend/test/test_config.json");
+    const OUTPUT: &str = include_str!("../../backend/test/test_output.json");
+
+    fn cache_dir(name: &str) -> std::path::PathBuf {
+        std::env::temp_dir().join(format!("ledgerflow_cache_{}_{}", name, std::process::id()))
+    }
+
+    fn result() -> SimulationResult {
+        serde_json::from_str(OUTPUT).unwrap()
+    }
+
+    #[test]
+    fn key_changes_with_the_config_catalog_seed_and_engine() {
+        let base = key(CONFIG, "[]", 7, b"engine");
+        assert_eq!(base, key(CONFIG, "[]", 7, b"engine"));
+        let others = [
+            key(&CONFIG.replace("100000", "100001"), "[]", 7, b"engine"),
+            key(CONFIG, r#"[{"sku": "A"}]"#, 7, b"engine"),
+            key(CONFIG, "[]", 8, b"engine"),
+            key(CONFIG, "[]", 7, b"engine v2"),
+        ];
+        for other in &others {
+            assert_ne!(*other, base);
+        }
+        // Moving bytes between the parts still changes the key
+        assert_ne!(key("ab", "c", 7, b""), key("a", "bc", 7, b""));
+    }
+
+    #[test]
+    fn stored_results_load_back() {
+        let dir = cache_dir("round_trip");
+        let key = key(CONFIG, "[]", 7, b"engine");
+        assert!(load(&dir, &key).is_none());
+        store(&dir, &key, &result()).unwrap();
+        let loaded = load(&dir, &key).unwrap();
+        assert_eq!(
+            serde_json::to_value(&loaded).unwrap(),
+            serde_json::to_value(result()).unwrap()
+        );
+        assert!(!dir.join(format!("{}.json.tmp", key)).exists());
+        assert_eq!(clear(&dir), Ok(1));
+        assert!(load(&dir, &key).is_none());
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn evicts_the_least_recently_used_first() {
+        let dir = cache_dir("evict");
+        let now = SystemTime::now();
+        for (age, name) in [(300, "a"), (200, "b"), (100, "c")] {
+            store(&dir, name, &result()).unwrap();
+            File::options()
+                .write(true)
+                .open(dir.join(format!("{}.json", name)))
+                .and_then(|file| file.set_modified(now - Duration::from_secs(age)))
+                .unwrap();
+        }
+        // Loading the older "b" makes it the most recently used
+        load(&dir, "b").unwrap();
+        let size = fs::metadata(dir.join("a.json")).unwrap().len();
+
+        evict(&dir, 2 * size).unwrap();
+        assert!(load(&dir, "a").is_none());
+        evict(&dir, size).unwrap();
+        assert!(load(&dir, "c").is_none());
+        assert!(load(&dir, "b").is_some());
+        let _ = fs::remove_dir_all(&dir);
+    }
+}
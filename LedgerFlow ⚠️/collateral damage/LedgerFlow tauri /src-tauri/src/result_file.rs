@@ -0,0 +1,34 @@
+use crate::engine;
+use crate::engine_output;
+use crate::models::SimulationResult;
+use crate::verify;
+use std::fs::File;
+use std::io;
+use std::path::Path;
+
+/// Reopens a result saved as JSON, plain or gzipped, in either invoice shape the engine
+/// has produced. The totals are checked again as if the run had just finished, and any
+/// new warnings are added to those saved with it.
+pub fn load(path: &Path) -> Result<SimulationResult, String> {
+    let file = File::open(path).map_err(|e| match e.kind() {
+        io::ErrorKind::NotFound => format!("Result file not found: {}", path.display()),
+        _ => format!("Failed to open result file {}: {}", path.display(), e),
+    })?;
+    let not_a_result =
+        |e: String| format!("{} is not a LedgerFlow result file: {}", path.display(), e);
+    let mut result =
+        engine_output::parse(file, engine::DEFAULT_MAX_OUTPUT_BYTES).map_err(not_a_result)?;
+
+    let checks = verify::duplicate_invoice_numbers(&result.invoices)
+        .into_iter()
+        .chain(verify::field_conflicts(&result.invoices))
+        .chain(verify::verify_totals(&result.invoices))
+        .chain(verify::verify_tax_breakdown(&result.invoices));
+    for warning in checks {
+        // A result saved after a run already carries that run's warnings
+        if !result.warnings.contains(&warning) {
+            result.warnings.push(warning);
+        }
+    }
+    Ok(result)
+}
@@ -0,0 +1,94 @@
+/// The values `rounding_mode` accepts.
+pub const ROUNDING_MODES: &[&str] = &["half_up", "half_even", "truncate"];
+
+// Amounts are scaled to whole cents and then snapped to this fraction of a cent, so
+// 2.345 (stored as 2.34499999...) is treated as the tie it was written as
+const SNAP: f64 = 1e6;
+
+/// How amounts are rounded to cents wherever Rust recomputes or formats them.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum RoundingMode {
+    /// Ties away from zero: 2.345 -> 2.35, -2.345 -> -2.35.
+    #[default]
+    HalfUp,
+    /// Ties to the even digit (banker's rounding): 2.345 -> 2.34, 2.355 -> 2.36.
+    HalfEven,
+    /// Drops the extra digits: 2.349 -> 2.34, -2.349 -> -2.34.
+    Truncate,
+}
+
+impl RoundingMode {
+    /// Reads a config's `rounding_mode`, `half_up` when it's unset.
+    pub fn parse(mode: Option<&str>) -> Result<Self, String> {
+        match mode.map(str::trim) {
+            None | Some("half_up") => Ok(RoundingMode::HalfUp),
+            Some("half_even") => Ok(RoundingMode::HalfEven),
+            Some("truncate") => Ok(RoundingMode::Truncate),
+            Some(other) => Err(format!(
+                "Unknown rounding_mode '{}' (expected one of: {})",
+                other,
+                ROUNDING_MODES.join(", ")
+            )),
+        }
+    }
+
+    /// Like `parse`, for a mode that was already validated with the config.
+    pub fn or_default(mode: Option<&str>) -> Self {
+        Self::parse(mode).unwrap_or_default()
+    }
+
+    pub fn round(self, value: f64, decimals: u32) -> f64 {
+        if !value.is_finite() {
+            return value;
+        }
+        let factor = 10f64.powi(decimals as i32);
+        let scaled = (value * factor * SNAP).round() / SNAP;
+        let rounded = match self {
+            RoundingMode::HalfUp => scaled.round(),
+            RoundingMode::HalfEven => scaled.round_ties_even(),
+            RoundingMode::Truncate => scaled.trunc(),
+        };
+        rounded / factor
+    }
+
+    pub fn round_cents(self, value: f64) -> f64 {
+        self.round(value, 2)
+    }
+
+    /// `value` rounded to `decimals` places and printed with exactly that many.
+    pub fn format(self, value: f64, decimals: u32) -> String {
+        format!("{:.*}", decimals as usize, self.round(value, decimals))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn each_mode_settles_ties_its_own_way() {
+        let cases = [
+            (RoundingMode::HalfUp, [2.35, 2.36, -2.35]),
+            (RoundingMode::HalfEven, [2.34, 2.36, -2.34]),
+            (RoundingMode::Truncate, [2.34, 2.35, -2.34]),
+        ];
+        for (mode, expected) in cases {
+            let rounded = [2.345, 2.355, -2.345].map(|value| mode.round_cents(value));
+            assert_eq!(rounded, expected, "{:?}", mode);
+        }
+        assert_eq!(RoundingMode::Truncate.format(2.349, 2), "2.34");
+        assert_eq!(RoundingMode::HalfUp.format(f64::NAN, 2), "NaN");
+    }
+
+    #[test]
+    fn parses_every_listed_mode() {
+        for mode in ROUNDING_MODES {
+            RoundingMode::parse(Some(mode)).unwrap();
+        }
+        assert_eq!(RoundingMode::parse(None), Ok(RoundingMode::HalfUp));
+        assert_eq!(
+            RoundingMode::parse(Some("banker")).unwrap_err(),
+            "Unknown rounding_mode 'banker' (expected one of: half_up, half_even, truncate)"
+        );
+    }
+}
@@ -0,0 +1,95 @@
+use crate::models::Invoice;
+use rand::rngs::StdRng;
+use rand::seq::index;
+use rand::SeedableRng;
+use std::collections::BTreeMap;
+
+/// Picks up to `n` invoices, spread over the months they fall in: each month contributes
+/// in proportion to its share of invoices, chosen at random within the month. The same
+/// seed always picks the same invoices. The sample keeps the invoices' original order.
+pub fn sample(invoices: Vec<Invoice>, n: usize, seed: u64) -> Vec<Invoice> {
+    if n >= invoices.len() {
+        return invoices;
+    }
+
+    // Month ("YYYY-MM") -> positions of its invoices; undated invoices share a bucket
+    let mut months: BTreeMap<&str, Vec<usize>> = BTreeMap::new();
+    for (i, invoice) in invoices.iter().enumerate() {
+        let month = invoice.date.get(..7).unwrap_or("");
+        months.entry(month).or_default().push(i);
+    }
+
+    let mut rng = StdRng::seed_from_u64(seed);
+    let mut chosen = vec![false; invoices.len()];
+    // Rounding the running total keeps the quotas summing to exactly n
+    let (mut seen, mut taken) = (0, 0);
+    for positions in months.values() {
+        seen += positions.len();
+        let quota = (n * seen + invoices.len() / 2) / invoices.len() - taken;
+        taken += quota;
+        for pick in index::sample(&mut rng, positions.len(), quota) {
+            chosen[positions[pick]] = true;
+        }
+    }
+
+    invoices
+        .into_iter()
+        .zip(chosen)
+        .filter_map(|(invoice, keep)| keep.then_some(invoice))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::SimulationResult;
+
+    // Six invoices in May, four in June and two in July
+    fn invoices() -> Vec<Invoice> {
+        let result: SimulationResult =
+            serde_json::from_str(include_str!("../../backend/test/test_output.json")).unwrap();
+        let mut invoices = result.invoices;
+        for (i, invoice) in invoices.iter_mut().enumerate() {
+            let month = match i {
+                0..=5 => "05",
+                6..=9 => "06",
+                _ => "07",
+            };
+            invoice.date.replace_range(5..7, month);
+        }
+        invoices
+    }
+
+    fn numbers(invoices: &[Invoice]) -> Vec<&str> {
+        invoices.iter().map(|i| i.invoice_number.as_str()).collect()
+    }
+
+    #[test]
+    fn asking_for_more_than_there_are_returns_them_all() {
+        let all = sample(invoices(), 20, 7);
+        assert_eq!(numbers(&all), numbers(&invoices()));
+        assert!(sample(Vec::new(), 5, 7).is_empty());
+    }
+
+    #[test]
+    fn the_same_seed_picks_the_same_invoices() {
+        let first = sample(invoices(), 5, 7);
+        assert_eq!(numbers(&first), numbers(&sample(invoices(), 5, 7)));
+        // Still in the original order
+        let mut sorted = numbers(&first);
+        sorted.sort();
+        assert_eq!(sorted, numbers(&first));
+    }
+
+    #[test]
+    fn each_month_gets_its_share() {
+        let picked = sample(invoices(), 6, 7);
+        assert_eq!(picked.len(), 6);
+        let in_month = |month: &str| picked.iter().filter(|i| &i.date[5..7] == month).count();
+        assert_eq!((in_month("05"), in_month("06"), in_month("07")), (3, 2, 1));
+        // Rounded shares still add up to whatever was asked for
+        for n in 1..12 {
+            assert_eq!(sample(invoices(), n, 7).len(), n);
+        }
+    }
+}
@@ -0,0 +1,67 @@
+use crate::models::{CatalogItem, SimulationConfig, SimulationResult, SmokeTestReport};
+use crate::verify;
+use serde_json::json;
+use std::time::Duration;
+
+/// A week of small GST invoices drawn from `catalog()`, with a fixed seed so every install
+/// runs the same simulation.
+pub fn config() -> SimulationConfig {
+    let config = json!({
+        "revenue_target": 5000.0,
+        "start_date": "2024-01-01",
+        "end_date": "2024-01-07",
+        "invoice_type": "gst",
+        "min_items": 1,
+        "max_items": 1,
+        "min_invoice_amount": 500.0,
+        "max_invoice_amount": 2000.0,
+        "item_filter_mode": "all",
+        "selected_items": [],
+        "name_type": "indian_company",
+        "realism_mode": "random",
+        "seed": 20240101,
+        "timeout_secs": 30,
+        // A cached answer wouldn't show whether the engine itself still works
+        "skip_cache": true,
+    });
+    serde_json::from_value(config).expect("smoke test config matches SimulationConfig")
+}
+
+pub fn catalog() -> Vec<CatalogItem> {
+    vec![CatalogItem {
+        sku: "SMOKE-1".to_string(),
+        name: "Smoke Test Item".to_string(),
+        price: 250.0,
+        gst_percent: Some(18.0),
+        vat_percent: None,
+        category: None,
+        currency: None,
+    }]
+}
+
+/// Checks what the pipeline made of `config()`: there has to be at least one invoice, and
+/// every invoice's lines have to add up to its total. `run` is the pipeline's own outcome,
+/// so an engine that fails to start or exits with an error fails the smoke test with its
+/// message.
+pub fn check<E: std::fmt::Display>(
+    run: Result<SimulationResult, E>,
+    elapsed: Duration,
+) -> Result<SmokeTestReport, String> {
+    let result = run.map_err(|e| format!("Smoke test failed: {}", e))?;
+    if result.invoices.is_empty() {
+        return Err("Smoke test failed: the engine returned no invoices".to_string());
+    }
+    let mismatches = verify::verify_totals(&result.invoices);
+    if !mismatches.is_empty() {
+        return Err(format!(
+            "Smoke test failed: totals don't reconcile: {}",
+            mismatches.join("; ")
+        ));
+    }
+    Ok(SmokeTestReport {
+        invoice_count: result.invoices.len(),
+        total_revenue: result.invoices.iter().map(|invoice| invoice.total).sum(),
+        duration_secs: elapsed.as_secs_f64(),
+        warnings: result.warnings,
+    })
+}
@@ -0,0 +1,420 @@
+use crate::models::{
+    ConvergenceStatus, CustomerSummary, Invoice, InvoiceItem, MonthlyRevenue, RevenueConvergence,
+    SimulationStats, TaxBucket, TaxSummary,
+};
+use crate::verify;
+use chrono::{Datelike, NaiveDate};
+use serde_json::Value;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::time::Duration;
+
+/// Summarises a run's invoices for display, so the UI doesn't have to rescan them.
+pub fn compute(invoices: &[Invoice], revenue_target: f64, duration: Duration) -> SimulationStats {
+    let totals = invoices.iter().map(|invoice| invoice.total);
+    let total_revenue: f64 = totals.clone().sum();
+    let mut customers = HashSet::new();
+    // `insert` is false for a customer already seen on an earlier invoice
+    let repeats = invoices
+        .iter()
+        .filter(|invoice| !customers.insert(invoice.customer_name()))
+        .count();
+    SimulationStats {
+        invoice_count: invoices.len(),
+        total_revenue,
+        revenue_target,
+        revenue_achieved_percent: if revenue_target > 0.0 {
+            total_revenue / revenue_target * 100.0
+        } else {
+            0.0
+        },
+        // Zero rather than infinities for a run with no invoices
+        min_invoice_amount: totals.clone().reduce(f64::min).unwrap_or(0.0),
+        max_invoice_amount: totals.reduce(f64::max).unwrap_or(0.0),
+        mean_invoice_amount: if invoices.is_empty() {
+            0.0
+        } else {
+            total_revenue / invoices.len() as f64
+        },
+        distinct_customers: customers.len(),
+        repeat_customer_rate: if invoices.is_empty() {
+            0.0
+        } else {
+            repeats as f64 / invoices.len() as f64
+        },
+        duration_secs: duration.as_secs_f64(),
+        convergence: None,
+    }
+}
+
+/// Compares the revenue in `stats` with its target, classing anything within
+/// `tolerance` (a fraction of the target, the config's `reality_buffer`) either side as
+/// on target. None when there's no target to compare with.
+pub fn convergence(stats: &SimulationStats, tolerance: f64) -> Option<RevenueConvergence> {
+    if stats.revenue_target <= 0.0 {
+        return None;
+    }
+    let ratio = stats.total_revenue / stats.revenue_target;
+    let status = if (ratio - 1.0).abs() <= tolerance {
+        ConvergenceStatus::OnTarget
+    } else if ratio < 1.0 {
+        ConvergenceStatus::Under
+    } else {
+        ConvergenceStatus::Over
+    };
+    Some(RevenueConvergence {
+        status,
+        ratio,
+        delta: stats.total_revenue - stats.revenue_target,
+        delta_percent: (ratio - 1.0) * 100.0,
+        tolerance,
+    })
+}
+
+/// Totals the invoices per customer, largest total first (ties by name). Names are
+/// compared trimmed, so a customer matches across both invoice shapes and stray spaces.
+pub fn by_customer(invoices: &[Invoice]) -> Vec<CustomerSummary> {
+    let mut customers: HashMap<&str, CustomerSummary> = HashMap::new();
+    for invoice in invoices {
+        let name = invoice.customer_name().trim();
+        let summary = customers.entry(name).or_insert_with(|| CustomerSummary {
+            customer: name.to_string(),
+            invoice_count: 0,
+            total_revenue: 0.0,
+            first_invoice_date: invoice.date.clone(),
+            last_invoice_date: invoice.date.clone(),
+            average_invoice_amount: 0.0,
+        });
+        summary.invoice_count += 1;
+        summary.total_revenue += invoice.total;
+        // YYYY-MM-DD sorts as text
+        if invoice.date < summary.first_invoice_date {
+            summary.first_invoice_date = invoice.date.clone();
+        }
+        if invoice.date > summary.last_invoice_date {
+            summary.last_invoice_date = invoice.date.clone();
+        }
+    }
+    let mut summaries: Vec<CustomerSummary> = customers
+        .into_values()
+        .map(|mut summary| {
+            summary.average_invoice_amount = summary.total_revenue / summary.invoice_count as f64;
+            summary
+        })
+        .collect();
+    summaries.sort_by(|a, b| {
+        b.total_revenue
+            .total_cmp(&a.total_revenue)
+            .then_with(|| a.customer.cmp(&b.customer))
+    });
+    summaries
+}
+
+/// How many invoices have each number of line items, as (items, invoices) pairs in
+/// ascending item count. Counts no invoice has are left out.
+pub fn item_count_distribution(invoices: &[Invoice]) -> Vec<(u32, u32)> {
+    let mut counts: BTreeMap<u32, u32> = BTreeMap::new();
+    for invoice in invoices {
+        *counts.entry(invoice.items.len() as u32).or_insert(0) += 1;
+    }
+    counts.into_iter().collect()
+}
+
+/// Totals the invoices per calendar month, oldest first, with a zero entry for each month
+/// between the first and last that has no invoices, so a chart of it has no gaps.
+/// Invoices whose date doesn't parse are left out and logged.
+pub fn by_month(invoices: &[Invoice]) -> Vec<MonthlyRevenue> {
+    // (year, month) -> (count, total)
+    let mut months: BTreeMap<(i32, u32), (usize, f64)> = BTreeMap::new();
+    for invoice in invoices {
+        let Ok(date) = NaiveDate::parse_from_str(invoice.date.trim(), "%Y-%m-%d") else {
+            tracing::warn!(
+                invoice = %invoice.invoice_number,
+                date = %invoice.date,
+                "Skipping invoice with an unparseable date in the monthly breakdown"
+            );
+            continue;
+        };
+        let month = months.entry((date.year(), date.month())).or_default();
+        month.0 += 1;
+        month.1 += invoice.total;
+    }
+
+    let (Some(&first), Some(&last)) = (months.keys().next(), months.keys().next_back()) else {
+        return Vec::new();
+    };
+    let mut breakdown = Vec::new();
+    let (mut year, mut month) = first;
+    while (year, month) <= last {
+        let (invoice_count, total_revenue) =
+            months.get(&(year, month)).copied().unwrap_or_default();
+        breakdown.push(MonthlyRevenue {
+            month: format!("{:04}-{:02}", year, month),
+            invoice_count,
+            total_revenue,
+        });
+        (year, month) = if month == 12 {
+            (year + 1, 1)
+        } else {
+            (year, month + 1)
+        };
+    }
+    breakdown
+}
+
+/// Totals the tax a run charges per rate. An invoice's grouped `tax_breakdown` is used
+/// when it has one; otherwise, or when the breakdown can't be read, each line item counts
+/// at its `gst_percent` or the rate its `tax` implies, and lines with neither count at 0%.
+/// Rates are bucketed to a hundredth of a percent.
+pub fn tax_summary(invoices: &[Invoice]) -> TaxSummary {
+    // Rate in hundredths of a percent -> (taxable value, tax)
+    let mut buckets: BTreeMap<i64, (f64, f64)> = BTreeMap::new();
+    let mut add = |rate: f64, taxable: f64, tax: f64| {
+        let bucket = buckets.entry((rate * 100.0).round() as i64).or_default();
+        bucket.0 += taxable;
+        bucket.1 += tax;
+    };
+    let mut summary = TaxSummary::default();
+    for invoice in invoices {
+        let grouped = match invoice.tax_breakdown.as_ref().map(breakdown_rates) {
+            Some(Ok(grouped)) => grouped,
+            Some(Err(e)) => {
+                summary.warnings.push(format!(
+                    "Invoice {}: {}, so its line items were used instead",
+                    invoice.invoice_number, e
+                ));
+                None
+            }
+            None => None,
+        };
+        match grouped {
+            Some(groups) => groups
+                .into_iter()
+                .for_each(|group| add(group.rate, group.taxable_value, group.tax_amount)),
+            None => invoice.items.iter().for_each(|item| {
+                let (rate, tax) = line_tax(item);
+                add(rate, item.line_amount(), tax)
+            }),
+        }
+    }
+    for (rate, (taxable_value, tax_amount)) in buckets {
+        summary.total_taxable_value += taxable_value;
+        summary.total_tax_amount += tax_amount;
+        summary.buckets.push(TaxBucket {
+            rate: rate as f64 / 100.0,
+            taxable_value,
+            tax_amount,
+        });
+    }
+    summary
+}
+
+// A bucket per group, or `None` for a breakdown without groups by rate, such as the flat
+// CGST/SGST shape, which doesn't say what each line was taxed at
+fn breakdown_rates(breakdown: &Value) -> Result<Option<Vec<TaxBucket>>, String> {
+    if breakdown.is_null() {
+        return Ok(None);
+    }
+    let groups = verify::tax_groups(breakdown)
+        .ok_or_else(|| format!("tax_breakdown {} is not an object", breakdown))?;
+    let grouped: Vec<_> = groups.iter().filter(|(key, _)| *key != "all").collect();
+    if grouped.is_empty() {
+        return Ok(None);
+    }
+    grouped
+        .into_iter()
+        .map(|(key, group)| match (group.rate, group.taxable) {
+            (Some(rate), Some(taxable_value)) => Ok(TaxBucket {
+                rate,
+                taxable_value,
+                tax_amount: group.tax(),
+            }),
+            (None, _) => Err(format!("tax_breakdown key '{}' is not a rate", key)),
+            (_, None) => Err(format!("tax_breakdown '{}' has no taxable amount", key)),
+        })
+        .collect::<Result<Vec<_>, _>>()
+        .map(Some)
+}
+
+// (rate, tax) for one line, preferring what the engine reported over what's implied
+fn line_tax(item: &InvoiceItem) -> (f64, f64) {
+    let amount = item.line_amount();
+    let rate = item
+        .gst_percent
+        .or_else(|| {
+            item.tax
+                .filter(|_| amount != 0.0)
+                .map(|tax| tax / amount * 100.0)
+        })
+        .unwrap_or(0.0);
+    (rate, item.tax.unwrap_or(amount * rate / 100.0))
+}
+
+/// Warns when a run's revenue missed the target by more than `tolerance`, a fraction of
+/// the target (the config's `reality_buffer`), with the settings to look at if runs keep
+/// missing the same way.
+pub fn revenue_warning(stats: &SimulationStats, tolerance: f64) -> Option<String> {
+    let convergence = convergence(stats, tolerance)?;
+    let advice = match convergence.status {
+        ConvergenceStatus::OnTarget => return None,
+        // The engine caps invoices per day, so a short run usually needs bigger invoices
+        ConvergenceStatus::Under => {
+            "If runs keep falling short, raise max_invoice_amount, lengthen the date range or widen reality_buffer"
+        }
+        ConvergenceStatus::Over => {
+            "If runs keep overshooting, lower min_invoice_amount or widen reality_buffer"
+        }
+    };
+    Some(format!(
+        "Revenue {:.2} is {:.1}% of the {:.2} target, outside the {:.0}% tolerance. {}",
+        stats.total_revenue,
+        stats.revenue_achieved_percent,
+        stats.revenue_target,
+        tolerance * 100.0,
+        advice
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn invoices() -> Vec<Invoice> {
+        let result: crate::models::SimulationResult =
+            serde_json::from_str(include_str!("../../backend/test/test_output.json")).unwrap();
+        result.invoices
+    }
+
+    #[test]
+    fn counts_each_invoice_after_a_customers_first_as_a_repeat() {
+        // 12 invoices to 11 customers
+        let stats = compute(&invoices(), 100000.0, Duration::ZERO);
+        assert_eq!(stats.distinct_customers, 11);
+        assert_eq!(stats.repeat_customer_rate, 1.0 / 12.0);
+
+        // What a repeat rate of 0 produces
+        let mut distinct = invoices();
+        for (n, invoice) in distinct.iter_mut().enumerate() {
+            invoice.customer = serde_json::json!({ "name": format!("Customer {}", n) });
+        }
+        let stats = compute(&distinct, 100000.0, Duration::ZERO);
+        assert_eq!(stats.distinct_customers, 12);
+        assert_eq!(stats.repeat_customer_rate, 0.0);
+        assert_eq!(compute(&[], 0.0, Duration::ZERO).repeat_customer_rate, 0.0);
+    }
+
+    #[test]
+    fn totals_a_customer_across_both_invoice_shapes() {
+        let mut invoices = invoices();
+        // 03575 and 03582 are both Saraswati Limited; give one the older bare-string shape
+        invoices[10].customer = serde_json::json!(" Saraswati Limited ");
+        let customers = by_customer(&invoices);
+        assert_eq!(customers.len(), 11);
+        let saraswati = &customers[0];
+        assert_eq!(saraswati.customer, "Saraswati Limited");
+        assert_eq!(saraswati.invoice_count, 2);
+        assert!((saraswati.total_revenue - (56719.65 + 36529.18)).abs() < 0.01);
+        assert!((saraswati.average_invoice_amount - saraswati.total_revenue / 2.0).abs() < 1e-9);
+        assert_eq!(
+            (
+                saraswati.first_invoice_date.as_str(),
+                saraswati.last_invoice_date.as_str()
+            ),
+            (invoices[3].date.as_str(), invoices[10].date.as_str())
+        );
+        // Largest total first
+        assert!(customers
+            .windows(2)
+            .all(|pair| pair[0].total_revenue >= pair[1].total_revenue));
+    }
+
+    #[test]
+    fn fills_a_month_without_invoices_with_zero() {
+        let mut invoices = invoices();
+        invoices.truncate(3);
+        invoices[0].date = "2024-05-31".to_string();
+        invoices[2].date = "2024-07-01".to_string();
+        // Left out rather than failing the breakdown
+        invoices[1].date = "someday".to_string();
+        let breakdown = by_month(&invoices);
+        let months: Vec<(&str, usize, f64)> = breakdown
+            .iter()
+            .map(|m| (m.month.as_str(), m.invoice_count, m.total_revenue))
+            .collect();
+        assert_eq!(
+            months,
+            [
+                ("2024-05", 1, 66563.86),
+                ("2024-06", 0, 0.0),
+                ("2024-07", 1, 13701.64),
+            ]
+        );
+        assert!(by_month(&[]).is_empty());
+    }
+
+    #[test]
+    fn classes_revenue_against_the_target() {
+        let stats = |revenue: f64| {
+            let mut stats = compute(&[], 100000.0, Duration::ZERO);
+            stats.total_revenue = revenue;
+            stats.revenue_achieved_percent = revenue / 1000.0;
+            stats
+        };
+        let status = |revenue| convergence(&stats(revenue), 0.1).unwrap().status;
+        assert_eq!(status(100000.0), ConvergenceStatus::OnTarget);
+        // The tolerance itself still counts as on target
+        assert_eq!(status(90000.0), ConvergenceStatus::OnTarget);
+        assert_eq!(status(89000.0), ConvergenceStatus::Under);
+        assert_eq!(status(125000.0), ConvergenceStatus::Over);
+
+        let under = convergence(&stats(80000.0), 0.1).unwrap();
+        assert_eq!(under.delta, -20000.0);
+        assert!((under.delta_percent + 20.0).abs() < 1e-9);
+        assert!(convergence(&compute(&[], 0.0, Duration::ZERO), 0.1).is_none());
+    }
+
+    #[test]
+    fn totals_tax_per_rate() {
+        let invoices: Vec<Invoice> = serde_json::from_value(serde_json::json!([
+            {
+                "invoice_number": "INV-1",
+                "date": "2024-07-11",
+                "customer": "Acme",
+                "items": [
+                    {"name": "Rice", "quantity": 1, "rate": 100.0, "amount": 100.0, "gst_percent": 5},
+                    {"name": "Desk", "quantity": 1, "rate": 200.0, "amount": 200.0, "gst_percent": 18},
+                ],
+                "total": 341.0,
+            },
+            {
+                "invoice_number": "INV-2",
+                "date": "2024-07-12",
+                "customer": "Acme",
+                "items": [{"name": "Ledger", "quantity": 1, "rate": 50.0, "amount": 50.0, "tax": 6.0}],
+                "total": 56.0,
+            },
+            {
+                "invoice_number": "INV-3",
+                "date": "2024-07-13",
+                "customer": "Acme",
+                "items": [{"name": "Ignored", "quantity": 1, "rate": 1.0, "amount": 1.0, "gst_percent": 28}],
+                "tax_breakdown": {"12%": {"taxable": 300.0, "cgst": 18.0, "sgst": 18.0}},
+                "total": 336.0,
+            },
+        ]))
+        .unwrap();
+        let summary = tax_summary(&invoices);
+        let buckets: Vec<(f64, f64, f64)> = summary
+            .buckets
+            .iter()
+            .map(|b| (b.rate, b.taxable_value, b.tax_amount))
+            .collect();
+        // INV-2's 12% is implied by its tax, and INV-3's breakdown replaces its lines
+        assert_eq!(
+            buckets,
+            [(5.0, 100.0, 5.0), (12.0, 350.0, 42.0), (18.0, 200.0, 36.0)]
+        );
+        assert_eq!(summary.total_taxable_value, 650.0);
+        assert_eq!(summary.total_tax_amount, 83.0);
+        assert!(summary.warnings.is_empty());
+    }
+}
@@ -0,0 +1,26 @@
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+static NEXT_ID: AtomicUsize = AtomicUsize::new(0);
+
+/// A temp file path that is removed when dropped, so every early return cleans up.
+pub struct TempFile(PathBuf);
+
+impl TempFile {
+    /// A path in the system temp dir that no other run, in this process or another, uses.
+    pub fn unique(prefix: &str, extension: &str) -> Self {
+        let id = NEXT_ID.fetch_add(1, Ordering::Relaxed);
+        let name = format!("{}_{}_{}.{}", prefix, std::process::id(), id, extension);
+        TempFile(std::env::temp_dir().join(name))
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.0
+    }
+}
+
+impl Drop for TempFile {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_file(&self.0);
+    }
+}
@@ -0,0 +1,611 @@
+use crate::currency;
+use crate::distribution::{self, DISTRIBUTION_MODES};
+use crate::engine;
+use crate::export::formats::OUTPUT_FORMATS;
+use crate::models::{CatalogItem, Invoice, SimulationConfig};
+use crate::rounding::RoundingMode;
+use chrono::{Datelike, Duration, NaiveDate, Weekday};
+
+// Kept in step with `_calculate_item_distribution` in engine.py: each line starts at one to
+// three units, may gain up to 20 more, and has its price varied by up to 5% either way
+const MAX_LINE_QUANTITY: f64 = 23.0;
+const PRICE_VARIATION: f64 = 0.05;
+
+// Future-dated invoice numbers named in a report before the rest are just counted
+const MAX_FUTURE_LISTED: usize = 10;
+
+// 'selected' is what older frontends send for 'include'
+const ITEM_FILTER_MODES: &[&str] = &["all", "include", "exclude", "selected"];
+
+// 'auto' and 'target_revenue' both size the run from revenue_target; 'manual' uses
+// manual_invoice_count
+const INVOICE_COUNT_MODES: &[&str] = &["auto", "manual", "target_revenue"];
+
+/// The customer name styles `engine.py` accepts for `name_type`, mirrored from its
+/// config, each with a description for the UI.
+pub const NAME_TYPES: &[(&str, &str)] = &[
+    (
+        "malabar_muslim",
+        "Muslim personal names common in Malabar, Kerala",
+    ),
+    (
+        "south_indian_generic",
+        "Personal names from across South India",
+    ),
+    (
+        "indian_company",
+        "Indian trading and manufacturing businesses",
+    ),
+    ("bahrain_name", "Arabic personal names common in Bahrain"),
+    (
+        "bahrain_company",
+        "Bahraini trading and services businesses",
+    ),
+    ("it_company", "Software and IT services firms"),
+];
+
+/// Runs expected to generate more invoices than this need `confirm_large_run`, since the
+/// usual cause is a mistyped revenue target or date range and the run would take hours.
+pub const LARGE_RUN_INVOICES: u64 = 1_000_000;
+
+/// Used when a config leaves `reality_buffer` out: in manual invoice-count mode each
+/// invoice may then land up to 10% either side of an even share of the target.
+pub const DEFAULT_REALITY_BUFFER: f64 = 0.1;
+
+/// Checks the invariants a config must satisfy before the engine is launched.
+pub fn validate(config: &SimulationConfig) -> Result<(), String> {
+    if config.revenue_target <= 0.0 {
+        return Err("Revenue target must be positive".into());
+    }
+    if config.min_invoice_amount <= 0.0 || config.max_invoice_amount <= 0.0 {
+        return Err("Invoice amounts must be positive".into());
+    }
+    if config.min_items <= 0 || config.max_items <= 0 {
+        return Err("Item counts must be positive".into());
+    }
+    if config.min_invoice_amount > config.max_invoice_amount {
+        return Err("Minimum invoice amount cannot exceed maximum".into());
+    }
+    if config.min_items > config.max_items {
+        return Err("Minimum items cannot exceed maximum".into());
+    }
+    if let Some(buffer) = config.reality_buffer {
+        // Also rejects NaN
+        if !(0.0..=1.0).contains(&buffer) {
+            return Err(format!(
+                "reality_buffer must be between 0.0 and 1.0 (got {}); 0.1 allows invoices 10% either side of an even split",
+                buffer
+            ));
+        }
+    }
+    if let Some(rate) = config.customer_repeat_rate {
+        if !(0.0..=1.0).contains(&rate) {
+            return Err(format!(
+                "customer_repeat_rate must be between 0.0 and 1.0 (got {}); 0.3 sends 30% of invoices to returning customers",
+                rate
+            ));
+        }
+    }
+    if config.timeout_secs == Some(0) {
+        return Err("Timeout must be at least one second".into());
+    }
+    if let Some(code) = config.currency.as_deref() {
+        if !currency::is_valid_code(code) {
+            return Err(format!(
+                "currency '{}' is not an ISO 4217 code like 'INR' or 'USD'",
+                code
+            ));
+        }
+    }
+    if let Some(code) = config.base_currency.as_deref() {
+        if !currency::is_valid_code(code) {
+            return Err(format!(
+                "base_currency '{}' is not an ISO 4217 code like 'INR' or 'USD'",
+                code
+            ));
+        }
+        // The engine's amounts come out in the base currency, which is what `currency` labels
+        if let Some(label) = config.currency.as_deref().filter(|label| *label != code) {
+            return Err(format!(
+                "base_currency '{}' differs from currency '{}', but invoice amounts are in the base currency",
+                code, label
+            ));
+        }
+    }
+    if let Some((code, rate)) = config
+        .exchange_rates
+        .iter()
+        .find(|(_, rate)| !(rate.is_finite() && **rate > 0.0))
+    {
+        return Err(format!(
+            "exchange_rates['{}'] must be a positive number (got {})",
+            code, rate
+        ));
+    }
+    if !NAME_TYPES.iter().any(|(name, _)| *name == config.name_type) {
+        return Err(format!(
+            "Unknown name_type '{}' (expected one of: {})",
+            config.name_type,
+            NAME_TYPES
+                .iter()
+                .map(|(name, _)| *name)
+                .collect::<Vec<_>>()
+                .join(", ")
+        ));
+    }
+    RoundingMode::parse(config.rounding_mode.as_deref())?;
+    engine::check_extra_args(config.extra_engine_args.as_deref().unwrap_or_default())?;
+    validate_output_formats(&config.output_formats)?;
+    validate_invoice_count(config)?;
+    validate_item_filter(config)?;
+    validate_dates(config)
+}
+
+pub fn validate_output_formats(formats: &[String]) -> Result<(), String> {
+    match formats
+        .iter()
+        .find(|format| !OUTPUT_FORMATS.contains(&format.as_str()))
+    {
+        Some(format) => Err(format!(
+            "Unknown output format '{}' (supported: {})",
+            format,
+            OUTPUT_FORMATS.join(", ")
+        )),
+        None => Ok(()),
+    }
+}
+
+/// Rejects a config whose `selected_items` name anything missing from `catalog`.
+pub fn validate_selection(
+    config: &SimulationConfig,
+    catalog: &[CatalogItem],
+) -> Result<(), String> {
+    let unknown = unknown_skus(config, catalog);
+    if unknown.is_empty() {
+        Ok(())
+    } else {
+        Err(format!(
+            "selected_items not found in catalog: {}",
+            unknown.join(", ")
+        ))
+    }
+}
+
+/// Entries of `selected_items` that match no catalog item. Like the engine, an entry
+/// may name an item by SKU or by name.
+pub fn unknown_skus(config: &SimulationConfig, catalog: &[CatalogItem]) -> Vec<String> {
+    if config.item_filter_mode == "all" {
+        return Vec::new();
+    }
+    config
+        .selected_items
+        .iter()
+        .filter(|selected| {
+            !catalog
+                .iter()
+                .any(|item| &item.sku == *selected || &item.name == *selected)
+        })
+        .cloned()
+        .collect()
+}
+
+/// Rejects a run that would take more than `LARGE_RUN_INVOICES` invoices to reach its
+/// revenue target unless `confirm_large_run` is set, in which case a warning with the
+/// estimate is returned. The count isn't capped at five a day like the engine's, since
+/// a mistyped target over a normal range would otherwise pass and end in one enormous
+/// adjustment invoice. Expects a config that already passed `validate`.
+pub fn check_run_size(config: &SimulationConfig) -> Result<Option<String>, String> {
+    let needed = distribution::needed(config);
+    if needed <= LARGE_RUN_INVOICES {
+        return Ok(None);
+    }
+    if !config.confirm_large_run {
+        return Err(format!(
+            "This run would need about {} invoices to reach revenue_target, more than the {} allowed without confirmation; check revenue_target, the invoice amounts and manual_invoice_count, or set confirm_large_run to run it anyway",
+            needed, LARGE_RUN_INVOICES
+        ));
+    }
+    Ok(Some(format!(
+        "Large run confirmed: about {} invoices are needed to reach revenue_target",
+        needed
+    )))
+}
+
+/// The catalog items `item_filter_mode` and `selected_items` leave for the engine to pick
+/// from, matched by SKU or name as the engine does.
+pub fn eligible_items<'a>(
+    config: &SimulationConfig,
+    catalog: &'a [CatalogItem],
+) -> Vec<&'a CatalogItem> {
+    let selected = |item: &CatalogItem| {
+        config
+            .selected_items
+            .iter()
+            .any(|entry| *entry == item.sku || *entry == item.name)
+    };
+    // The engine ignores a filter with nothing selected
+    let keep_selected = match config.item_filter_mode.as_str() {
+        _ if config.selected_items.is_empty() => None,
+        "include" | "selected" => Some(true),
+        "exclude" => Some(false),
+        _ => None,
+    };
+    catalog
+        .iter()
+        .filter(|item| keep_selected.is_none_or(|keep| selected(item) == keep))
+        .collect()
+}
+
+/// Checks `max_items` against the number of eligible catalog items. With
+/// `clamp_max_items` set, a `max_items` over the limit is lowered to it (and `min_items`
+/// with it) and a warning describing the change is returned instead of an error.
+pub fn fit_max_items(
+    config: &mut SimulationConfig,
+    catalog: &[CatalogItem],
+) -> Result<Option<String>, String> {
+    let eligible = eligible_items(config, catalog).len();
+    if eligible == 0 {
+        return Err(format!(
+            "item_filter_mode '{}' leaves no catalog items to invoice",
+            config.item_filter_mode
+        ));
+    }
+    let limit = i32::try_from(eligible).unwrap_or(i32::MAX);
+    if config.max_items <= limit {
+        return Ok(None);
+    }
+    if !config.clamp_max_items {
+        return Err(format!(
+            "max_items is {} but only {} catalog items are eligible under item_filter_mode '{}'",
+            config.max_items, eligible, config.item_filter_mode
+        ));
+    }
+    let requested = config.max_items;
+    config.max_items = limit;
+    config.min_items = config.min_items.min(limit);
+    Ok(Some(format!(
+        "max_items lowered from {} to {}, the number of eligible catalog items",
+        requested, eligible
+    )))
+}
+
+/// Rejects a config whose invoice amount range no invoice can land in: even the
+/// `min_items` cheapest eligible items, one of each at the engine's lowest price, come to
+/// more than `max_invoice_amount`, or the `max_items` priciest, at the most units and
+/// highest price the engine uses, come to less than `min_invoice_amount`. The engine
+/// would otherwise keep drawing invoices outside the range.
+pub fn check_amount_range(
+    config: &SimulationConfig,
+    catalog: &[CatalogItem],
+) -> Result<(), String> {
+    let mut prices: Vec<f64> = eligible_items(config, catalog)
+        .iter()
+        .map(|item| item.price)
+        .collect();
+    if prices.is_empty() {
+        return Ok(());
+    }
+    prices.sort_by(f64::total_cmp);
+    // Each item once, best first, then repeats once the eligible items run out, which at
+    // best are the first item again
+    let bound = |ordered: &[f64], count: i32| {
+        let count = count.max(0) as usize;
+        let distinct: f64 = ordered.iter().take(count).sum();
+        distinct + ordered[0] * count.saturating_sub(ordered.len()) as f64
+    };
+    let cheapest = bound(&prices, config.min_items) * (1.0 - PRICE_VARIATION);
+    prices.reverse();
+    let priciest = bound(&prices, config.max_items) * (1.0 + PRICE_VARIATION) * MAX_LINE_QUANTITY;
+    if cheapest > config.max_invoice_amount {
+        return Err(format!(
+            "No invoice can stay under max_invoice_amount {:.2}: the {} cheapest eligible items cost at least {:.2} together. Raise max_invoice_amount or lower min_items",
+            config.max_invoice_amount, config.min_items, cheapest
+        ));
+    }
+    if priciest < config.min_invoice_amount {
+        return Err(format!(
+            "No invoice can reach min_invoice_amount {:.2}: {} of the priciest eligible items come to at most {:.2}. Lower min_invoice_amount or raise max_items",
+            config.min_invoice_amount, config.max_items, priciest
+        ));
+    }
+    Ok(())
+}
+
+/// Rejects catalog items whose tax regime is ambiguous: a non-zero GST and VAT rate at
+/// once, or no rate at all when `invoice_type` is taxed. An explicit rate of zero counts
+/// as set.
+pub fn validate_catalog_taxes(invoice_type: &str, catalog: &[CatalogItem]) -> Result<(), String> {
+    let nonzero = |rate: Option<f64>| rate.is_some_and(|rate| rate != 0.0);
+    let both: Vec<&str> = catalog
+        .iter()
+        .filter(|item| nonzero(item.gst_percent) && nonzero(item.vat_percent))
+        .map(|item| item.sku.as_str())
+        .collect();
+    let taxed = ["gst", "vat"]
+        .iter()
+        .any(|t| t.eq_ignore_ascii_case(invoice_type));
+    let neither: Vec<&str> = catalog
+        .iter()
+        .filter(|item| taxed && item.gst_percent.is_none() && item.vat_percent.is_none())
+        .map(|item| item.sku.as_str())
+        .collect();
+
+    let mut problems = Vec::new();
+    if !both.is_empty() {
+        problems.push(format!(
+            "catalog items with both a GST and a VAT rate: {}",
+            both.join(", ")
+        ));
+    }
+    if !neither.is_empty() {
+        problems.push(format!(
+            "catalog items with no tax rate for a '{}' invoice: {}",
+            invoice_type,
+            neither.join(", ")
+        ));
+    }
+    if problems.is_empty() {
+        Ok(())
+    } else {
+        Err(problems.join("; "))
+    }
+}
+
+fn validate_invoice_count(config: &SimulationConfig) -> Result<(), String> {
+    let mode = config.invoice_count_mode.as_deref().unwrap_or("auto");
+    if !INVOICE_COUNT_MODES.contains(&mode) {
+        return Err(format!(
+            "Unknown invoice_count_mode '{}' (expected 'auto', 'manual' or 'target_revenue')",
+            mode
+        ));
+    }
+    match config.manual_invoice_count {
+        None if mode == "manual" => {
+            Err("invoice_count_mode 'manual' needs manual_invoice_count".to_string())
+        }
+        Some(count) if mode == "manual" && count <= 0 => Err(format!(
+            "manual_invoice_count must be positive (got {})",
+            count
+        )),
+        _ => Ok(()),
+    }
+}
+
+fn validate_item_filter(config: &SimulationConfig) -> Result<(), String> {
+    let mode = config.item_filter_mode.as_str();
+    if !ITEM_FILTER_MODES.contains(&mode) {
+        return Err(format!(
+            "Unknown item_filter_mode '{}' (expected 'all', 'include' or 'exclude')",
+            mode
+        ));
+    }
+    if mode != "all" && config.selected_items.is_empty() {
+        return Err(format!(
+            "item_filter_mode '{}' needs at least one entry in selected_items",
+            mode
+        ));
+    }
+    Ok(())
+}
+
+/// Cross-field combinations that are legal but probably not what the user meant.
+pub fn warnings(config: &SimulationConfig) -> Vec<String> {
+    let mut warnings = Vec::new();
+    let count_mode = config.invoice_count_mode.as_deref().unwrap_or("auto");
+    if config.manual_invoice_count.is_some() && count_mode != "manual" {
+        warnings.push(format!(
+            "manual_invoice_count set but invoice_count_mode is not 'manual' (got '{}')",
+            count_mode
+        ));
+    }
+    if config.item_filter_mode == "all" && !config.selected_items.is_empty() {
+        warnings.push("selected_items are ignored because item_filter_mode is 'all'".to_string());
+    }
+    warnings
+}
+
+pub fn parse_date(field: &str, value: &str) -> Result<NaiveDate, String> {
+    NaiveDate::parse_from_str(value, "%Y-%m-%d")
+        .map_err(|_| format!("{} '{}' is not a valid YYYY-MM-DD date", field, value))
+}
+
+/// Describes the invoices dated after `today`, or after `end_date` when that's earlier,
+/// or `None` when there are none. Invoices whose date doesn't parse aren't counted.
+pub fn future_dates(
+    invoices: &[Invoice],
+    end_date: Option<NaiveDate>,
+    today: NaiveDate,
+) -> Option<String> {
+    let (limit, what) = match end_date {
+        Some(end) if end < today => (end, "end_date"),
+        _ => (today, "today"),
+    };
+    let late: Vec<&str> = invoices
+        .iter()
+        .filter(|invoice| {
+            NaiveDate::parse_from_str(&invoice.date, "%Y-%m-%d").is_ok_and(|date| date > limit)
+        })
+        .map(|invoice| invoice.invoice_number.as_str())
+        .collect();
+    if late.is_empty() {
+        return None;
+    }
+    let mut listed: Vec<String> = late
+        .iter()
+        .take(MAX_FUTURE_LISTED)
+        .map(|number| number.to_string())
+        .collect();
+    if late.len() > MAX_FUTURE_LISTED {
+        listed.push(format!("{} more", late.len() - MAX_FUTURE_LISTED));
+    }
+    Some(format!(
+        "Invoices dated after {} ({}): {}",
+        what,
+        limit,
+        listed.join(", ")
+    ))
+}
+
+// Distribution modes that shape invoices over the range and so need more than one day
+fn spreads_across_days(mode: &str) -> bool {
+    matches!(mode, "front_loaded" | "back_loaded")
+}
+
+fn validate_dates(config: &SimulationConfig) -> Result<(), String> {
+    let start = parse_date("start_date", &config.start_date)?;
+    let end = parse_date("end_date", &config.end_date)?;
+    if start > end {
+        return Err(format!(
+            "start_date '{}' is after end_date '{}'",
+            config.start_date, config.end_date
+        ));
+    }
+    if let Some(requested) = config.distribution_mode.as_deref() {
+        let mode = distribution::canonical(requested).ok_or_else(|| {
+            format!(
+                "Unknown distribution_mode '{}' (expected one of: {})",
+                requested,
+                DISTRIBUTION_MODES.join(", ")
+            )
+        })?;
+        if start == end && spreads_across_days(mode) {
+            return Err(format!(
+                "distribution_mode '{}' needs a date range spanning more than one day",
+                requested
+            ));
+        }
+        let weekend = |day: NaiveDate| matches!(day.weekday(), Weekday::Sat | Weekday::Sun);
+        let mut days = (0..=(end - start).num_days()).map(|offset| start + Duration::days(offset));
+        if mode == "weekday_only" && days.all(weekend) {
+            return Err(format!(
+                "distribution_mode '{}' needs a date range with at least one weekday",
+                requested
+            ));
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config() -> SimulationConfig {
+        serde_json::from_str(include_str!("../../backend/test/test_config.json")).unwrap()
+    }
+
+    #[test]
+    fn huge_revenue_target_needs_confirmation() {
+        let mut config = config();
+        config.revenue_target = 1e15;
+        validate(&config).unwrap();
+        // Capped at five a day, the estimate alone stays far under the threshold
+        assert!(distribution::estimate(&config).unwrap().expected < 200);
+
+        let error = check_run_size(&config).unwrap_err();
+        assert!(error.contains("about 181818181819 invoices"), "{}", error);
+        assert!(error.contains("confirm_large_run"), "{}", error);
+
+        config.confirm_large_run = true;
+        let warning = check_run_size(&config).unwrap().unwrap();
+        assert!(warning.starts_with("Large run confirmed"), "{}", warning);
+    }
+
+    #[test]
+    fn ordinary_runs_pass_the_size_check() {
+        assert_eq!(check_run_size(&config()).unwrap(), None);
+        let mut manual = config();
+        manual.invoice_count_mode = Some("manual".to_string());
+        manual.manual_invoice_count = Some(LARGE_RUN_INVOICES as i32 + 1);
+        assert!(check_run_size(&manual).is_err());
+    }
+
+    #[test]
+    fn rejects_reversed_and_garbage_dates() {
+        let mut reversed = config();
+        reversed.start_date = "2023-02-01".to_string();
+        assert_eq!(
+            validate(&reversed).unwrap_err(),
+            "start_date '2023-02-01' is after end_date '2023-01-31'"
+        );
+        for garbage in ["yesterday", "2023-02-30", "01/01/2023", ""] {
+            let mut config = config();
+            config.end_date = garbage.to_string();
+            assert_eq!(
+                validate(&config).unwrap_err(),
+                format!("end_date '{}' is not a valid YYYY-MM-DD date", garbage)
+            );
+        }
+        let mut one_day = config();
+        one_day.end_date = one_day.start_date.clone();
+        validate(&one_day).unwrap();
+    }
+
+    #[test]
+    fn flags_catalog_items_with_both_or_neither_tax_rate() {
+        let catalog: Vec<CatalogItem> = serde_json::from_str(include_str!(
+            "../../backend/test/test_catalog_tax_mixed.json"
+        ))
+        .unwrap();
+        // GST001, VAT001 and ZERO001, whose VAT rate of zero doesn't count, are valid
+        assert_eq!(
+            validate_catalog_taxes("gst", &catalog).unwrap_err(),
+            "catalog items with both a GST and a VAT rate: BOTH001; \
+             catalog items with no tax rate for a 'gst' invoice: NONE001"
+        );
+        // An untaxed invoice doesn't need a rate
+        assert_eq!(
+            validate_catalog_taxes("plain", &catalog).unwrap_err(),
+            "catalog items with both a GST and a VAT rate: BOTH001"
+        );
+        let valid: Vec<CatalogItem> = catalog
+            .into_iter()
+            .filter(|item| ["GST001", "VAT001", "ZERO001"].contains(&item.sku.as_str()))
+            .collect();
+        assert_eq!(validate_catalog_taxes("vat", &valid), Ok(()));
+    }
+
+    #[test]
+    fn reality_buffer_must_be_a_fraction() {
+        for buffer in [0.0, 0.1, 1.0] {
+            let mut config = config();
+            config.reality_buffer = Some(buffer);
+            validate(&config).unwrap();
+        }
+        for buffer in [-0.01, 1.01, 10.0, f64::NAN] {
+            let mut config = config();
+            config.reality_buffer = Some(buffer);
+            let error = validate(&config).unwrap_err();
+            assert!(
+                error.starts_with(&format!(
+                    "reality_buffer must be between 0.0 and 1.0 (got {})",
+                    buffer
+                )),
+                "{}",
+                error
+            );
+        }
+    }
+
+    #[test]
+    fn customer_repeat_rate_must_be_a_fraction() {
+        for rate in [0.0, 0.3, 1.0] {
+            let mut config = config();
+            config.customer_repeat_rate = Some(rate);
+            validate(&config).unwrap();
+        }
+        for rate in [-0.1, 30.0, f64::NAN] {
+            let mut config = config();
+            config.customer_repeat_rate = Some(rate);
+            let error = validate(&config).unwrap_err();
+            assert!(
+                error.starts_with(&format!(
+                    "customer_repeat_rate must be between 0.0 and 1.0 (got {})",
+                    rate
+                )),
+                "{}",
+                error
+            );
+        }
+    }
+}
@@ -0,0 +1,582 @@
+use crate::models::{CatalogItem, Invoice};
+use crate::rounding::RoundingMode;
+use serde_json::Value;
+use std::collections::{BTreeMap, HashMap};
+
+// Allowed difference before a total is reported, i.e. one cent of rounding
+const EPSILON: f64 = 0.01;
+
+// Each rounded amount can be off by up to half a cent
+const HALF_CENT: f64 = 0.005;
+// Room for float noise when comparing against HALF_CENT multiples
+const NOISE: f64 = 1e-9;
+
+// Colliding numbers named in a duplicate report before the rest are just counted
+const MAX_DUPLICATES_LISTED: usize = 10;
+
+/// Flags invoices whose line amounts, each rounded on its own, sum to something other
+/// than the subtotal by more than half a cent but no more than that rounding explains.
+/// Bigger gaps are real mismatches and left to `verify_totals`.
+///
+/// With `fix` set the largest line absorbs the difference (a penny-rounding pass),
+/// rounded with `rounding`, and the warning records the adjustment instead.
+pub fn rounding_drift(invoices: &mut [Invoice], fix: bool, rounding: RoundingMode) -> Vec<String> {
+    let mut warnings = Vec::new();
+    for invoice in invoices.iter_mut() {
+        if invoice.subtotal == 0.0 || invoice.items.is_empty() {
+            continue;
+        }
+        let sum: f64 = invoice.items.iter().map(|item| item.line_amount()).sum();
+        let drift = invoice.subtotal - sum;
+        // Every line plus the subtotal itself may have been rounded
+        let explainable = HALF_CENT * (invoice.items.len() + 1) as f64;
+        if drift.abs() <= HALF_CENT + NOISE || drift.abs() > explainable + NOISE {
+            continue;
+        }
+
+        if !fix {
+            warnings.push(format!(
+                "Invoice {}: line items sum to {:.2}, {:.2} off subtotal {:.2} from rounding",
+                invoice.invoice_number,
+                sum,
+                drift.abs(),
+                invoice.subtotal
+            ));
+            continue;
+        }
+
+        // Reversed so the first of several equally large lines is the one adjusted
+        let item = invoice
+            .items
+            .iter_mut()
+            .rev()
+            .max_by(|a, b| a.line_amount().total_cmp(&b.line_amount()))
+            .expect("checked non-empty above");
+        item.amount = rounding.round_cents(item.line_amount() + drift);
+        if let Some(total) = item.total.as_mut() {
+            *total = rounding.round_cents(*total + drift);
+        }
+        warnings.push(format!(
+            "Invoice {}: adjusted '{}' by {:+.2} so line items sum to subtotal {:.2}",
+            invoice.invoice_number, item.name, drift, invoice.subtotal
+        ));
+    }
+    warnings
+}
+
+/// Recomputes each invoice's subtotal from its line items and checks the total against
+/// subtotal plus line-level tax, returning a warning per mismatch.
+pub fn verify_totals(invoices: &[Invoice]) -> Vec<String> {
+    let mut warnings = Vec::new();
+    for invoice in invoices {
+        let computed: f64 = invoice.items.iter().map(|item| item.line_amount()).sum();
+        // The older shape has no subtotal at all, so the recomputed one stands in
+        let subtotal = if invoice.subtotal == 0.0 {
+            computed
+        } else {
+            invoice.subtotal
+        };
+        if (subtotal - computed).abs() > EPSILON {
+            warnings.push(format!(
+                "Invoice {}: subtotal {:.2} does not match sum of line items {:.2}",
+                invoice.invoice_number, subtotal, computed
+            ));
+        }
+
+        let line_taxes: Vec<f64> = invoice.items.iter().filter_map(|item| item.tax).collect();
+        if !line_taxes.is_empty() {
+            let tax: f64 = line_taxes.iter().sum();
+            if (invoice.total - (subtotal + tax)).abs() > EPSILON {
+                warnings.push(format!(
+                    "Invoice {}: total {:.2} does not match subtotal {:.2} plus tax {:.2}",
+                    invoice.invoice_number, invoice.total, subtotal, tax
+                ));
+            }
+        } else if is_untaxed(&invoice.invoice_type) {
+            if (invoice.total - subtotal).abs() > EPSILON {
+                warnings.push(format!(
+                    "Invoice {}: total {:.2} does not match untaxed subtotal {:.2}",
+                    invoice.invoice_number, invoice.total, subtotal
+                ));
+            }
+        } else if invoice.total + EPSILON < subtotal {
+            // Tax is only itemised at invoice level here, but it can't be negative
+            warnings.push(format!(
+                "Invoice {}: total {:.2} is less than subtotal {:.2}",
+                invoice.invoice_number, invoice.total, subtotal
+            ));
+        }
+    }
+    warnings
+}
+
+/// One group of an invoice's `tax_breakdown`: the taxes charged at one rate, with the
+/// amount they were charged on when the engine reports it.
+#[derive(Debug, Default, PartialEq)]
+pub struct TaxGroup {
+    pub rate: Option<f64>,
+    pub taxable: Option<f64>,
+    // Tax head, upper-cased (CGST, SGST, IGST, VAT), -> amount
+    pub taxes: BTreeMap<String, f64>,
+}
+
+impl TaxGroup {
+    pub fn tax(&self) -> f64 {
+        self.taxes.values().sum()
+    }
+}
+
+/// Reads a `tax_breakdown` into groups keyed by rate. The grouped shape
+/// (`{"18%": {"taxable": 100.0, "cgst": 9.0, "sgst": 9.0}}`) gives one group per rate; the
+/// flat shape (`{"CGST": 9.0, "CGST_rate": 9}`) becomes a single group keyed "all", with
+/// no taxable amount since it only covers the whole subtotal. Returns `None` for anything
+/// that isn't an object.
+pub fn tax_groups(breakdown: &Value) -> Option<BTreeMap<String, TaxGroup>> {
+    let fields = breakdown.as_object()?;
+    let mut groups = BTreeMap::new();
+    let mut flat = TaxGroup::default();
+    for (key, value) in fields {
+        match value.as_object() {
+            Some(group) => {
+                let mut parsed = TaxGroup {
+                    rate: key.trim_end_matches('%').trim().parse().ok(),
+                    ..TaxGroup::default()
+                };
+                for (key, value) in group {
+                    add_field(&mut parsed, key, value);
+                }
+                groups.insert(key.clone(), parsed);
+            }
+            None => add_field(&mut flat, key, value),
+        }
+    }
+    if !flat.taxes.is_empty() || flat.taxable.is_some() {
+        groups.insert("all".to_string(), flat);
+    }
+    Some(groups)
+}
+
+// Rate fields such as `CGST_rate` describe the tax next to them and aren't amounts
+fn add_field(group: &mut TaxGroup, key: &str, value: &Value) {
+    let Some(amount) = value.as_f64() else {
+        return;
+    };
+    let key = key.to_uppercase();
+    if key == "TAXABLE" {
+        group.taxable = Some(amount);
+    } else if !key.ends_with("_RATE") {
+        *group.taxes.entry(key).or_insert(0.0) += amount;
+    }
+}
+
+/// Checks each invoice's `tax_breakdown` against its line items and total: grouped
+/// taxable amounts must add up to the line items, each group's tax must match its rate,
+/// and subtotal plus every tax must equal the total. Each reported tax was rounded on its
+/// own, so the allowance grows with the number of them.
+pub fn verify_tax_breakdown(invoices: &[Invoice]) -> Vec<String> {
+    let mut warnings = Vec::new();
+    for invoice in invoices {
+        let Some(groups) = invoice.tax_breakdown.as_ref().and_then(tax_groups) else {
+            continue;
+        };
+        if groups.is_empty() {
+            continue;
+        }
+        let lines: f64 = invoice.items.iter().map(|item| item.line_amount()).sum();
+        let subtotal = if invoice.subtotal == 0.0 {
+            lines
+        } else {
+            invoice.subtotal
+        };
+
+        let taxable: Vec<f64> = groups.values().filter_map(|g| g.taxable).collect();
+        if taxable.len() == groups.len() {
+            let sum: f64 = taxable.iter().sum();
+            if (sum - lines).abs() > allowance(taxable.len() + invoice.items.len()) {
+                warnings.push(format!(
+                    "Invoice {}: taxable amounts in tax_breakdown sum to {:.2}, but line items sum to {:.2}",
+                    invoice.invoice_number, sum, lines
+                ));
+            }
+        }
+        for (key, group) in &groups {
+            let (Some(rate), Some(taxable)) = (group.rate, group.taxable) else {
+                continue;
+            };
+            let expected = taxable * rate / 100.0;
+            if (group.tax() - expected).abs() > allowance(group.taxes.len()) {
+                warnings.push(format!(
+                    "Invoice {}: {} tax in tax_breakdown is {:.2}, expected {:.2} on taxable {:.2}",
+                    invoice.invoice_number,
+                    key,
+                    group.tax(),
+                    expected,
+                    taxable
+                ));
+            }
+        }
+
+        let tax: f64 = groups.values().map(TaxGroup::tax).sum();
+        let components: usize = groups.values().map(|g| g.taxes.len()).sum();
+        if (invoice.total - (subtotal + tax)).abs() > allowance(components + 1) {
+            warnings.push(format!(
+                "Invoice {}: total {:.2} does not match subtotal {:.2} plus tax_breakdown taxes {:.2}",
+                invoice.invoice_number, invoice.total, subtotal, tax
+            ));
+        }
+    }
+    warnings
+}
+
+/// Checks the tax on each line of a GST invoice against the GST rate of the catalog item
+/// it was drawn from, warning when it's more than a cent off `amount * gst_percent / 100`
+/// at the catalog rate. A line's tax is the `tax` it reports or, as the engine writes GST
+/// lines, its amount at the line's own `gst_percent`. Lines are matched to the catalog by
+/// SKU, or by name when the SKU is left out; lines with neither a tax nor a rate, or whose
+/// catalog item has no GST rate, aren't checked.
+pub fn catalog_tax(invoices: &[Invoice], catalog: &[CatalogItem]) -> Vec<String> {
+    let by_sku: HashMap<&str, &CatalogItem> = catalog
+        .iter()
+        .map(|item| (item.sku.as_str(), item))
+        .collect();
+    let by_name: HashMap<&str, &CatalogItem> = catalog
+        .iter()
+        .map(|item| (item.name.as_str(), item))
+        .collect();
+    let mut warnings = Vec::new();
+    for invoice in invoices {
+        if !invoice.invoice_type.eq_ignore_ascii_case("gst") {
+            continue;
+        }
+        for item in &invoice.items {
+            let implied = item
+                .gst_percent
+                .map(|rate| item.line_amount() * rate / 100.0);
+            let Some(tax) = item.tax.or(implied) else {
+                continue;
+            };
+            let matched = match &item.sku {
+                Some(sku) => by_sku.get(sku.as_str()),
+                None => by_name.get(item.name.as_str()),
+            };
+            let Some((sku, rate)) = matched.and_then(|c| Some((&c.sku, c.gst_percent?))) else {
+                continue;
+            };
+            let expected = item.line_amount() * rate / 100.0;
+            if (tax - expected).abs() > EPSILON {
+                warnings.push(format!(
+                    "Invoice {}: '{}' has tax {:.2}, but its catalog GST rate of {}% on {:.2} is {:.2}",
+                    invoice.invoice_number,
+                    sku,
+                    tax,
+                    rate,
+                    item.line_amount(),
+                    expected
+                ));
+            }
+        }
+    }
+    warnings
+}
+
+// Half a cent per rounded amount, and never less than the one-cent EPSILON
+fn allowance(rounded: usize) -> f64 {
+    (HALF_CENT * rounded as f64).max(EPSILON) + NOISE
+}
+
+fn is_untaxed(invoice_type: &str) -> bool {
+    invoice_type.is_empty() || invoice_type.eq_ignore_ascii_case("plain")
+}
+
+/// Reports line items whose legacy duplicate fields disagreed during deserialization.
+pub fn field_conflicts(invoices: &[Invoice]) -> Vec<String> {
+    invoices
+        .iter()
+        .flat_map(|invoice| {
+            invoice.items.iter().filter_map(move |item| {
+                item.conflict.as_ref().map(|conflict| {
+                    format!("Invoice {}: line item {}", invoice.invoice_number, conflict)
+                })
+            })
+        })
+        .collect()
+}
+
+/// Describes every invoice number used more than once, with how often, or `None` when
+/// they're all unique. Two invoices with one number would merge in a downstream ledger.
+pub fn duplicate_invoice_numbers(invoices: &[Invoice]) -> Option<String> {
+    let mut counts: HashMap<&str, usize> = HashMap::new();
+    for invoice in invoices {
+        *counts.entry(invoice.invoice_number.as_str()).or_insert(0) += 1;
+    }
+    let mut duplicates: Vec<(&str, usize)> =
+        counts.into_iter().filter(|(_, count)| *count > 1).collect();
+    if duplicates.is_empty() {
+        return None;
+    }
+    duplicates.sort_unstable();
+    let mut listed: Vec<String> = duplicates
+        .iter()
+        .take(MAX_DUPLICATES_LISTED)
+        .map(|(number, count)| format!("{} ({} times)", number, count))
+        .collect();
+    if duplicates.len() > MAX_DUPLICATES_LISTED {
+        listed.push(format!("{} more", duplicates.len() - MAX_DUPLICATES_LISTED));
+    }
+    Some(format!("Duplicate invoice numbers: {}", listed.join(", ")))
+}
+
+/// Reports invoices with fewer than `min_items` or more than `max_items` line items.
+pub fn item_count_bounds(invoices: &[Invoice], min_items: i32, max_items: i32) -> Vec<String> {
+    let bounds = min_items.max(0) as usize..=max_items.max(0) as usize;
+    invoices
+        .iter()
+        .filter(|invoice| !bounds.contains(&invoice.items.len()))
+        .map(|invoice| {
+            format!(
+                "Invoice {}: {} line items, outside the configured {} to {}",
+                invoice.invoice_number,
+                invoice.items.len(),
+                min_items,
+                max_items
+            )
+        })
+        .collect()
+}
+
+/// Turns the warnings from checking a result into one error listing them all, for configs
+/// with `strict` set. No warnings means the result stands.
+pub fn strict(warnings: &[String]) -> Result<(), String> {
+    match warnings.len() {
+        0 => Ok(()),
+        1 => Err(format!("Strict mode rejected the result: {}", warnings[0])),
+        n => Err(format!(
+            "Strict mode rejected the result with {} problems: {}",
+            n,
+            warnings.join("; ")
+        )),
+    }
+}
+
+/// The error a parsed result stands for, or `None` when it succeeded. The engine reports
+/// its own `error` as is, even alongside a status of "success"; a failing status without
+/// one is named so the run still fails with something to show.
+pub fn engine_error(status: &str, error: Option<&str>) -> Option<String> {
+    if let Some(error) = error {
+        return Some(error.to_string());
+    }
+    match status.to_ascii_lowercase().as_str() {
+        "success" | "ok" => None,
+        _ => Some(format!(
+            "Engine reported status '{}' without an error message",
+            status
+        )),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::SimulationResult;
+
+    fn invoices() -> Vec<Invoice> {
+        let result: SimulationResult =
+            serde_json::from_str(include_str!("../../backend/test/test_output.json")).unwrap();
+        result.invoices
+    }
+
+    #[test]
+    fn reports_one_duplicated_number_with_its_count() {
+        let mut invoices = invoices();
+        assert_eq!(duplicate_invoice_numbers(&invoices), None);
+        let repeated = invoices[2].invoice_number.clone();
+        invoices[7].invoice_number = repeated.clone();
+        invoices[9].invoice_number = repeated.clone();
+        assert_eq!(
+            duplicate_invoice_numbers(&invoices),
+            Some(format!("Duplicate invoice numbers: {} (3 times)", repeated))
+        );
+    }
+
+    #[test]
+    fn clean_invoices_pass_and_a_corrupted_total_is_flagged() {
+        let mut invoices = invoices();
+        // The fixture's last invoice was left with a short subtotal
+        let short = invoices.pop().unwrap();
+        assert_eq!(
+            verify_totals(&[short]),
+            ["Invoice GST/FY24-25/03583: subtotal 34891.72 does not match sum of line items 51182.41"]
+        );
+        assert!(verify_totals(&invoices).is_empty());
+
+        invoices[1].total = 100.0;
+        assert_eq!(
+            verify_totals(&invoices),
+            ["Invoice GST/FY24-25/03573: total 100.00 is less than subtotal 29356.94"]
+        );
+    }
+
+    #[test]
+    fn three_lines_that_each_round_up_drift_a_cent() {
+        // Each line is 10.005 rounded up, the subtotal 30.015 rounded up
+        let mut invoices: Vec<Invoice> = serde_json::from_value(serde_json::json!([{
+            "invoice_number": "INV-1",
+            "date": "2024-07-11",
+            "customer": "Asha Traders",
+            "items": [
+                {"name": "Pens", "quantity": 1, "rate": 10.01, "amount": 10.01},
+                {"name": "Pads", "quantity": 1, "rate": 10.01, "amount": 10.01},
+                {"name": "Ink", "quantity": 1, "rate": 10.01, "amount": 10.01},
+            ],
+            "subtotal": 30.02,
+            "total": 30.02,
+        }]))
+        .unwrap();
+        assert_eq!(
+            rounding_drift(&mut invoices, false, RoundingMode::default()),
+            ["Invoice INV-1: line items sum to 30.03, 0.01 off subtotal 30.02 from rounding"]
+        );
+        assert_eq!(
+            rounding_drift(&mut invoices, true, RoundingMode::default()),
+            ["Invoice INV-1: adjusted 'Pens' by -0.01 so line items sum to subtotal 30.02"]
+        );
+        assert_eq!(invoices[0].items[0].amount, 10.0);
+        assert!(rounding_drift(&mut invoices, false, RoundingMode::default()).is_empty());
+    }
+
+    // Lines of 100.00 at 18% and 200.00 at 5%, with `breakdown` as its tax_breakdown
+    fn taxed_invoice(breakdown: Value) -> Invoice {
+        serde_json::from_value(serde_json::json!({
+            "invoice_type": "GST",
+            "invoice_number": "INV-1",
+            "date": "2024-07-11",
+            "customer": "Acme",
+            "items": [
+                {"name": "Desk", "quantity": 1, "rate": 100.0, "amount": 100.0, "gst_percent": 18},
+                {"name": "Pens", "quantity": 4, "rate": 50.0, "amount": 200.0, "gst_percent": 5},
+            ],
+            "subtotal": 300.0,
+            "total": 328.0,
+            "tax_breakdown": breakdown,
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn flat_and_grouped_breakdowns_read_alike() {
+        let flat =
+            tax_groups(&serde_json::json!({"CGST": 9.0, "CGST_rate": 9, "SGST": 9.0})).unwrap();
+        assert_eq!(flat["all"].tax(), 18.0);
+        assert_eq!(flat["all"].taxable, None);
+        let grouped =
+            tax_groups(&serde_json::json!({"18%": {"taxable": 100.0, "cgst": 9.0, "sgst": 9.0}}))
+                .unwrap();
+        assert_eq!(grouped["18%"].rate, Some(18.0));
+        assert_eq!(grouped["18%"].taxable, Some(100.0));
+        assert_eq!(grouped["18%"].tax(), 18.0);
+        assert_eq!(tax_groups(&Value::from(3)), None);
+    }
+
+    #[test]
+    fn breakdowns_must_add_up_to_the_lines_and_total() {
+        let good = taxed_invoice(serde_json::json!({
+            "18%": {"taxable": 100.0, "cgst": 9.0, "sgst": 9.0},
+            "5%": {"taxable": 200.0, "cgst": 5.0, "sgst": 5.0},
+        }));
+        let flat = taxed_invoice(serde_json::json!({"CGST": 14.0, "SGST": 14.0}));
+        assert!(verify_tax_breakdown(&[good, flat]).is_empty());
+
+        let bad = taxed_invoice(serde_json::json!({
+            "18%": {"taxable": 100.0, "cgst": 9.0, "sgst": 9.0},
+            "5%": {"taxable": 150.0, "cgst": 5.0, "sgst": 5.0},
+        }));
+        assert_eq!(
+            verify_tax_breakdown(&[bad]),
+            [
+                "Invoice INV-1: taxable amounts in tax_breakdown sum to 250.00, but line items sum to 300.00",
+                "Invoice INV-1: 5% tax in tax_breakdown is 10.00, expected 7.50 on taxable 150.00",
+            ]
+        );
+        let short = taxed_invoice(serde_json::json!({"CGST": 14.0}));
+        assert_eq!(
+            verify_tax_breakdown(&[short]),
+            ["Invoice INV-1: total 328.00 does not match subtotal 300.00 plus tax_breakdown taxes 14.00"]
+        );
+    }
+
+    #[test]
+    fn flags_a_line_taxed_at_other_than_its_catalog_rate() {
+        // The engine bills Office Supplies Bundle at 12%
+        let catalog: Vec<CatalogItem> = serde_json::from_value(serde_json::json!([
+            {"sku": "SW1", "name": "Software Development", "price": 9000.0, "gst_percent": 18.0},
+            {"sku": "OFF1", "name": "Office Supplies Bundle", "price": 2000.0, "gst_percent": 5.0},
+        ]))
+        .unwrap();
+        let mut invoices = invoices();
+        let office_lines = invoices
+            .iter()
+            .flat_map(|invoice| &invoice.items)
+            .filter(|item| item.name == "Office Supplies Bundle")
+            .count();
+        let warnings = catalog_tax(&invoices, &catalog);
+        assert_eq!(warnings.len(), office_lines);
+        assert_eq!(
+            warnings[0],
+            "Invoice GST/FY24-25/03572: 'OFF1' has tax 233.54, but its catalog GST rate of 5% on 1946.13 is 97.31"
+        );
+
+        // A reported tax is checked as is, against the item its SKU names
+        let line = &mut invoices[0].items[0];
+        line.sku = Some("OFF1".to_string());
+        line.tax = Some(line.amount * 0.05);
+        let warnings = catalog_tax(&invoices[..1], &catalog);
+        // Only the invoice's own Office Supplies Bundle line is still off
+        assert_eq!(warnings.len(), 1);
+        assert!(warnings[0].contains("on 1946.13"), "{}", warnings[0]);
+        // Only GST invoices are checked
+        invoices[0].invoice_type = "Plain".to_string();
+        assert!(catalog_tax(&invoices[..1], &catalog).is_empty());
+    }
+
+    #[test]
+    fn strict_mode_fails_on_any_warning() {
+        // What the fixture's short subtotal and a duplicated number produce
+        let mut invoices = invoices();
+        invoices[1].invoice_number = invoices[0].invoice_number.clone();
+        let mut warnings = verify_totals(&invoices);
+        warnings.extend(duplicate_invoice_numbers(&invoices));
+        assert_eq!(warnings.len(), 2);
+        assert_eq!(
+            strict(&warnings).unwrap_err(),
+            format!(
+                "Strict mode rejected the result with 2 problems: {}; {}",
+                warnings[0], warnings[1]
+            )
+        );
+        assert_eq!(
+            strict(&warnings[..1]).unwrap_err(),
+            format!("Strict mode rejected the result: {}", warnings[0])
+        );
+        assert_eq!(strict(&[]), Ok(()));
+    }
+
+    #[test]
+    fn an_engine_error_wins_over_its_status() {
+        assert_eq!(engine_error("success", None), None);
+        assert_eq!(engine_error("OK", None), None);
+        // A success status with an error message
+        assert_eq!(
+            engine_error("ok", Some("catalog was empty")),
+            Some("catalog was empty".to_string())
+        );
+        // A failing status with a message, and without one
+        assert_eq!(
+            engine_error("error", Some("catalog was empty")),
+            Some("catalog was empty".to_string())
+        );
+        assert_eq!(
+            engine_error("crashed", None),
+            Some("Engine reported status 'crashed' without an error message".to_string())
+        );
+    }
+}